@@ -2,21 +2,35 @@ use std::env;
 
 use askama::Template;
 use axum::{
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse},
     routing::get,
     Json, Router,
 };
 use axum_extra::extract::CookieJar;
 use serde::Serialize;
-use tower_http::services::ServeDir;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::TraceLayer,
+};
+
+use telemetry::{init_tracing, make_span, trace_context_headers};
+
+mod telemetry;
 
 #[tokio::main]
 async fn main() {
+    let _tracing = init_tracing().expect("Failed to initialize tracing");
+
     let app = Router::new()
         .nest_service("/assets", ServeDir::new("assets"))
         .route("/", get(root))
-        .route("/protected", get(protected));
+        .route("/protected", get(protected))
+        .layer(TraceLayer::new_for_http().make_span_with(make_span))
+        // Keeps the caller's X-Request-Id or generates one, and returns it on the response
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
 
@@ -46,7 +60,7 @@ async fn root() -> impl IntoResponse {
     Html(template.render().unwrap())
 }
 
-async fn protected(jar: CookieJar) -> impl IntoResponse {
+async fn protected(jar: CookieJar, headers: HeaderMap) -> impl IntoResponse {
     let jwt_cookie = match jar.get("jwt") {
         Some(cookie) => cookie,
         None => {
@@ -63,7 +77,18 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
     let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
     let url = format!("http://{}:3000/verify-token", auth_hostname);
 
-    let response = match api_client.post(&url).json(&verify_token_body).send().await {
+    // Carries the trace and request id on to auth-service so the request can be followed across
+    // both services
+    let mut request = api_client.post(&url).headers(trace_context_headers());
+    if let Some(request_id) = headers.get(REQUEST_ID_HEADER).and_then(|value| value.to_str().ok()) {
+        request = request.header(REQUEST_ID_HEADER, request_id);
+    }
+
+    let response = match request
+        .json(&verify_token_body)
+        .send()
+        .await
+    {
         Ok(response) => response,
         Err(_) => {
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
@@ -71,7 +96,9 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
     };
 
     match response.status() {
-        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
+        reqwest::StatusCode::UNAUTHORIZED
+        | reqwest::StatusCode::BAD_REQUEST
+        | reqwest::StatusCode::FORBIDDEN => {
             StatusCode::UNAUTHORIZED.into_response()
         }
         reqwest::StatusCode::OK => Json(ProtectedRouteResponse {
@@ -82,6 +109,8 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
     }
 }
 
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
 #[derive(Serialize)]
 pub struct ProtectedRouteResponse {
     pub img_url: String,
@@ -0,0 +1,111 @@
+use std::{env, error::Error};
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, Request},
+};
+use opentelemetry::{
+    propagation::{Extractor, Injector, TextMapPropagator},
+    trace::TracerProvider,
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+// Keeps tracing set up while alive, dropping it flushes the spans not exported yet
+pub struct TracingGuard {
+    tracer_provider: SdkTracerProvider,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            eprintln!("Failed to flush traces: {}", e);
+        }
+    }
+}
+
+// Logs to stdout, and exports spans to the OTLP/HTTP collector at OTEL_EXPORTER_OTLP_ENDPOINT
+// when it is set
+pub fn init_tracing() -> Result<TracingGuard, Box<dyn Error>> {
+    let service_name = env::var("OTEL_SERVICE_NAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("app-service".to_owned());
+
+    let resource = Resource::builder().with_service_name(service_name.clone()).build();
+    let mut builder = SdkTracerProvider::builder().with_resource(resource);
+
+    if let Some(endpoint) = env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|endpoint| !endpoint.is_empty()) {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()?;
+        builder = builder.with_batch_exporter(exporter);
+    }
+    let tracer_provider = builder.build();
+
+    let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
+    let otel_layer = tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer(service_name));
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(fmt::layer().compact())
+        .with(otel_layer)
+        .init();
+
+    Ok(TracingGuard { tracer_provider })
+}
+
+// Continues the caller's trace when it sent a traceparent header
+pub fn make_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(crate::REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "[REQUEST]",
+        method = tracing::field::display(request.method()),
+        uri = tracing::field::display(request.uri()),
+        request_id = request_id,
+    );
+    span.set_parent(TraceContextPropagator::new().extract(&HeaderExtractor(request.headers())));
+    span
+}
+
+// traceparent and tracestate headers continuing the current span's trace, for calls to auth-service
+pub fn trace_context_headers() -> reqwest::header::HeaderMap {
+    let mut headers = reqwest::header::HeaderMap::new();
+    let context = Span::current().context();
+    TraceContextPropagator::new().inject_context(&context, &mut HeaderInjector(&mut headers));
+    headers
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// reqwest is still on http 0.2, so its header map is a different type to axum's
+struct HeaderInjector<'a>(&'a mut reqwest::header::HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,3 @@
+ALTER TABLE users
+   ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'active'
+   CHECK (status IN ('active', 'suspended', 'pending'));
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS audit_log(
+   id UUID NOT NULL PRIMARY KEY,
+   occurred_at TIMESTAMPTZ NOT NULL,
+   event_type TEXT NOT NULL,
+   outcome TEXT NOT NULL,
+   actor TEXT,
+   ip TEXT,
+   user_agent TEXT,
+   request_id TEXT,
+   detail TEXT
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_actor_occurred_at_idx ON audit_log (actor, occurred_at);
+CREATE INDEX IF NOT EXISTS audit_log_occurred_at_idx ON audit_log (occurred_at);
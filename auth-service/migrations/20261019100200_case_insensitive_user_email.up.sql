@@ -0,0 +1,30 @@
+-- Addresses that only differ in case can't each keep an account once emails are case-insensitive, and
+-- which one to keep is up to the operator. Stop here and list them; to fix up, delete or rename all
+-- but one account of every listed group, e.g.
+--   DELETE FROM users WHERE email = 'Bob@Example.com';
+-- then start the service again so the migrations run.
+DO $$
+DECLARE
+    conflicts TEXT;
+BEGIN
+    SELECT string_agg(addresses, '; ')
+      INTO conflicts
+      FROM (SELECT string_agg(email, ', ' ORDER BY email) AS addresses
+              FROM users
+             GROUP BY lower(email)
+            HAVING count(*) > 1) AS duplicates;
+
+    IF conflicts IS NOT NULL THEN
+        RAISE EXCEPTION 'accounts with emails that only differ in case: %', conflicts
+            USING HINT = 'Keep one account per address, delete or rename the others and run the migrations again';
+    END IF;
+END $$;
+
+-- New addresses are stored with a lower-case domain, bring existing ones in line
+UPDATE users
+   SET email = left(email, length(email) - position('@' IN reverse(email)) + 1)
+            || lower(right(email, position('@' IN reverse(email)) - 1))
+ WHERE position('@' IN email) > 0;
+
+-- Serves the case-insensitive lookups and prevents the same address being registered twice
+CREATE UNIQUE INDEX IF NOT EXISTS users_lower_email_idx ON users (lower(email));
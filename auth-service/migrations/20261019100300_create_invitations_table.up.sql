@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS invitations(
+   code TEXT NOT NULL PRIMARY KEY,
+   inviter TEXT NOT NULL,
+   email TEXT,
+   expires_at TIMESTAMPTZ NOT NULL,
+   max_uses INTEGER NOT NULL CHECK (max_uses > 0),
+   uses INTEGER NOT NULL DEFAULT 0
+);
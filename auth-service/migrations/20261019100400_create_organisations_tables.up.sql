@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS organisations(
+   id UUID NOT NULL PRIMARY KEY,
+   slug TEXT NOT NULL UNIQUE,
+   name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS memberships(
+   organisation_id UUID NOT NULL REFERENCES organisations (id) ON DELETE CASCADE,
+   email TEXT NOT NULL REFERENCES users (email) ON DELETE CASCADE ON UPDATE CASCADE,
+   role TEXT NOT NULL DEFAULT 'member' CHECK (role IN ('member', 'admin')),
+   PRIMARY KEY (organisation_id, email)
+);
+
+CREATE INDEX IF NOT EXISTS memberships_lower_email_idx ON memberships (lower(email));
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS audit_log_lower_actor_occurred_at_idx;
+CREATE INDEX IF NOT EXISTS audit_log_actor_occurred_at_idx ON audit_log (actor, occurred_at);
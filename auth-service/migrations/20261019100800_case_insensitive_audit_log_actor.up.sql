@@ -0,0 +1,3 @@
+-- Audit events are queried by actor regardless of case
+DROP INDEX IF EXISTS audit_log_actor_occurred_at_idx;
+CREATE INDEX IF NOT EXISTS audit_log_lower_actor_occurred_at_idx ON audit_log (lower(actor), occurred_at);
@@ -1,12 +1,26 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{data_stores::{BannedTokenStore, TwoFACodeStore, UserStore}, email_client::EmailClient};
+use crate::{
+    domain::{data_stores::{AuditLogStore, BannedTokenStore, BreachedPasswordStore, EmailOutboxStore, InvitationStore, TwoFACodeStore, UserStore}, email_client::EmailClient},
+    domain::email_domain_policy::EmailDomainPolicy,
+    services::{data_stores::DevMailbox, webhook_dispatcher::WebhookDispatcher},
+    utils::{metrics::Metrics, settings::Settings},
+};
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 pub type EmailClientType = Arc<RwLock<dyn EmailClient + Send + Sync>>;
+pub type AuditLogStoreType = Arc<RwLock<dyn AuditLogStore + Send + Sync>>;
+pub type BreachedPasswordStoreType = Arc<RwLock<dyn BreachedPasswordStore + Send + Sync>>;
+pub type InvitationStoreType = Arc<RwLock<dyn InvitationStore + Send + Sync>>;
+pub type EmailOutboxStoreType = Arc<RwLock<dyn EmailOutboxStore + Send + Sync>>;
+pub type DevMailboxType = Arc<RwLock<DevMailbox>>;
+pub type WebhookDispatcherType = Arc<WebhookDispatcher>;
+pub type EmailDomainPolicyType = Arc<EmailDomainPolicy>;
+pub type SettingsType = Arc<Settings>;
+pub type MetricsType = Arc<Metrics>;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -14,15 +28,49 @@ pub struct AppState {
     pub token_store: BannedTokenStoreType, 
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub audit_log_store: AuditLogStoreType,
+    pub webhook_dispatcher: WebhookDispatcherType,
+    pub breached_password_store: BreachedPasswordStoreType,
+    pub email_domain_policy: EmailDomainPolicyType,
+    pub invitation_store: InvitationStoreType,
+    pub email_outbox: EmailOutboxStoreType,
+    // Only set with the dev email provider, exposes the sent emails under /dev/inbox
+    pub dev_mailbox: Option<DevMailboxType>,
+    pub settings: SettingsType,
+    pub metrics: MetricsType,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType, 
         token_store: BannedTokenStoreType, 
         two_fa_code_store: TwoFACodeStoreType,
-        email_client: EmailClientType
+        email_client: EmailClientType,
+        audit_log_store: AuditLogStoreType,
+        webhook_dispatcher: WebhookDispatcherType,
+        breached_password_store: BreachedPasswordStoreType,
+        email_domain_policy: EmailDomainPolicyType,
+        invitation_store: InvitationStoreType,
+        email_outbox: EmailOutboxStoreType,
+        dev_mailbox: Option<DevMailboxType>,
+        settings: SettingsType,
+        metrics: MetricsType,
     ) -> Self {
-        Self { user_store, token_store, two_fa_code_store, email_client }
+        Self {
+            user_store,
+            token_store,
+            two_fa_code_store,
+            email_client,
+            audit_log_store,
+            webhook_dispatcher,
+            breached_password_store,
+            email_domain_policy,
+            invitation_store,
+            email_outbox,
+            dev_mailbox,
+            settings,
+            metrics,
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[async_trait::async_trait]
+pub trait AuditLogStore {
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError>;
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>, AuditLogStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditLogStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub event_type: AuditEventType,
+    pub outcome: AuditOutcome,
+    pub actor: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub detail: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(event_type: AuditEventType, outcome: AuditOutcome) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            occurred_at: Utc::now(),
+            event_type,
+            outcome,
+            actor: None,
+            ip: None,
+            user_agent: None,
+            request_id: None,
+            detail: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventType {
+    Signup,
+    Login,
+    Verify2FA,
+    Logout,
+    VerifyToken,
+    DeleteAccount,
+    CreateInvitation,
+    RevokeInvitation,
+    CreateOrganisation,
+    SetMembership,
+    SetLocale,
+}
+
+impl AuditEventType {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input {
+            "signup" => Ok(Self::Signup),
+            "login" => Ok(Self::Login),
+            "verify_2fa" => Ok(Self::Verify2FA),
+            "logout" => Ok(Self::Logout),
+            "verify_token" => Ok(Self::VerifyToken),
+            "delete_account" => Ok(Self::DeleteAccount),
+            "create_invitation" => Ok(Self::CreateInvitation),
+            "revoke_invitation" => Ok(Self::RevokeInvitation),
+            "create_organisation" => Ok(Self::CreateOrganisation),
+            "set_membership" => Ok(Self::SetMembership),
+            "set_locale" => Ok(Self::SetLocale),
+            _ => Err(eyre!(format!("Not valid audit event type: {}", input))),
+        }
+    }
+}
+
+impl AsRef<str> for AuditEventType {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Signup => "signup",
+            Self::Login => "login",
+            Self::Verify2FA => "verify_2fa",
+            Self::Logout => "logout",
+            Self::VerifyToken => "verify_token",
+            Self::DeleteAccount => "delete_account",
+            Self::CreateInvitation => "create_invitation",
+            Self::RevokeInvitation => "revoke_invitation",
+            Self::CreateOrganisation => "create_organisation",
+            Self::SetMembership => "set_membership",
+            Self::SetLocale => "set_locale",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+    TwoFactorRequired,
+}
+
+impl AuditOutcome {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input {
+            "success" => Ok(Self::Success),
+            "failure" => Ok(Self::Failure),
+            "two_factor_required" => Ok(Self::TwoFactorRequired),
+            _ => Err(eyre!(format!("Not valid audit outcome: {}", input))),
+        }
+    }
+}
+
+impl AsRef<str> for AuditOutcome {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "failure",
+            Self::TwoFactorRequired => "two_factor_required",
+        }
+    }
+}
+
+// Events are returned newest first; `from` is inclusive and `to` is exclusive. `actor` ignores case,
+// like the email lookups of the user store.
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: i64,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        self.actor.iter().all(|actor| event.actor.as_ref().is_some_and(|a| a.eq_ignore_ascii_case(actor)))
+            && self.from.iter().all(|from| event.occurred_at >= *from)
+            && self.to.iter().all(|to| event.occurred_at < *to)
+    }
+}
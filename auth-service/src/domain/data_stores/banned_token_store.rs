@@ -12,4 +12,8 @@ pub enum BannedTokenStoreError {
 pub trait BannedTokenStore {
     async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
     async fn contains_token(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError>;
+    // Probed by /health/ready, stores without a backing service are always healthy
+    async fn health_check(&self) -> Result<(), BannedTokenStoreError> {
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+use color_eyre::eyre::Report;
+use secrecy::ExposeSecret;
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+
+use crate::domain::password::Password;
+
+#[derive(Debug, Error)]
+pub enum BreachedPasswordStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[async_trait::async_trait]
+pub trait BreachedPasswordStore {
+    async fn contains_password(&self, password: &Password) -> Result<bool, BreachedPasswordStoreError>;
+}
+
+// Breach corpora such as Have I Been Pwned identify passwords by their upper-case hex SHA-1
+pub fn password_sha1(password: &Password) -> String {
+    hex::encode_upper(Sha1::digest(password.as_ref().expose_secret().as_bytes()))
+}
@@ -0,0 +1,196 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report, Result};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::{email::Email, email_client::EmailMessage};
+
+// Emails are written here by the handlers and delivered by the outbox worker, so a slow or
+// failing email provider never holds up a request
+#[async_trait::async_trait]
+pub trait EmailOutboxStore {
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError>;
+    // Hands out pending emails that are due and counts the attempt. Claimed emails are not due
+    // again before `lease_until`, so concurrent workers never send the same email twice.
+    async fn claim_due(&mut self, limit: i64, lease_until: DateTime<Utc>) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError>;
+    async fn mark_sent(&mut self, id: Uuid) -> Result<(), EmailOutboxStoreError>;
+    async fn schedule_retry(&mut self, id: Uuid, error: &str, retry_at: DateTime<Utc>) -> Result<(), EmailOutboxStoreError>;
+    // Gives up on the email, its subject, recipient and error stay in the outbox for inspection
+    async fn mark_dead(&mut self, id: Uuid, error: &str) -> Result<(), EmailOutboxStoreError>;
+    async fn get_email(&self, id: Uuid) -> Result<OutboxEmail, EmailOutboxStoreError>;
+    async fn query(&self, query: &OutboxQuery) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailOutboxStoreError {
+    #[error("Email not found")]
+    EmailNotFound,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailOutboxStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::EmailNotFound, Self::EmailNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEmail {
+    pub id: Uuid,
+    pub recipient: Email,
+    // The bodies are dropped once the email is sent or dead-lettered, they carry login codes and
+    // invitation links
+    pub message: EmailMessage,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    // Not worth delivering after this, such as once the login code in it is no longer valid
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl OutboxEmail {
+    pub fn new(recipient: Email, message: EmailMessage) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            recipient,
+            message,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            next_attempt_at: now,
+            sent_at: None,
+            expires_at: None,
+        }
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.status == DeliveryStatus::Pending && self.next_attempt_at <= now
+    }
+
+    pub fn is_expired(&self, at: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= at)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Sent,
+    // Ran out of attempts
+    Dead,
+}
+
+impl DeliveryStatus {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input {
+            "pending" => Ok(Self::Pending),
+            "sent" => Ok(Self::Sent),
+            "dead" => Ok(Self::Dead),
+            _ => Err(eyre!(format!("Not valid delivery status: {}", input))),
+        }
+    }
+}
+
+impl AsRef<str> for DeliveryStatus {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+// Emails are returned newest first
+#[derive(Debug, Clone, Default)]
+pub struct OutboxQuery {
+    pub recipient: Option<Email>,
+    pub status: Option<DeliveryStatus>,
+    pub limit: i64,
+}
+
+impl OutboxQuery {
+    pub fn matches(&self, email: &OutboxEmail) -> bool {
+        self.recipient.iter().all(|recipient| email.recipient == *recipient)
+            && self.status.iter().all(|status| email.status == *status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email(input: &str) -> Email {
+        Email::parse(Secret::new(input.to_owned())).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Subject".to_owned(),
+            html_body: "<p>Body</p>".to_owned(),
+            text_body: "Body".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_delivery_status_roundtrip() {
+        for status in [DeliveryStatus::Pending, DeliveryStatus::Sent, DeliveryStatus::Dead] {
+            assert_eq!(DeliveryStatus::parse(status.as_ref()).unwrap(), status);
+        }
+        assert!(DeliveryStatus::parse("queued").is_err());
+    }
+
+    #[test]
+    fn test_is_due() {
+        let email = OutboxEmail::new(email("user@example.com"), message());
+        let now = Utc::now();
+        assert!(email.is_due(now));
+
+        let later = OutboxEmail { next_attempt_at: now + chrono::Duration::minutes(1), ..email.clone() };
+        assert!(!later.is_due(now));
+
+        let sent = OutboxEmail { status: DeliveryStatus::Sent, ..email };
+        assert!(!sent.is_due(now));
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let now = Utc::now();
+        let email = OutboxEmail::new(email("user@example.com"), message());
+        assert!(!email.is_expired(now));
+
+        let expiring = OutboxEmail { expires_at: Some(now + chrono::Duration::minutes(10)), ..email };
+        assert!(!expiring.is_expired(now));
+        assert!(expiring.is_expired(now + chrono::Duration::minutes(10)));
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let outbox_email = OutboxEmail::new(email("user@example.com"), message());
+
+        assert!(OutboxQuery::default().matches(&outbox_email));
+        assert!(OutboxQuery {
+            recipient: Some(email("USER@example.com")),
+            status: Some(DeliveryStatus::Pending),
+            limit: 10,
+        }
+        .matches(&outbox_email));
+        assert!(!OutboxQuery { status: Some(DeliveryStatus::Dead), ..Default::default() }.matches(&outbox_email));
+        assert!(!OutboxQuery { recipient: Some(email("other@example.com")), ..Default::default() }.matches(&outbox_email));
+    }
+}
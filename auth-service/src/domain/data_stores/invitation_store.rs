@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::{ExposeSecret, Secret};
+use thiserror::Error;
+
+use crate::domain::email::Email;
+
+#[async_trait::async_trait]
+pub trait InvitationStore {
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), InvitationStoreError>;
+    async fn get_invitation(&self, code: &InvitationCode) -> Result<Invitation, InvitationStoreError>;
+    // Checks that `email` may still use the invitation and counts the use, in one step
+    async fn redeem_invitation(&mut self, code: &InvitationCode, email: &Email) -> Result<(), InvitationStoreError>;
+    // Gives back a use counted by `redeem_invitation` when the signup fails afterwards
+    async fn release_invitation(&mut self, code: &InvitationCode) -> Result<(), InvitationStoreError>;
+    async fn revoke_invitation(&mut self, code: &InvitationCode) -> Result<(), InvitationStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum InvitationStoreError {
+    #[error("Invitation not found")]
+    InvitationNotFound,
+
+    // Expired, used up or bound to a different email address
+    #[error("Invitation can not be used")]
+    InvitationNotUsable,
+
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for InvitationStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::InvitationNotFound, Self::InvitationNotFound)
+                | (Self::InvitationNotUsable, Self::InvitationNotUsable)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invitation {
+    pub code: InvitationCode,
+    pub inviter: Email,
+    // When set, only this address may sign up with the invitation
+    pub email: Option<Email>,
+    pub expires_at: DateTime<Utc>,
+    pub max_uses: i32,
+    pub uses: i32,
+}
+
+impl Invitation {
+    pub fn new(inviter: Email, email: Option<Email>, expires_at: DateTime<Utc>, max_uses: i32) -> Self {
+        Self {
+            code: InvitationCode::default(),
+            inviter,
+            email,
+            expires_at,
+            max_uses,
+            uses: 0,
+        }
+    }
+
+    pub fn is_usable_by(&self, email: &Email) -> bool {
+        self.expires_at > Utc::now()
+            && self.uses < self.max_uses
+            && self.email.iter().all(|bound| bound == email)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvitationCode(Secret<String>);
+
+impl InvitationCode {
+    pub fn parse(code: Secret<String>) -> Result<Self> {
+        let valid = code.expose_secret().len() == INVITATION_CODE_LENGTH
+            && code.expose_secret().chars().all(|c| c.is_ascii_alphanumeric());
+
+        if valid {
+            Ok(Self(code))
+        } else {
+            Err(eyre!("Invalid invitation code"))
+        }
+    }
+}
+
+impl Default for InvitationCode {
+    fn default() -> Self {
+        let code: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(INVITATION_CODE_LENGTH)
+            .map(char::from)
+            .collect();
+        Self(Secret::new(code))
+    }
+}
+
+impl AsRef<Secret<String>> for InvitationCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl PartialEq for InvitationCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+const INVITATION_CODE_LENGTH: usize = 24;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(input: &str) -> Email {
+        Email::parse(Secret::new(input.to_owned())).unwrap()
+    }
+
+    #[test]
+    fn test_invitation_code_roundtrip() {
+        let code = InvitationCode::default();
+        let parsed = InvitationCode::parse(code.as_ref().clone()).unwrap();
+
+        assert_eq!(code, parsed);
+        assert!(InvitationCode::parse(Secret::new("too-short".to_owned())).is_err());
+    }
+
+    #[test]
+    fn test_is_usable_by() {
+        let inviter = email("admin@example.com");
+        let invitee = email("invitee@example.com");
+        let in_an_hour = Utc::now() + chrono::Duration::hours(1);
+
+        let open = Invitation::new(inviter.clone(), None, in_an_hour, 1);
+        assert!(open.is_usable_by(&invitee));
+
+        let bound = Invitation::new(inviter.clone(), Some(invitee.clone()), in_an_hour, 1);
+        assert!(bound.is_usable_by(&email("INVITEE@example.com")));
+        assert!(!bound.is_usable_by(&email("other@example.com")));
+
+        let used_up = Invitation { uses: 1, ..open.clone() };
+        assert!(!used_up.is_usable_by(&invitee));
+
+        let expired = Invitation { expires_at: Utc::now() - chrono::Duration::seconds(1), ..open };
+        assert!(!expired.is_usable_by(&invitee));
+    }
+}
@@ -1,7 +1,15 @@
 mod user_store;
 mod banned_token_store;
 mod two_fa_code_store;
+mod audit_log_store;
+mod breached_password_store;
+mod invitation_store;
+mod email_outbox_store;
 
 pub use user_store::*;
 pub use banned_token_store::*;
-pub use two_fa_code_store::*;
\ No newline at end of file
+pub use two_fa_code_store::*;
+pub use audit_log_store::*;
+pub use breached_password_store::*;
+pub use invitation_store::*;
+pub use email_outbox_store::*;
\ No newline at end of file
@@ -12,6 +12,10 @@ pub trait TwoFACodeStore {
     async fn add_code(&mut self, email: Email, login_attempt_id: LoginAttemptId, code: TwoFACode) -> Result<(), TwoFACodeStoreError>;
     async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
     async fn get_code(&self, email: &Email) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    // Probed by /health/ready, stores without a backing service are always healthy
+    async fn health_check(&self) -> Result<(), TwoFACodeStoreError> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -1,8 +1,11 @@
 use color_eyre::eyre::Report;
 use thiserror::Error;
+use uuid::Uuid;
 
-use crate::domain::user::User;
+use crate::domain::user::{AccountStatus, User, UserRole};
 use crate::domain::email::Email;
+use crate::domain::locale::Locale;
+use crate::domain::organisation::{Membership, OrgRole, Organisation};
 use crate::domain::password::Password;
 
 #[async_trait::async_trait]
@@ -11,6 +14,18 @@ pub trait UserStore {
     async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError>;
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
     async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    async fn update_status(&mut self, email: &Email, status: AccountStatus) -> Result<(), UserStoreError>;
+    async fn update_role(&mut self, email: &Email, role: UserRole) -> Result<(), UserStoreError>;
+    async fn update_locale(&mut self, email: &Email, locale: Option<Locale>) -> Result<(), UserStoreError>;
+    async fn add_organisation(&mut self, organisation: Organisation) -> Result<(), UserStoreError>;
+    async fn get_organisation(&self, slug: &str) -> Result<Organisation, UserStoreError>;
+    // Adds the user to the organisation, or changes their role if they already are a member
+    async fn set_membership(&mut self, email: &Email, organisation_id: Uuid, role: OrgRole) -> Result<(), UserStoreError>;
+    async fn get_memberships(&self, email: &Email) -> Result<Vec<Membership>, UserStoreError>;
+    // Probed by /health/ready, stores without a backing service are always healthy
+    async fn health_check(&self) -> Result<(), UserStoreError> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, Error)]
@@ -24,6 +39,12 @@ pub enum UserStoreError {
     #[error("Invalid credentials")]
     InvalidCredentials,
 
+    #[error("Organisation already exists")]
+    OrganisationAlreadyExists,
+
+    #[error("Organisation not found")]
+    OrganisationNotFound,
+
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -35,6 +56,8 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::OrganisationAlreadyExists, Self::OrganisationAlreadyExists)
+                | (Self::OrganisationNotFound, Self::OrganisationNotFound)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -6,33 +6,124 @@ use secrecy::{ExposeSecret, Secret};
 #[derive(Debug, Clone)]
 pub struct Email(Secret<String>);
 
+// The domain is always stored in lower case, and local-parts are treated case-insensitively
+// as well, since practically every mail provider does so
 impl PartialEq for Email {
     fn eq(&self, other: &Self) -> bool {
-        self.0.expose_secret() == other.0.expose_secret()
+        self.0.expose_secret().eq_ignore_ascii_case(other.0.expose_secret())
     }
 }
 
 impl Hash for Email {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.0.expose_secret().hash(state);
+        self.0.expose_secret().to_ascii_lowercase().hash(state);
     }
 }
 
 impl Eq for Email {}
 
-fn validate_email(input: &str) -> bool {
-    input.contains('@')
+// Validates an RFC 5322 addr-spec and returns it with the domain converted to lower-case
+// ASCII (punycode for internationalised domains)
+fn canonicalize_email(input: &str) -> Option<String> {
+    let (local_part, domain) = input.rsplit_once('@')?;
+
+    if !is_valid_local_part(local_part) {
+        return None;
+    }
+
+    let domain = canonicalize_domain(domain)?;
+    let email = format!("{}@{}", local_part, domain);
+
+    (email.len() <= MAX_EMAIL_LENGTH).then_some(email)
+}
+
+fn is_valid_local_part(local_part: &str) -> bool {
+    if local_part.is_empty() || local_part.len() > MAX_LOCAL_PART_LENGTH {
+        return false;
+    }
+
+    if let Some(quoted) = local_part.strip_prefix('"').and_then(|l| l.strip_suffix('"')) {
+        return is_valid_quoted_string(quoted);
+    }
+
+    // dot-atom: atoms separated by single dots, no leading or trailing dot
+    local_part
+        .split('.')
+        .all(|atom| !atom.is_empty() && atom.chars().all(is_atext))
+}
+
+fn is_atext(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~-".contains(c)
+}
+
+fn is_valid_quoted_string(content: &str) -> bool {
+    let is_printable = |c: char| c == ' ' || c == '\t' || c.is_ascii_graphic();
+
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if !chars.next().is_some_and(is_printable) {
+                    return false;
+                }
+            }
+            '"' => return false,
+            c if is_printable(c) => {}
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+fn canonicalize_domain(domain: &str) -> Option<String> {
+    // Lower-cases and converts any Unicode labels to punycode
+    let domain = idna::domain_to_ascii(domain).ok()?;
+    let labels: Vec<&str> = domain.split('.').collect();
+
+    if domain.len() > MAX_DOMAIN_LENGTH || labels.len() < 2 {
+        return None;
+    }
+
+    let valid_labels = labels.iter().all(|label| {
+        !label.is_empty()
+            && label.len() <= MAX_LABEL_LENGTH
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    });
+
+    // An all-numeric top-level label would make this an IP address, which needs a domain-literal
+    let valid_tld = labels
+        .last()
+        .is_some_and(|tld| !tld.bytes().all(|b| b.is_ascii_digit()));
+
+    (valid_labels && valid_tld).then_some(domain)
 }
 
 impl Email {
     pub fn parse(input: Secret<String>) -> Result<Self> {
-        if !validate_email(input.expose_secret()) {
-            return Err(eyre!(format!("Not valid email: {}", input.expose_secret())));
-        } 
-        Ok(Self(input))
+        let email = canonicalize_email(input.expose_secret())
+            .ok_or_else(|| eyre!(format!("Not valid email: {}", input.expose_secret())))?;
+
+        Ok(Self(Secret::new(email)))
+    }
+
+    // Always lower-case ASCII, see `canonicalize_domain`
+    pub fn domain(&self) -> &str {
+        self.0
+            .expose_secret()
+            .rsplit_once('@')
+            .map_or("", |(_, domain)| domain)
     }
 }
 
+// RFC 5321 limits, in octets. The overall limit is the 256 octet path minus its angle brackets.
+const MAX_EMAIL_LENGTH: usize = 254;
+const MAX_LOCAL_PART_LENGTH: usize = 64;
+const MAX_DOMAIN_LENGTH: usize = 253;
+const MAX_LABEL_LENGTH: usize = 63;
+
 impl AsRef<Secret<String>> for Email {
     fn as_ref(&self) -> &Secret<String> {
         &self.0
@@ -69,6 +160,70 @@ mod tests {
         assert!(results.iter().all(|r| r.is_err()))
     }
 
+    #[test]
+    fn should_accept_rfc_local_parts() {
+        let results = [
+            Email::parse(Secret::new("first.last+tag@value.com".to_string())),
+            Email::parse(Secret::new("o'brien@value.com".to_string())),
+            Email::parse(Secret::new("\"john doe\"@value.com".to_string())),
+            Email::parse(Secret::new("\"quoted@at\"@value.com".to_string())),
+        ];
+
+        assert!(results.iter().all(|r| r.is_ok()))
+    }
+
+    #[test]
+    fn should_reject_invalid_addr_specs() {
+        let results = [
+            Email::parse(Secret::new("@value.com".to_string())),
+            Email::parse(Secret::new(".some@value.com".to_string())),
+            Email::parse(Secret::new("some..other@value.com".to_string())),
+            Email::parse(Secret::new("some other@value.com".to_string())),
+            Email::parse(Secret::new("some@value".to_string())),
+            Email::parse(Secret::new("some@value..com".to_string())),
+            Email::parse(Secret::new("some@-value.com".to_string())),
+            Email::parse(Secret::new("some@value-.com".to_string())),
+            Email::parse(Secret::new("some@val_ue.com".to_string())),
+            Email::parse(Secret::new("some@127.0.0.1".to_string())),
+        ];
+
+        assert!(results.iter().all(|r| r.is_err()))
+    }
+
+    #[test]
+    fn should_enforce_length_limits() {
+        let label = "a".repeat(63);
+        let long_domain = format!("{}.{}.{}.com", label, label, label);
+
+        assert!(Email::parse(Secret::new(format!("{}@value.com", "a".repeat(64)))).is_ok());
+        assert!(Email::parse(Secret::new(format!("{}@value.com", "a".repeat(65)))).is_err());
+        assert!(Email::parse(Secret::new(format!("some@{}.com", "a".repeat(64)))).is_err());
+        assert!(Email::parse(Secret::new(format!("some@{}", long_domain))).is_ok());
+        // 64 + 1 + 193 octets is over the 254 octet limit for the whole address
+        assert!(Email::parse(Secret::new(format!("{}@{}", "a".repeat(64), long_domain))).is_err());
+    }
+
+    #[test]
+    fn should_canonicalize_domain() {
+        let email = Email::parse(Secret::new("Some.User@Value.COM".to_string())).unwrap();
+        assert_eq!(email.as_ref().expose_secret(), "Some.User@value.com");
+
+        let email = Email::parse(Secret::new("user@bücher.example".to_string())).unwrap();
+        assert_eq!(email.as_ref().expose_secret(), "user@xn--bcher-kva.example");
+    }
+
+    #[test]
+    fn should_compare_case_insensitively() {
+        let first = Email::parse(Secret::new("Some.User@Value.com".to_string())).unwrap();
+        let second = Email::parse(Secret::new("some.user@value.com".to_string())).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(
+            std::collections::HashSet::from([first]),
+            std::collections::HashSet::from([second])
+        );
+    }
+
     #[derive(Debug, Clone)]
     struct ValidEmailFixture(pub String);
 
@@ -1,8 +1,83 @@
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
+use serde::Deserialize;
 
 use super::email::Email;
 
 #[async_trait::async_trait]
 pub trait EmailClient {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()>;
-}
\ No newline at end of file
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()>;
+    // Checks the provider can be reached without sending anything
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// A rendered email, every message carries both an HTML and a plain-text part
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailMessage {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+// Which EmailClient the service sends through, picked with `email.providers`. Listing more than
+// one provider fails over between them in order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailProvider {
+    #[default]
+    Postmark,
+    Smtp,
+    // Keeps emails in memory and on disk instead of sending them, for local development
+    Dev,
+}
+
+impl EmailProvider {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "postmark" => Ok(Self::Postmark),
+            "smtp" => Ok(Self::Smtp),
+            "dev" => Ok(Self::Dev),
+            _ => Err(eyre!(format!("Not supported email provider: {}", input))),
+        }
+    }
+
+    // An ordered list such as "postmark,smtp", each provider may only appear once
+    pub fn parse_list(input: &str) -> Result<Vec<Self>> {
+        let mut providers = Vec::new();
+        for provider in input.split(',') {
+            let provider = Self::parse(provider)?;
+            if providers.contains(&provider) {
+                return Err(eyre!(format!("Email provider listed twice: {}", provider.as_ref())));
+            }
+            providers.push(provider);
+        }
+        Ok(providers)
+    }
+}
+
+impl AsRef<str> for EmailProvider {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Postmark => "postmark",
+            Self::Smtp => "smtp",
+            Self::Dev => "dev",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(EmailProvider::parse_list("postmark").unwrap(), vec![EmailProvider::Postmark]);
+        assert_eq!(
+            EmailProvider::parse_list("smtp, Postmark").unwrap(),
+            vec![EmailProvider::Smtp, EmailProvider::Postmark]
+        );
+        assert!(EmailProvider::parse_list("postmark,postmark").is_err());
+        assert!(EmailProvider::parse_list("postmark,").is_err());
+    }
+}
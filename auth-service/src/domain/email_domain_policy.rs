@@ -0,0 +1,177 @@
+use std::{collections::HashSet, io, path::Path};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::email::Email;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EmailDomainPolicy {
+    // When non-empty only these domains, and their subdomains, may sign up
+    pub allowed_domains: Vec<String>,
+    // Always rejected, even if they fall under an allowed domain
+    pub denied_domains: Vec<String>,
+    // Loaded separately from a file, see `load_disposable_domains`
+    #[serde(skip)]
+    pub disposable_domains: HashSet<String>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum EmailDomainError {
+    #[error("Email domain is not allowed to sign up")]
+    NotAllowed,
+
+    #[error("Email domain is blocked")]
+    Denied,
+
+    #[error("Disposable email addresses are not allowed")]
+    Disposable,
+}
+
+impl EmailDomainPolicy {
+    // Rules are compared against the canonical (lower-case, punycode) domain of an `Email`,
+    // so they are brought into the same form once up front
+    pub fn normalized(self) -> Result<Self, String> {
+        Ok(Self {
+            allowed_domains: normalize_domains(self.allowed_domains)?,
+            denied_domains: normalize_domains(self.denied_domains)?,
+            disposable_domains: self.disposable_domains,
+        })
+    }
+
+    pub fn check(&self, email: &Email) -> Result<(), EmailDomainError> {
+        let domain = email.domain();
+
+        if self.denied_domains.iter().any(|rule| is_within(domain, rule)) {
+            return Err(EmailDomainError::Denied);
+        }
+
+        if !self.allowed_domains.is_empty() {
+            // Explicitly allowed domains are trusted, disposable or not
+            return match self.allowed_domains.iter().any(|rule| is_within(domain, rule)) {
+                true => Ok(()),
+                false => Err(EmailDomainError::NotAllowed),
+            };
+        }
+
+        if suffixes(domain).any(|suffix| self.disposable_domains.contains(suffix)) {
+            return Err(EmailDomainError::Disposable);
+        }
+
+        Ok(())
+    }
+}
+
+// One domain per line, blank lines and lines starting with '#' are skipped. This is the format
+// of the commonly used community lists, e.g. disposable-email-domains.
+pub fn load_disposable_domains(path: impl AsRef<Path>) -> io::Result<HashSet<String>> {
+    let contents = std::fs::read_to_string(path)?;
+
+    let domains = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| idna::domain_to_ascii(line).ok())
+        .collect();
+
+    Ok(domains)
+}
+
+fn normalize_domains(domains: Vec<String>) -> Result<Vec<String>, String> {
+    domains
+        .into_iter()
+        .map(|domain| {
+            idna::domain_to_ascii(domain.trim().trim_start_matches('.'))
+                .ok()
+                .filter(|domain| !domain.is_empty())
+                .ok_or(domain)
+        })
+        .collect()
+}
+
+fn is_within(domain: &str, rule: &str) -> bool {
+    suffixes(domain).any(|suffix| suffix == rule)
+}
+
+// "a.b.example.com", "b.example.com", "example.com", "com"
+fn suffixes(domain: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(domain), |d| d.split_once('.').map(|(_, rest)| rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email(input: &str) -> Email {
+        Email::parse(Secret::new(input.to_owned())).unwrap()
+    }
+
+    fn policy(allowed: &[&str], denied: &[&str], disposable: &[&str]) -> EmailDomainPolicy {
+        EmailDomainPolicy {
+            allowed_domains: allowed.iter().map(|d| d.to_string()).collect(),
+            denied_domains: denied.iter().map(|d| d.to_string()).collect(),
+            disposable_domains: disposable.iter().map(|d| d.to_string()).collect(),
+        }
+        .normalized()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_default_policy_accepts_everything() {
+        assert_eq!(EmailDomainPolicy::default().check(&email("foo@example.com")), Ok(()));
+    }
+
+    #[test]
+    fn test_allowed_domains() {
+        let policy = policy(&["Example.com"], &[], &[]);
+
+        assert_eq!(policy.check(&email("foo@example.com")), Ok(()));
+        assert_eq!(policy.check(&email("foo@mail.EXAMPLE.com")), Ok(()));
+        assert_eq!(policy.check(&email("foo@notexample.com")), Err(EmailDomainError::NotAllowed));
+        assert_eq!(policy.check(&email("foo@example.org")), Err(EmailDomainError::NotAllowed));
+    }
+
+    #[test]
+    fn test_denied_domains_take_precedence() {
+        let policy = policy(&["example.com"], &["contractors.example.com"], &[]);
+
+        assert_eq!(policy.check(&email("foo@example.com")), Ok(()));
+        assert_eq!(
+            policy.check(&email("foo@contractors.example.com")),
+            Err(EmailDomainError::Denied)
+        );
+    }
+
+    #[test]
+    fn test_disposable_domains() {
+        let policy = policy(&[], &[], &["mailinator.com"]);
+
+        assert_eq!(policy.check(&email("foo@mailinator.com")), Err(EmailDomainError::Disposable));
+        assert_eq!(policy.check(&email("foo@x.mailinator.com")), Err(EmailDomainError::Disposable));
+        assert_eq!(policy.check(&email("foo@example.com")), Ok(()));
+    }
+
+    #[test]
+    fn test_unicode_rules_match_punycode_emails() {
+        let policy = policy(&[], &["bücher.example"], &[]);
+
+        assert_eq!(policy.check(&email("foo@BÜCHER.example")), Err(EmailDomainError::Denied));
+    }
+
+    #[test]
+    fn test_load_disposable_domains() {
+        let path = std::env::temp_dir().join(format!("disposable-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "# disposable domains\nMailinator.com\n\n  guerrillamail.com \n").unwrap();
+
+        let domains = load_disposable_domains(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            domains,
+            HashSet::from(["mailinator.com".to_owned(), "guerrillamail.com".to_owned()])
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use color_eyre::eyre::Report;
 use thiserror::Error;
 
+use super::{email_domain_policy::EmailDomainError, password_policy::PasswordPolicyError};
+
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
     #[error("User already exists")]
@@ -12,11 +14,53 @@ pub enum AuthAPIError {
     #[error("Incorrect credentials")]
     IncorrectCredentials,
 
+    #[error("Breached password")]
+    BreachedPassword,
+
+    #[error("{0}")]
+    PasswordPolicyViolation(PasswordPolicyError),
+
+    #[error("{0}")]
+    EmailDomainRejected(EmailDomainError),
+
     #[error("Missing token")]
     MissingToken,
 
     #[error("Invalid token")]
     InvalidToken,
+
+    #[error("Account suspended")]
+    AccountSuspended,
+
+    #[error("Account pending")]
+    AccountPending,
+
+    #[error("Invitation required")]
+    InvitationRequired,
+
+    #[error("Invalid invitation")]
+    InvalidInvitation,
+
+    #[error("Organisation required")]
+    OrganisationRequired,
+
+    #[error("Not an organisation member")]
+    NotOrganisationMember,
+
+    #[error("Organisation already exists")]
+    OrganisationAlreadyExists,
+
+    #[error("Organisation not found")]
+    OrganisationNotFound,
+
+    #[error("User not found")]
+    UserNotFound,
+
+    #[error("Email not found")]
+    EmailNotFound,
+
+    #[error("Insufficient permissions")]
+    InsufficientPermissions,
     
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
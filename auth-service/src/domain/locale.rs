@@ -0,0 +1,112 @@
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+// Languages we have translation catalogs for, English is the fallback for everything else
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Es,
+    Fr,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 4] = [Self::En, Self::De, Self::Es, Self::Fr];
+
+    // Accepts language tags with a region or script, e.g. "de-AT" is German
+    pub fn parse(input: &str) -> Result<Self> {
+        let language = input
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        Self::ALL
+            .into_iter()
+            .find(|locale| locale.as_ref() == language)
+            .ok_or_else(|| eyre!(format!("Not supported locale: {}", input)))
+    }
+
+    // Picks the supported locale the client prefers most from an Accept-Language header,
+    // e.g. "fr-CH, fr;q=0.9, en;q=0.8, *;q=0.5". Returns None if nothing matches.
+    pub fn negotiate(accept_language: &str) -> Option<Self> {
+        let mut candidates: Vec<(f32, usize, Locale)> = accept_language
+            .split(',')
+            .enumerate()
+            .filter_map(|(position, range)| {
+                let mut parts = range.split(';').map(str::trim);
+                let tag = parts.next().filter(|tag| !tag.is_empty())?;
+
+                let quality = match parts.find_map(|param| param.strip_prefix("q=")) {
+                    Some(q) => q.parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q))?,
+                    None => 1.0,
+                };
+
+                let locale = match tag {
+                    "*" => Locale::default(),
+                    tag => Locale::parse(tag).ok()?,
+                };
+
+                (quality > 0.0).then_some((quality, position, locale))
+            })
+            .collect();
+
+        // Highest quality first, ties are broken by the order in the header
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+        candidates.first().map(|(_, _, locale)| *locale)
+    }
+}
+
+impl AsRef<str> for Locale {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::En => "en",
+            Self::De => "de",
+            Self::Es => "es",
+            Self::Fr => "fr",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_language_tags() {
+        assert_eq!(Locale::parse("de").unwrap(), Locale::De);
+        assert_eq!(Locale::parse("de-AT").unwrap(), Locale::De);
+        assert_eq!(Locale::parse("FR_ca").unwrap(), Locale::Fr);
+        assert!(Locale::parse("ja").is_err());
+        assert!(Locale::parse("").is_err());
+    }
+
+    #[test]
+    fn should_round_trip_locale() {
+        assert!(Locale::ALL
+            .iter()
+            .all(|l| Locale::parse(l.as_ref()).ok() == Some(*l)));
+    }
+
+    #[test]
+    fn should_negotiate_preferred_supported_locale() {
+        let cases = [
+            ("de", Some(Locale::De)),
+            ("fr-CH, fr;q=0.9, en;q=0.8, *;q=0.5", Some(Locale::Fr)),
+            ("ja, es;q=0.5", Some(Locale::Es)),
+            ("en;q=0.2, de;q=0.7", Some(Locale::De)),
+            ("es, fr", Some(Locale::Es)),
+            ("ja, *;q=0.1", Some(Locale::En)),
+            ("de;q=0, fr;q=0.3", Some(Locale::Fr)),
+            ("ja, zh", None),
+            ("de;q=abc", None),
+            ("", None),
+        ];
+
+        for (header, expected) in cases {
+            assert_eq!(Locale::negotiate(header), expected, "Failed for header: {}", header);
+        }
+    }
+}
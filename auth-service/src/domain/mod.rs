@@ -2,5 +2,10 @@ pub mod user;
 pub mod error;
 pub mod data_stores;
 pub mod email;
+pub mod email_domain_policy;
 pub mod password;
-pub mod email_client;
\ No newline at end of file
+pub mod password_policy;
+pub mod email_client;
+pub mod security_event;
+pub mod organisation;
+pub mod locale;
\ No newline at end of file
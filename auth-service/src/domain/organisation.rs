@@ -0,0 +1,110 @@
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Organisation {
+    pub id: Uuid,
+    // Short unique name users pick their organisation by when logging in
+    pub slug: String,
+    pub name: String,
+}
+
+impl Organisation {
+    pub fn new(slug: &str, name: String) -> Result<Self> {
+        let slug = parse_slug(slug)?;
+
+        if name.trim().is_empty() {
+            return Err(eyre!("Organisation name must not be empty"));
+        }
+
+        Ok(Self { id: Uuid::new_v4(), slug, name })
+    }
+}
+
+// Lower-case letters, digits and inner dashes, e.g. "acme-corp"
+pub fn parse_slug(input: &str) -> Result<String> {
+    let slug = input.to_lowercase();
+
+    let valid = (MIN_SLUG_LENGTH..=MAX_SLUG_LENGTH).contains(&slug.len())
+        && !slug.starts_with('-')
+        && !slug.ends_with('-')
+        && slug.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+
+    match valid {
+        true => Ok(slug),
+        false => Err(eyre!(format!("Not valid organisation slug: {}", input))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    #[default]
+    Member,
+    Admin,
+}
+
+impl OrgRole {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input {
+            "member" => Ok(Self::Member),
+            "admin" => Ok(Self::Admin),
+            _ => Err(eyre!(format!("Not valid organisation role: {}", input))),
+        }
+    }
+}
+
+impl AsRef<str> for OrgRole {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Member => "member",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Membership {
+    pub organisation: Organisation,
+    pub role: OrgRole,
+}
+
+const MIN_SLUG_LENGTH: usize = 2;
+const MAX_SLUG_LENGTH: usize = 63;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_valid_slugs() {
+        assert_eq!(parse_slug("acme").unwrap(), "acme");
+        assert_eq!(parse_slug("Acme-Corp2").unwrap(), "acme-corp2");
+    }
+
+    #[test]
+    fn should_reject_invalid_slugs() {
+        let results = [
+            parse_slug(""),
+            parse_slug("a"),
+            parse_slug("-acme"),
+            parse_slug("acme-"),
+            parse_slug("acme corp"),
+            parse_slug("acme_corp"),
+            parse_slug(&"a".repeat(64)),
+        ];
+
+        assert!(results.iter().all(|r| r.is_err()))
+    }
+
+    #[test]
+    fn should_round_trip_org_role() {
+        let roles = [OrgRole::Member, OrgRole::Admin];
+
+        assert!(roles
+            .iter()
+            .all(|r| OrgRole::parse(r.as_ref()).ok() == Some(*r)));
+        assert!(OrgRole::parse("owner").is_err());
+    }
+}
@@ -1,6 +1,14 @@
 use color_eyre::eyre::{eyre, Result};
 use secrecy::{ExposeSecret, Secret};
 
+use super::{
+    email::Email,
+    password_policy::{PasswordPolicy, PasswordPolicyError},
+};
+
+// Shortest password accepted at login, so no password policy may allow a shorter one
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
 #[derive(Debug, Clone)]
 pub struct Password(Secret<String>);
 
@@ -11,7 +19,15 @@ impl PartialEq for Password {
 }
 
 impl Password {
-    pub fn parse(input: Secret<String>) -> Result<Password> {
+    // Every new password goes through the policy
+    pub fn parse(input: Secret<String>, policy: &PasswordPolicy, email: &Email) -> Result<Password, PasswordPolicyError> {
+        policy.validate(&input, email)?;
+        Ok(Self(input))
+    }
+
+    // For passwords being checked at login and stored hashes, never for setting a password:
+    // existing passwords must keep working after the policy is tightened
+    pub fn parse_credential(input: Secret<String>) -> Result<Password> {
         if !validate_password(&input) {
             return Err(eyre!("Failed to parse string to a Password type"));
         } 
@@ -26,12 +42,16 @@ impl AsRef<Secret<String>> for Password {
 }
 
 fn validate_password(s: &Secret<String>) -> bool {
-    s.expose_secret().len() >= 8
+    s.expose_secret().len() >= MIN_PASSWORD_LENGTH
 }
 
 #[cfg(test)]
 mod tests {
     use super::Password;
+    use crate::domain::{
+        email::Email,
+        password_policy::{PasswordPolicy, PasswordPolicyError},
+    };
 
     use fake::faker::internet::en::Password as FakePassword;
     use fake::Fake;
@@ -40,14 +60,14 @@ mod tests {
     #[test]
     fn should_return_password_err_when_not_properly_parsed() {
         let results = [
-            Password::parse(Secret::new("".to_string())),
-            Password::parse(Secret::new("1".to_string())),
-            Password::parse(Secret::new("12".to_string())),
-            Password::parse(Secret::new("123".to_string())),
-            Password::parse(Secret::new("1234".to_string())),
-            Password::parse(Secret::new("12345".to_string())),
-            Password::parse(Secret::new("123456".to_string())),
-            Password::parse(Secret::new("1234567".to_string())),
+            Password::parse_credential(Secret::new("".to_string())),
+            Password::parse_credential(Secret::new("1".to_string())),
+            Password::parse_credential(Secret::new("12".to_string())),
+            Password::parse_credential(Secret::new("123".to_string())),
+            Password::parse_credential(Secret::new("1234".to_string())),
+            Password::parse_credential(Secret::new("12345".to_string())),
+            Password::parse_credential(Secret::new("123456".to_string())),
+            Password::parse_credential(Secret::new("1234567".to_string())),
         ];
 
         assert!(results.iter().all(|r| r.is_err()))
@@ -56,13 +76,27 @@ mod tests {
     #[test]
     fn should_return_password_ok_when_properly_parsed() {
         let results = [
-            Password::parse(Secret::new("password".to_string())),
-            Password::parse(Secret::new("some long passphrase that should also work".to_string())),
+            Password::parse_credential(Secret::new("password".to_string())),
+            Password::parse_credential(Secret::new("some long passphrase that should also work".to_string())),
         ];
 
         assert!(results.iter().all(|r| r.is_ok()))
     }
 
+    #[test]
+    fn should_apply_policy_when_parsing_new_password() {
+        let email = Email::parse(Secret::new("johnsmith@example.com".to_owned())).unwrap();
+        let policy = PasswordPolicy { min_strength: 3, ..Default::default() };
+
+        // Still a valid credential for existing accounts
+        assert!(Password::parse_credential(Secret::new("password123".to_owned())).is_ok());
+        assert_eq!(
+            Password::parse(Secret::new("password123".to_owned()), &policy, &email).unwrap_err(),
+            PasswordPolicyError::TooWeak { score: 2, required: 3 }
+        );
+        assert!(Password::parse(Secret::new("Tr0ub4dor&3xyz".to_owned()), &policy, &email).is_ok());
+    }
+
     #[derive(Debug, Clone)]
     struct ValidPasswordFixture(pub Secret<String>); 
 
@@ -74,6 +108,6 @@ mod tests {
     }
     #[quickcheck_macros::quickcheck]
     fn valid_passwords_are_parsed_successfully(valid_password: ValidPasswordFixture) -> bool {
-        Password::parse(valid_password.0).is_ok()
+        Password::parse_credential(valid_password.0).is_ok()
     }
 }
\ No newline at end of file
@@ -0,0 +1,251 @@
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::email::Email;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub required_classes: Vec<CharacterClass>,
+    // Compared case-insensitively; the user's email local-part is always banned as well
+    pub banned_words: Vec<String>,
+    // 0 (very weak) to 4 (very strong), see `strength_score`
+    pub min_strength: u8,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            required_classes: Vec::new(),
+            banned_words: Vec::new(),
+            min_strength: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CharacterClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+impl CharacterClass {
+    fn of(c: char) -> Option<Self> {
+        match c {
+            'a'..='z' => Some(Self::Lowercase),
+            'A'..='Z' => Some(Self::Uppercase),
+            '0'..='9' => Some(Self::Digit),
+            c if c.is_ascii_punctuation() || c == ' ' => Some(Self::Symbol),
+            _ => None,
+        }
+    }
+
+    fn pool_size(&self) -> f64 {
+        match self {
+            Self::Lowercase | Self::Uppercase => 26.0,
+            Self::Digit => 10.0,
+            Self::Symbol => 33.0,
+        }
+    }
+
+    fn description(&self) -> &str {
+        match self {
+            Self::Lowercase => "a lowercase letter",
+            Self::Uppercase => "an uppercase letter",
+            Self::Digit => "a digit",
+            Self::Symbol => "a symbol",
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PasswordPolicyError {
+    #[error("Password must be at least {0} characters long")]
+    TooShort(usize),
+
+    #[error("Password must be at most {0} characters long")]
+    TooLong(usize),
+
+    #[error("Password must contain {}", .0.description())]
+    MissingCharacterClass(CharacterClass),
+
+    #[error("Password must not contain your email address or common words")]
+    BannedWord,
+
+    #[error("Password is too weak (strength {score} of 4, at least {required} required)")]
+    TooWeak { score: u8, required: u8 },
+}
+
+impl PasswordPolicy {
+    pub fn validate(&self, password: &Secret<String>, email: &Email) -> Result<(), PasswordPolicyError> {
+        let password = password.expose_secret();
+        let length = password.chars().count();
+
+        if length < self.min_length {
+            return Err(PasswordPolicyError::TooShort(self.min_length));
+        }
+
+        if length > self.max_length {
+            return Err(PasswordPolicyError::TooLong(self.max_length));
+        }
+
+        if let Some(class) = self
+            .required_classes
+            .iter()
+            .find(|class| !password.chars().any(|c| CharacterClass::of(c) == Some(**class)))
+        {
+            return Err(PasswordPolicyError::MissingCharacterClass(*class));
+        }
+
+        let lowercase = password.to_lowercase();
+        let local_part = email
+            .as_ref()
+            .expose_secret()
+            .split('@')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if self
+            .banned_words
+            .iter()
+            .map(|word| word.to_lowercase())
+            .chain(std::iter::once(local_part))
+            .filter(|word| word.chars().count() >= MIN_BANNED_WORD_LENGTH)
+            .any(|word| lowercase.contains(&word))
+        {
+            return Err(PasswordPolicyError::BannedWord);
+        }
+
+        let score = strength_score(password);
+        if score < self.min_strength {
+            return Err(PasswordPolicyError::TooWeak { score, required: self.min_strength });
+        }
+
+        Ok(())
+    }
+}
+
+// Estimated entropy in bits: the size of the character pool the password draws from,
+// raised to its length. Runs of the same character only count once, so "aaaaaaaa"
+// is as weak as "a".
+pub fn entropy_bits(password: &str) -> f64 {
+    let mut classes: Vec<Option<CharacterClass>> = password.chars().map(CharacterClass::of).collect();
+    classes.sort_by_key(|class| class.map(|c| c as u8));
+    classes.dedup();
+
+    let pool: f64 = classes
+        .iter()
+        .map(|class| class.map_or(OTHER_POOL_SIZE, |c| c.pool_size()))
+        .sum();
+
+    let mut chars: Vec<char> = password.chars().collect();
+    chars.dedup();
+
+    chars.len() as f64 * pool.max(1.0).log2()
+}
+
+pub fn strength_score(password: &str) -> u8 {
+    let bits = entropy_bits(password);
+    STRENGTH_THRESHOLDS.iter().filter(|threshold| bits >= **threshold).count() as u8
+}
+
+// Minimum entropy in bits for a strength score of 1, 2, 3 and 4
+const STRENGTH_THRESHOLDS: [f64; 4] = [28.0, 36.0, 60.0, 128.0];
+// Non-ASCII characters are counted as one generous extra class
+const OTHER_POOL_SIZE: f64 = 100.0;
+// Shorter words (and email local-parts) would reject far too many passwords
+const MIN_BANNED_WORD_LENGTH: usize = 3;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("johnsmith@example.com".to_owned())).unwrap()
+    }
+
+    fn validate(policy: &PasswordPolicy, password: &str) -> Result<(), PasswordPolicyError> {
+        policy.validate(&Secret::new(password.to_owned()), &email())
+    }
+
+    #[test]
+    fn test_default_policy_accepts_reasonable_passwords() {
+        let policy = PasswordPolicy::default();
+
+        assert_eq!(validate(&policy, "password123"), Ok(()));
+        assert_eq!(validate(&policy, "correct horse battery staple"), Ok(()));
+    }
+
+    #[test]
+    fn test_length_limits() {
+        let policy = PasswordPolicy { min_length: 10, max_length: 12, ..Default::default() };
+
+        assert_eq!(validate(&policy, "abcdefgh"), Err(PasswordPolicyError::TooShort(10)));
+        assert_eq!(validate(&policy, "abcdefghijklmnop"), Err(PasswordPolicyError::TooLong(12)));
+        // Length is counted in characters, not bytes
+        assert_eq!(validate(&policy, "pässwörtäöü"), Ok(()));
+    }
+
+    #[test]
+    fn test_required_classes() {
+        let policy = PasswordPolicy {
+            required_classes: vec![CharacterClass::Uppercase, CharacterClass::Symbol],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            validate(&policy, "password123"),
+            Err(PasswordPolicyError::MissingCharacterClass(CharacterClass::Uppercase))
+        );
+        assert_eq!(
+            validate(&policy, "Password123"),
+            Err(PasswordPolicyError::MissingCharacterClass(CharacterClass::Symbol))
+        );
+        assert_eq!(validate(&policy, "Password123!"), Ok(()));
+    }
+
+    #[test]
+    fn test_banned_words() {
+        let policy = PasswordPolicy {
+            banned_words: vec!["Bootcamp".to_owned()],
+            ..Default::default()
+        };
+
+        assert_eq!(validate(&policy, "my-BOOTCAMP-pass"), Err(PasswordPolicyError::BannedWord));
+        assert_eq!(validate(&policy, "JohnSmith1984"), Err(PasswordPolicyError::BannedWord));
+        assert_eq!(validate(&policy, "unrelated-phrase"), Ok(()));
+    }
+
+    #[test]
+    fn test_min_strength() {
+        let policy = PasswordPolicy { min_strength: 3, ..Default::default() };
+
+        assert_eq!(
+            validate(&policy, "aaaaaaaaaaaa"),
+            Err(PasswordPolicyError::TooWeak { score: 0, required: 3 })
+        );
+        assert_eq!(
+            validate(&policy, "password123"),
+            Err(PasswordPolicyError::TooWeak { score: 2, required: 3 })
+        );
+        assert_eq!(validate(&policy, "Tr0ub4dor&3xyz"), Ok(()));
+    }
+
+    #[test]
+    fn test_strength_score() {
+        assert_eq!(strength_score("12345678"), 0);
+        assert_eq!(strength_score("abcdefg"), 1);
+        assert_eq!(strength_score("password123"), 2);
+        assert_eq!(strength_score("Tr0ub4dor&3xyz"), 3);
+        assert_eq!(strength_score("correct horse battery staple with some more words"), 4);
+    }
+}
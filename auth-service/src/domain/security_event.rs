@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Payload delivered to webhook subscribers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityEvent {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: SecurityEventType,
+    pub occurred_at: DateTime<Utc>,
+    pub email: String,
+}
+
+impl SecurityEvent {
+    pub fn new(event_type: SecurityEventType, email: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_type,
+            occurred_at: Utc::now(),
+            email,
+        }
+    }
+}
+
+// Accounts are never locked after failed logins, so there is no lockout event. Rejected logins of
+// suspended accounts are reported instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SecurityEventType {
+    #[serde(rename = "user.signed_up")]
+    SignedUp,
+    #[serde(rename = "user.logged_in")]
+    LoggedIn,
+    // A login or 2FA attempt against a suspended account
+    #[serde(rename = "user.login_rejected_suspended")]
+    LoginRejectedSuspended,
+    #[serde(rename = "user.deleted")]
+    AccountDeleted,
+}
+
+impl AsRef<str> for SecurityEventType {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::SignedUp => "user.signed_up",
+            Self::LoggedIn => "user.logged_in",
+            Self::LoginRejectedSuspended => "user.login_rejected_suspended",
+            Self::AccountDeleted => "user.deleted",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_security_event_serialization() {
+        let event = SecurityEvent::new(SecurityEventType::SignedUp, "foo@example.com".to_owned());
+
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["type"], "user.signed_up");
+        assert_eq!(json["email"], "foo@example.com");
+        assert!(json.get("occurredAt").is_some());
+        assert_eq!(serde_json::from_value::<SecurityEvent>(json).unwrap(), event);
+    }
+}
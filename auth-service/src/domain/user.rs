@@ -1,15 +1,122 @@
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
 use super::email::Email;
+use super::locale::Locale;
 use super::password::Password;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct User {
-    pub email: Email, 
+    pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    pub status: AccountStatus,
+    pub role: UserRole,
+    // Language emails are sent in; without one the language of the request is used
+    pub locale: Option<Locale>,
 }
 
 impl User {
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
-        Self { email, password, requires_2fa }
+        Self {
+            email,
+            password,
+            requires_2fa,
+            status: AccountStatus::default(),
+            role: UserRole::default(),
+            locale: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountStatus {
+    #[default]
+    Active,
+    Suspended,
+    Pending,
+}
+
+impl AccountStatus {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input {
+            "active" => Ok(Self::Active),
+            "suspended" => Ok(Self::Suspended),
+            "pending" => Ok(Self::Pending),
+            _ => Err(eyre!(format!("Not valid account status: {}", input))),
+        }
     }
-}
\ No newline at end of file
+}
+
+impl AsRef<str> for AccountStatus {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Active => "active",
+            Self::Suspended => "suspended",
+            Self::Pending => "pending",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    #[default]
+    User,
+    Admin,
+}
+
+impl UserRole {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input {
+            "user" => Ok(Self::User),
+            "admin" => Ok(Self::Admin),
+            _ => Err(eyre!(format!("Not valid user role: {}", input))),
+        }
+    }
+}
+
+impl AsRef<str> for UserRole {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::User => "user",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_round_trip_account_status() {
+        let statuses = [AccountStatus::Active, AccountStatus::Suspended, AccountStatus::Pending];
+
+        assert!(statuses
+            .iter()
+            .all(|s| AccountStatus::parse(s.as_ref()).ok() == Some(*s)))
+    }
+
+    #[test]
+    fn should_return_err_when_account_status_not_properly_parsed() {
+        let results = [
+            AccountStatus::parse(""),
+            AccountStatus::parse("Active"),
+            AccountStatus::parse("disabled"),
+        ];
+
+        assert!(results.iter().all(|r| r.is_err()))
+    }
+
+    #[test]
+    fn should_round_trip_user_role() {
+        let roles = [UserRole::User, UserRole::Admin];
+
+        assert!(roles
+            .iter()
+            .all(|r| UserRole::parse(r.as_ref()).ok() == Some(*r)));
+        assert!(UserRole::parse("root").is_err());
+    }
+}
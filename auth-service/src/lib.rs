@@ -1,17 +1,26 @@
 use axum::{
-    http::{Method, StatusCode},
+    extract::connect_info::{ConnectInfo, IntoMakeServiceWithConnectInfo},
+    http::StatusCode,
+    middleware::{self, AddExtension},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{get, post},
     serve::Serve,
     Json, Router,
 };
-use redis::{Client, RedisResult};
+use redis::{Client, Connection, RedisResult};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use utils::tracing::{make_span_with_request_id, on_request, on_response};
-use std::error::Error;
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use utils::{
+    cors::cors_layer,
+    i18n::{request_locale, set_request_locale, translate, Localize},
+    metrics::set_route_labels,
+    shutdown::ShutdownHandle,
+    tracing::{make_span_with_request_id, on_request, on_response, request_id, set_request_id},
+};
+use std::{error::Error, future::IntoFuture, net::SocketAddr, time::Duration};
+use tower_http::{services::ServeDir, trace::TraceLayer};
+use tracing::Span;
 
 pub mod routes;
 pub mod services;
@@ -23,80 +32,163 @@ use app_state::app_state::AppState;
 use domain::error::AuthAPIError;
 use routes::*;
 
+type Server = Serve<
+    IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+    AddExtension<Router, ConnectInfo<SocketAddr>>,
+>;
+
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Server,
     pub address: String,
+    shutdown: ShutdownHandle,
+    drain_timeout: Duration,
 }
 
 impl Application {
-    pub fn new(server: Serve<Router, Router>, address: String) -> Self {
-        Self { server, address }
+    pub fn new(server: Server, address: String, drain_timeout: Duration) -> Self {
+        Self { server, address, shutdown: ShutdownHandle::default(), drain_timeout }
+    }
+
+    // Stops the server started with `run`
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
     }
 }
 
 impl Application {
     pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let allowed_origins = [
-            "http://localhost:8000".parse()?,
-            "http://198.211.97.43:8000".parse()?,
-        ];
-
-        let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
-            // Allow cookies to be included in requests
-            .allow_credentials(true)
-            .allow_origin(allowed_origins);
-
-        let router = Router::new()
+        let cors = cors_layer(&app_state.settings.cors)?;
+        let drain_timeout = app_state.settings.application.shutdown_timeout;
+        let request_metrics = app_state.metrics.clone();
+
+        let mut router = Router::new()
             .nest_service("/", ServeDir::new("assets"))
             .route("/signup", post(signup))
             .route("/login", post(login))
             .route("/logout", post(logout))
             .route("/verify-2fa", post(verify_2fa))
             .route("/verify-token", post(verify_token))
-            // .route("/delete-account", post(delete_account)) # TODO delete-account router complete
+            .route("/audit-log", get(audit_log))
+            .route("/email-outbox", get(email_outbox))
+            .route("/delete-account", post(delete_account))
+            .route("/create-invitation", post(create_invitation))
+            .route("/revoke-invitation", post(revoke_invitation))
+            .route("/create-organisation", post(create_organisation))
+            .route("/set-membership", post(set_membership))
+            .route("/set-locale", post(set_locale))
+            .route("/health/live", get(health_live))
+            .route("/health/ready", get(health_ready))
+            .route("/metrics", get(metrics));
+
+        // Only with the dev email provider, the inbox shows login codes to anyone who asks
+        if app_state.dev_mailbox.is_some() {
+            router = router
+                .route("/dev/inbox", get(dev_inbox))
+                .route("/dev/inbox/:id", get(dev_inbox_email));
+        }
+
+        let router = router
             .with_state(app_state)
             .layer(cors)
+            .layer(middleware::from_fn(set_route_labels))
             .layer(
                 TraceLayer::new_for_http()
                     .make_span_with(make_span_with_request_id)
                     .on_request(on_request)
-                    .on_response(on_response),
-            );
+                    .on_response(move |response: &Response, latency: Duration, span: &Span| {
+                        request_metrics.record_request(response, latency);
+                        on_response(response, latency, span)
+                    }),
+            )
+            .layer(middleware::from_fn(set_request_locale))
+            .layer(middleware::from_fn(set_request_id));
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
-        Ok(Application::new(server, address))
+        Ok(Application::new(server, address, drain_timeout))
     }
 
+    // Serves until the shutdown handle is triggered, then stops accepting connections and gives
+    // in-flight requests up to the drain timeout to finish
     pub async fn run(self) -> Result<(), std::io::Error> {
         tracing::info!("listening on {}", &self.address);
-        self.server.await
+
+        let shutdown = self.shutdown.clone();
+        let server = self.server
+            .with_graceful_shutdown(async move { shutdown.wait().await })
+            .into_future();
+        tokio::pin!(server);
+
+        tokio::select! {
+            result = &mut server => return result,
+            _ = self.shutdown.wait() => {
+                tracing::info!("shutting down, draining in-flight requests for up to {:?}", self.drain_timeout);
+            }
+        }
+
+        match tokio::time::timeout(self.drain_timeout, server).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!("in-flight requests did not finish within {:?}, dropping them", self.drain_timeout);
+                Ok(())
+            }
+        }
     }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
+    // Same as the X-Request-Id response header, for quoting in support tickets
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
 
+        // Messages are in the language negotiated from the request's Accept-Language header
+        let locale = request_locale();
+
+        // Policy violations tell the client which rule the password or email broke
+        let policy_message: String;
         let (status, error_message) = match self {
-            AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
-            AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
-            AuthAPIError::IncorrectCredentials => (StatusCode::UNAUTHORIZED, "Incorrect credentials"),
-            AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
-            AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
-            AuthAPIError::UnexpectedError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error"),
+            AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, translate(locale, "error.user_already_exists")),
+            AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, translate(locale, "error.invalid_credentials")),
+            AuthAPIError::IncorrectCredentials => (StatusCode::UNAUTHORIZED, translate(locale, "error.incorrect_credentials")),
+            AuthAPIError::BreachedPassword => (StatusCode::BAD_REQUEST, translate(locale, "error.breached_password")),
+            AuthAPIError::PasswordPolicyViolation(e) => {
+                policy_message = e.localize(locale);
+                (StatusCode::BAD_REQUEST, policy_message.as_str())
+            }
+            AuthAPIError::EmailDomainRejected(e) => {
+                policy_message = e.localize(locale);
+                (StatusCode::FORBIDDEN, policy_message.as_str())
+            }
+            AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, translate(locale, "error.missing_token")),
+            AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, translate(locale, "error.invalid_token")),
+            AuthAPIError::AccountSuspended => (StatusCode::FORBIDDEN, translate(locale, "error.account_suspended")),
+            AuthAPIError::AccountPending => (StatusCode::FORBIDDEN, translate(locale, "error.account_pending")),
+            AuthAPIError::InvitationRequired => (StatusCode::FORBIDDEN, translate(locale, "error.invitation_required")),
+            AuthAPIError::InvalidInvitation => (StatusCode::FORBIDDEN, translate(locale, "error.invalid_invitation")),
+            AuthAPIError::OrganisationRequired => (StatusCode::BAD_REQUEST, translate(locale, "error.organisation_required")),
+            AuthAPIError::NotOrganisationMember => (StatusCode::FORBIDDEN, translate(locale, "error.not_organisation_member")),
+            AuthAPIError::OrganisationAlreadyExists => (StatusCode::CONFLICT, translate(locale, "error.organisation_already_exists")),
+            AuthAPIError::OrganisationNotFound => (StatusCode::NOT_FOUND, translate(locale, "error.organisation_not_found")),
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, translate(locale, "error.user_not_found")),
+            AuthAPIError::EmailNotFound => (StatusCode::NOT_FOUND, translate(locale, "error.email_not_found")),
+            AuthAPIError::InsufficientPermissions => (StatusCode::FORBIDDEN, translate(locale, "error.insufficient_permissions")),
+            AuthAPIError::UnexpectedError(_) => (StatusCode::INTERNAL_SERVER_ERROR, translate(locale, "error.unexpected_error")),
         };
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
+            request_id: request_id().map(|request_id| request_id.to_string()),
         });
         (status, body).into_response()
     }
@@ -126,4 +218,13 @@ pub async fn get_postgres_pool(url: &Secret<String>) -> Result<PgPool, sqlx::Err
 pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
     let redis_url = format!("redis://{}/", redis_hostname);
     redis::Client::open(redis_url)
+}
+
+// Commands on the synchronous connection block until Redis answers, so they fail after `timeout`
+// instead of hanging requests and the readiness probe when Redis stops responding
+pub fn get_redis_connection(redis_hostname: String, timeout: Duration) -> RedisResult<Connection> {
+    let connection = get_redis_client(redis_hostname)?.get_connection_with_timeout(timeout)?;
+    connection.set_read_timeout(Some(timeout))?;
+    connection.set_write_timeout(Some(timeout))?;
+    Ok(connection)
 }
\ No newline at end of file
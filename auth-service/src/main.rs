@@ -6,42 +6,100 @@ use secrecy::Secret;
 use tokio::sync::RwLock;
 
 use auth_service::{
-    app_state::app_state::AppState, 
-    domain::email::Email, 
+    app_state::app_state::{AppState, BreachedPasswordStoreType, DevMailboxType, EmailClientType, EmailOutboxStoreType, MetricsType}, 
+    domain::{email::Email, email_client::{EmailClient, EmailProvider}, email_domain_policy::{load_disposable_domains, EmailDomainPolicy}}, 
     get_postgres_pool, 
-    get_redis_client, 
-    services::data_stores::{PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore, RedisTwoFACodeStore}, 
-    utils::{constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME, LOG_NAME}, tracing::init_tracing}, 
+    get_redis_connection, 
+    services::{
+        data_stores::{
+            CircuitBreakerPolicy, DevEmailClient, DevMailbox, FailoverEmailClient, HashsetBreachedPasswordStore, HibpBreachedPasswordStore, PostgresAuditLogStore, PostgresEmailOutboxStore, PostgresInvitationStore, PostgresUserStore,
+            PostmarkEmailClient, RedisBannedTokenStore, RedisTwoFACodeStore, SmtpEmailClient,
+        },
+        email_outbox_worker::EmailOutboxWorker,
+        webhook_dispatcher::WebhookDispatcher,
+    },
+    utils::{metrics::Metrics, settings::Settings, shutdown::shutdown_signal, tracing::init_tracing}, 
     Application
 };
 
 #[tokio::main]
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
-    init_tracing(LOG_NAME).expect("Failed to initialize tracing");
+    let settings = Arc::new(Settings::load().expect("Failed to load settings"));
+    let _tracing = init_tracing(&settings.logging, &settings.telemetry).expect("Failed to initialize tracing");
     
-    let pg_pool = configure_postgresql().await;
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let redis_con = configure_redis();
-    let redis_con = Arc::new(RwLock::new(redis_con));
-    let token_store = RedisBannedTokenStore::new(redis_con);
+    let pg_pool = configure_postgresql(&settings).await;
+    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone(), settings.passwords.hash_params)));
+    let audit_log_store = Arc::new(RwLock::new(PostgresAuditLogStore::new(pg_pool.clone())));
+    let invitation_store = Arc::new(RwLock::new(PostgresInvitationStore::new(pg_pool.clone())));
+    let email_outbox = Arc::new(RwLock::new(PostgresEmailOutboxStore::new(pg_pool.clone())));
+    let redis_con = configure_redis(&settings);
+    let token_store_redis_con = Arc::new(RwLock::new(redis_con));
+    let token_store = RedisBannedTokenStore::new(token_store_redis_con.clone(), settings.auth.token_ttl);
     let token_store = Arc::new(RwLock::new(token_store));
-    let redis_con = configure_redis();
-    let two_fa_code_store = RedisTwoFACodeStore::new(Arc::new(RwLock::new(redis_con)));
+    let redis_con = configure_redis(&settings);
+    let two_fa_redis_con = Arc::new(RwLock::new(redis_con));
+    let two_fa_code_store = RedisTwoFACodeStore::new(two_fa_redis_con.clone(), settings.two_fa.code_ttl);
     let two_fa_code_store = Arc::new(RwLock::new(two_fa_code_store));
-    let email_client = Arc::new(RwLock::new(configure_postmark_email_client()));
+    let dev_mailbox = settings.email.providers
+        .contains(&EmailProvider::Dev)
+        .then(|| Arc::new(RwLock::new(DevMailbox::default())));
+    let email_client = configure_email_client(&settings, &dev_mailbox);
+    let webhook_dispatcher = Arc::new(configure_webhook_dispatcher(&settings));
+    let breached_password_store = configure_breached_password_store(&settings);
+    let email_domain_policy = Arc::new(configure_email_domain_policy(&settings));
+    let metrics = Arc::new(Metrics::new().expect("Failed to register metrics"));
 
-    let app_state = AppState::new(user_store, token_store, two_fa_code_store, email_client);
+    let app_state = AppState::new(
+        user_store,
+        token_store,
+        two_fa_code_store,
+        email_client.clone(),
+        audit_log_store,
+        webhook_dispatcher.clone(),
+        breached_password_store,
+        email_domain_policy,
+        invitation_store,
+        email_outbox.clone(),
+        dev_mailbox,
+        settings.clone(),
+        metrics.clone(),
+    );
 
-    let app = Application::build(app_state, prod::APP_ADDRESS)
+    let app = Application::build(app_state, &settings.application.address)
         .await
         .expect("Failed to build app");
 
+    let shutdown = app.shutdown_handle();
+    let email_outbox_worker = configure_email_outbox_worker(&settings, email_outbox, email_client, metrics)
+        .spawn(settings.email.outbox.poll_interval, shutdown.clone());
+
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        shutdown.shutdown();
+    });
+
     app.run().await.expect("Failed to run app");
+
+    // Requests are drained, emails being sent and webhooks being delivered get the same time again
+    let background_work = async {
+        if let Err(e) = email_outbox_worker.await {
+            tracing::error!("email outbox worker failed: {:?}", e);
+        }
+        webhook_dispatcher.drain().await;
+    };
+    if tokio::time::timeout(settings.application.shutdown_timeout, background_work).await.is_err() {
+        tracing::warn!("background work did not finish within {:?}", settings.application.shutdown_timeout);
+    }
+
+    pg_pool.close().await;
+    close_redis(&token_store_redis_con).await;
+    close_redis(&two_fa_redis_con).await;
+    tracing::info!("shut down");
 }
 
-async fn configure_postgresql() -> PgPool {
-    let pg_pool = get_postgres_pool(&DATABASE_URL)
+async fn configure_postgresql(settings: &Settings) -> PgPool {
+    let pg_pool = get_postgres_pool(&settings.database.url)
         .await
         .expect("Failed to create Postgres connection pool!");
 
@@ -53,23 +111,121 @@ async fn configure_postgresql() -> PgPool {
     pg_pool
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+fn configure_redis(settings: &Settings) -> redis::Connection {
+    get_redis_connection(settings.redis.host_name.to_owned(), settings.health.timeout)
         .expect("Failed to get Redis connection")
 }
 
-fn configure_postmark_email_client() -> PostmarkEmailClient {
+// Dropping the connection closes it too, QUIT lets Redis know right away
+async fn close_redis(redis_con: &RwLock<redis::Connection>) {
+    if let Err(e) = redis::cmd("QUIT").query::<()>(&mut *redis_con.write().await) {
+        tracing::warn!("failed to close Redis connection: {:?}", e);
+    }
+}
+
+fn configure_email_client(settings: &Settings, dev_mailbox: &Option<DevMailboxType>) -> EmailClientType {
+    match settings.email.providers.as_slice() {
+        [EmailProvider::Postmark] => Arc::new(RwLock::new(configure_postmark_email_client(settings))),
+        [EmailProvider::Smtp] => Arc::new(RwLock::new(configure_smtp_email_client(settings))),
+        [EmailProvider::Dev] => Arc::new(RwLock::new(configure_dev_email_client(settings, dev_mailbox))),
+        providers => Arc::new(RwLock::new(configure_failover_email_client(settings, providers, dev_mailbox))),
+    }
+}
+
+fn configure_failover_email_client(settings: &Settings, providers: &[EmailProvider], dev_mailbox: &Option<DevMailboxType>) -> FailoverEmailClient {
+    let providers = providers
+        .iter()
+        .map(|provider| (provider.as_ref().to_owned(), configure_provider_email_client(settings, *provider, dev_mailbox)))
+        .collect();
+
+    let failover = &settings.email.failover;
+    let policy = CircuitBreakerPolicy {
+        failure_threshold: failover.failure_threshold,
+        open_duration: failover.open_duration,
+    };
+
+    FailoverEmailClient::new(providers, policy, failover.timeout)
+}
+
+fn configure_provider_email_client(settings: &Settings, provider: EmailProvider, dev_mailbox: &Option<DevMailboxType>) -> Box<dyn EmailClient + Send + Sync> {
+    match provider {
+        EmailProvider::Postmark => Box::new(configure_postmark_email_client(settings)),
+        EmailProvider::Smtp => Box::new(configure_smtp_email_client(settings)),
+        EmailProvider::Dev => Box::new(configure_dev_email_client(settings, dev_mailbox)),
+    }
+}
+
+fn configure_postmark_email_client(settings: &Settings) -> PostmarkEmailClient {
     let http_client = Client::builder()
-        .timeout(prod::email_client::TIMEOUT)
+        .timeout(settings.email.timeout)
         .build()
         .expect("Failed to build HTTP client");
 
     PostmarkEmailClient::new(
-        prod::email_client::BASE_URL.to_owned(),
-        Email::parse(Secret::new(prod::email_client::SENDER.to_string())).unwrap(),
-        POSTMARK_AUTH_TOKEN.to_owned(),
+        settings.email.postmark.base_url.to_owned(),
+        sender(settings),
+        settings.email.postmark.auth_token.clone().expect("Postmark auth token must be set for the postmark email provider"),
         http_client,
     )
-}
\ No newline at end of file
+}
+
+fn configure_smtp_email_client(settings: &Settings) -> SmtpEmailClient {
+    SmtpEmailClient::new(
+        settings.email.smtp.as_ref().expect("SMTP config must be set for the smtp email provider"),
+        settings.email.smtp_password.clone(),
+        sender(settings),
+        settings.email.timeout,
+    )
+    .expect("Failed to build SMTP email client")
+}
+
+fn configure_dev_email_client(settings: &Settings, dev_mailbox: &Option<DevMailboxType>) -> DevEmailClient {
+    DevEmailClient::new(
+        sender(settings),
+        dev_mailbox.clone().expect("Dev mailbox must be set for the dev email provider"),
+        Some(settings.email.dev_mailbox_dir.clone()),
+    )
+}
+
+fn sender(settings: &Settings) -> Email {
+    Email::parse(Secret::new(settings.email.sender.clone())).expect("Email sender was validated with the settings")
+}
+
+fn configure_email_outbox_worker(settings: &Settings, email_outbox: EmailOutboxStoreType, email_client: EmailClientType, metrics: MetricsType) -> EmailOutboxWorker {
+    EmailOutboxWorker::new(email_outbox, email_client, settings.email.outbox.retry, settings.email.send_timeout(), metrics)
+}
+
+fn configure_webhook_dispatcher(settings: &Settings) -> WebhookDispatcher {
+    let http_client = Client::builder()
+        .timeout(settings.webhooks.timeout)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    WebhookDispatcher::new(settings.webhooks.subscriptions.clone(), settings.webhooks.retry, http_client)
+}
+
+fn configure_breached_password_store(settings: &Settings) -> BreachedPasswordStoreType {
+    match settings.passwords.breached_passwords_path.as_deref() {
+        Some(path) => {
+            let store = HibpBreachedPasswordStore::new(path)
+                .expect("Failed to open breached password corpus");
+            Arc::new(RwLock::new(store))
+        }
+        None => {
+            tracing::warn!("passwords.breached_passwords_path is not set, breached password check is disabled");
+            Arc::new(RwLock::new(HashsetBreachedPasswordStore::new()))
+        }
+    }
+}
+
+fn configure_email_domain_policy(settings: &Settings) -> EmailDomainPolicy {
+    let mut policy = settings.signup.email_domain_policy.to_owned();
+
+    if let Some(path) = settings.signup.disposable_email_domains_path.as_deref() {
+        policy.disposable_domains = load_disposable_domains(path)
+            .expect("Failed to load disposable email domains");
+        tracing::info!("loaded {} disposable email domains", policy.disposable_domains.len());
+    }
+
+    policy
+}
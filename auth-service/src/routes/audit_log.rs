@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{AuditEvent, AuditQuery},
+        email::Email,
+        error::AuthAPIError,
+        user::UserRole,
+    },
+    utils::auth::get_authenticated_user,
+};
+
+#[derive(Deserialize)]
+pub struct AuditLogRequest {
+    pub email: Option<Secret<String>>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditLogResponse {
+    pub events: Vec<AuditEvent>,
+}
+
+// Admins may query any user (or everyone when `email` is omitted),
+// everybody else only ever sees their own events
+#[tracing::instrument(name = "Audit log", skip_all)]
+pub async fn audit_log(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(request): Query<AuditLogRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = get_authenticated_user(&jar, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await?;
+
+    let email = request.email
+        .map(Email::parse)
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let actor = match (user.role, email) {
+        (UserRole::Admin, email) => email,
+        (UserRole::User, None) => Some(user.email),
+        (UserRole::User, Some(email)) if email == user.email => Some(email),
+        (UserRole::User, Some(_)) => return Err(AuthAPIError::InsufficientPermissions),
+    };
+
+    let query = AuditQuery {
+        actor: actor.map(|email| email.as_ref().expose_secret().to_owned()),
+        from: request.from,
+        to: request.to,
+        limit: request.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+    };
+
+    let events = state.audit_log_store
+        .read()
+        .await
+        .query(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((StatusCode::OK, Json(AuditLogResponse { events })))
+}
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
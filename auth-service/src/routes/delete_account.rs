@@ -1,42 +1,73 @@
-// use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-// use serde::{Deserialize, Serialize};
-
-// use crate::app_state::app_state::AppState;
-// use crate::domain::{email::Email, error::AuthAPIError};
-// use crate::utils::auth::validate_token;
-
-// #[derive(Deserialize)]
-// pub struct DeleteRequest {
-//     pub token: String,
-// }
-
-// #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
-// pub struct DeleteResponse {
-//     pub message: String,
-// }
-
-// pub async fn delete_account(
-//     State(state): State<AppState>,
-//     Json(request): Json<DeleteRequest>,
-// ) -> Result<impl IntoResponse, AuthAPIError> {
-//     let token = request.token;
-//     let claims = match validate_token(&token, state.token_store).await {
-//         Ok(claims) => claims,
-//         Err(_e) => return Err(AuthAPIError::InvalidToken)
-//     };
-//     let email = claims.sub;
-
-//     let email = Email::parse(&email)
-//         .map_err(|_| AuthAPIError::InvalidCredentials)?;
-
-//     let mut user_store = state.user_store.write().await;
-//     if let Err(_e) = user_store.delete_user(&email).await {
-//         return Err(AuthAPIError::UnexpectedError);
-//     }
-
-//     let response = Json(DeleteResponse {
-//         message: "User deleted successfully!".to_string(),
-//     });
-
-//     Ok((StatusCode::OK, response))
-// }
\ No newline at end of file
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{AuditEventType, UserStoreError},
+        email::Email,
+        error::AuthAPIError,
+        security_event::{SecurityEvent, SecurityEventType},
+    },
+    utils::{audit::record_audit_event, auth::validate_token, request_context::RequestContext},
+};
+
+#[derive(Deserialize)]
+pub struct DeleteRequest {
+    pub token: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeleteResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Delete account", skip_all)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    ctx: RequestContext,
+    Json(request): Json<DeleteRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = delete_user(&state, request).await;
+
+    let actor = result.as_ref().ok().map(|email| email.as_ref().expose_secret().to_owned());
+    if let Some(actor) = &actor {
+        state.webhook_dispatcher.dispatch(SecurityEvent::new(SecurityEventType::AccountDeleted, actor.clone()));
+    }
+
+    let event = ctx.audit_event(AuditEventType::DeleteAccount, actor, &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    result?;
+
+    let response = Json(DeleteResponse {
+        message: "User deleted successfully!".to_string(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+async fn delete_user(state: &AppState, request: DeleteRequest) -> Result<Email, AuthAPIError> {
+    let claims = validate_token(&request.token, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await?;
+
+    let email = Email::parse(Secret::new(claims.sub))
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    match state.user_store.write().await.delete_user(&email).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // The token must not outlive the account it was issued for
+    state.token_store
+        .write()
+        .await
+        .add_token(request.token)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state.metrics.token_banned();
+
+    Ok(email)
+}
@@ -0,0 +1,109 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::app_state::{AppState, DevMailboxType},
+    domain::{email::Email, error::AuthAPIError},
+    services::data_stores::DevEmail,
+};
+
+#[derive(Deserialize)]
+pub struct DevInboxRequest {
+    pub email: Option<Secret<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevInboxEmail {
+    pub id: Uuid,
+    pub recipient: String,
+    pub subject: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+impl From<DevEmail> for DevInboxEmail {
+    fn from(email: DevEmail) -> Self {
+        Self {
+            id: email.id,
+            recipient: email.recipient.as_ref().expose_secret().to_owned(),
+            subject: email.message.subject,
+            sent_at: email.sent_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevInboxResponse {
+    pub emails: Vec<DevInboxEmail>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevEmailResponse {
+    pub id: Uuid,
+    pub recipient: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+impl From<DevEmail> for DevEmailResponse {
+    fn from(email: DevEmail) -> Self {
+        Self {
+            id: email.id,
+            recipient: email.recipient.as_ref().expose_secret().to_owned(),
+            subject: email.message.subject,
+            html_body: email.message.html_body,
+            text_body: email.message.text_body,
+            sent_at: email.sent_at,
+        }
+    }
+}
+
+// Lists the emails caught by the dev email provider, only routed when it is in use
+#[tracing::instrument(name = "Dev inbox", skip_all)]
+pub async fn dev_inbox(
+    State(state): State<AppState>,
+    Query(request): Query<DevInboxRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let mailbox = dev_mailbox(&state)?;
+
+    let recipient = request.email
+        .map(Email::parse)
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let emails = mailbox.read().await.list(recipient.as_ref());
+
+    let response = DevInboxResponse {
+        emails: emails.into_iter().map(DevInboxEmail::from).collect(),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// Shows one caught email with both bodies
+#[tracing::instrument(name = "Dev inbox email", skip_all)]
+pub async fn dev_inbox_email(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let mailbox = dev_mailbox(&state)?;
+
+    let email = mailbox.read().await.get(id).ok_or(AuthAPIError::EmailNotFound)?;
+
+    Ok((StatusCode::OK, Json(DevEmailResponse::from(email))))
+}
+
+fn dev_mailbox(state: &AppState) -> Result<&DevMailboxType, AuthAPIError> {
+    state.dev_mailbox.as_ref().ok_or(AuthAPIError::EmailNotFound)
+}
@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{DeliveryStatus, OutboxEmail, OutboxQuery},
+        email::Email,
+        error::AuthAPIError,
+        user::UserRole,
+    },
+    utils::auth::get_authenticated_user,
+};
+
+#[derive(Deserialize)]
+pub struct EmailOutboxRequest {
+    pub email: Option<Secret<String>>,
+    pub status: Option<DeliveryStatus>,
+    pub limit: Option<i64>,
+}
+
+// Delivery state of an outbox email, the bodies are left out as they carry login codes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEmailStatus {
+    pub id: Uuid,
+    pub recipient: String,
+    pub subject: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+impl From<OutboxEmail> for OutboxEmailStatus {
+    fn from(email: OutboxEmail) -> Self {
+        Self {
+            id: email.id,
+            recipient: email.recipient.as_ref().expose_secret().to_owned(),
+            subject: email.message.subject,
+            status: email.status,
+            attempts: email.attempts,
+            last_error: email.last_error,
+            created_at: email.created_at,
+            next_attempt_at: email.next_attempt_at,
+            sent_at: email.sent_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailOutboxResponse {
+    pub emails: Vec<OutboxEmailStatus>,
+}
+
+// Lets admins see why an email did not arrive
+#[tracing::instrument(name = "Email outbox", skip_all)]
+pub async fn email_outbox(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(request): Query<EmailOutboxRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user = get_authenticated_user(&jar, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await?;
+
+    if user.role != UserRole::Admin {
+        return Err(AuthAPIError::InsufficientPermissions);
+    }
+
+    let recipient = request.email
+        .map(Email::parse)
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let query = OutboxQuery {
+        recipient,
+        status: request.status,
+        limit: request.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+    };
+
+    let emails = state.email_outbox
+        .read()
+        .await
+        .query(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = EmailOutboxResponse {
+        emails: emails.into_iter().map(OutboxEmailStatus::from).collect(),
+    };
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
@@ -0,0 +1,116 @@
+use std::{fmt::Debug, future::Future, time::{Duration, Instant}};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::app_state::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    // Only a non-critical dependency is down, requests are still served
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LivenessResponse {
+    pub status: HealthStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyHealth {
+    pub name: String,
+    pub status: HealthStatus,
+    // Requests can't be served while a critical dependency is down
+    pub critical: bool,
+    pub latency_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    pub status: HealthStatus,
+    pub dependencies: Vec<DependencyHealth>,
+}
+
+// The process is up and serving requests, dependencies are not checked
+pub async fn health_live() -> impl IntoResponse {
+    (StatusCode::OK, Json(LivenessResponse { status: HealthStatus::Up }))
+}
+
+// Probes every dependency concurrently, 503 when a critical one is down
+#[tracing::instrument(name = "Readiness check", skip_all)]
+pub async fn health_ready(State(state): State<AppState>) -> impl IntoResponse {
+    let settings = &state.settings.health;
+
+    let (postgres, banned_tokens, two_fa_codes, email) = tokio::join!(
+        probe("postgres", true, settings.timeout, async {
+            state.user_store.read().await.health_check().await
+        }),
+        probe("redis_banned_tokens", true, settings.timeout, async {
+            state.token_store.read().await.health_check().await
+        }),
+        probe("redis_two_fa_codes", true, settings.timeout, async {
+            state.two_fa_code_store.read().await.health_check().await
+        }),
+        async {
+            // Emails wait in the outbox while the provider is down, so it is never critical
+            match settings.check_email_provider {
+                true => Some(probe("email", false, settings.timeout, async {
+                    state.email_client.read().await.health_check().await
+                }).await),
+                false => None,
+            }
+        },
+    );
+
+    let dependencies: Vec<DependencyHealth> = [postgres, banned_tokens, two_fa_codes]
+        .into_iter()
+        .chain(email)
+        .collect();
+
+    // Whether any dependency that is down is critical, None when everything is up
+    let down_is_critical = dependencies
+        .iter()
+        .filter(|dependency| dependency.status == HealthStatus::Down)
+        .map(|dependency| dependency.critical)
+        .max();
+
+    let (status_code, status) = match down_is_critical {
+        None => (StatusCode::OK, HealthStatus::Up),
+        Some(false) => (StatusCode::OK, HealthStatus::Degraded),
+        Some(true) => (StatusCode::SERVICE_UNAVAILABLE, HealthStatus::Down),
+    };
+
+    (status_code, Json(ReadinessResponse { status, dependencies }))
+}
+
+async fn probe<E: Debug>(
+    name: &str,
+    critical: bool,
+    timeout: Duration,
+    check: impl Future<Output = Result<(), E>>,
+) -> DependencyHealth {
+    let start = Instant::now();
+
+    let status = match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(())) => HealthStatus::Up,
+        Ok(Err(e)) => {
+            tracing::warn!("{} health check failed: {:?}", name, e);
+            HealthStatus::Down
+        }
+        Err(_) => {
+            tracing::warn!("{} health check timed out after {:?}", name, timeout);
+            HealthStatus::Down
+        }
+    };
+
+    DependencyHealth {
+        name: name.to_owned(),
+        status,
+        critical,
+        latency_ms: u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+    }
+}
@@ -0,0 +1,191 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Duration, Utc};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{AuditEventType, Invitation, InvitationCode, InvitationStoreError, OutboxEmail},
+        email::Email,
+        error::AuthAPIError,
+        locale::Locale,
+        user::{User, UserRole},
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::get_authenticated_user,
+        email_templates::{EmailTemplate, InvitationEmail},
+        request_context::RequestContext,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct CreateInvitationRequest {
+    // Binds the invitation to this address and emails the code to it
+    pub email: Option<Secret<String>>,
+    #[serde(rename = "maxUses")]
+    pub max_uses: Option<i32>,
+    #[serde(rename = "expiresInHours")]
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CreateInvitationResponse {
+    pub code: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "maxUses")]
+    pub max_uses: i32,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeInvitationRequest {
+    pub code: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RevokeInvitationResponse {
+    pub message: String,
+}
+
+// Any active user may invite someone, but only admins may hand out multi-use invitations
+#[tracing::instrument(name = "Create invitation", skip_all)]
+pub async fn create_invitation(
+    State(state): State<AppState>,
+    ctx: RequestContext,
+    jar: CookieJar,
+    Json(request): Json<CreateInvitationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = match get_authenticated_user(&jar, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await {
+        Ok(user) => add_invitation(&state, &user, request, ctx.locale).await.map(|invitation| (user, invitation)),
+        Err(e) => Err(e),
+    };
+
+    let actor = result.as_ref().ok().map(|(user, _)| user.email.as_ref().expose_secret().to_owned());
+    let event = ctx.audit_event(AuditEventType::CreateInvitation, actor, &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    let (_, invitation) = result?;
+
+    let response = Json(CreateInvitationResponse {
+        code: invitation.code.as_ref().expose_secret().to_owned(),
+        expires_at: invitation.expires_at,
+        max_uses: invitation.max_uses,
+    });
+
+    Ok((StatusCode::CREATED, response))
+}
+
+async fn add_invitation(
+    state: &AppState,
+    user: &User,
+    request: CreateInvitationRequest,
+    request_locale: Option<Locale>,
+) -> Result<Invitation, AuthAPIError> {
+    let email = request.email
+        .map(Email::parse)
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let max_uses = request.max_uses.unwrap_or(1);
+    let expires_in_hours = request.expires_in_hours.unwrap_or(DEFAULT_EXPIRY_HOURS);
+
+    if max_uses < 1 || !(1..=MAX_EXPIRY_HOURS).contains(&expires_in_hours) {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    if max_uses > 1 && user.role != UserRole::Admin {
+        return Err(AuthAPIError::InsufficientPermissions);
+    }
+
+    let expires_at = Utc::now() + Duration::hours(expires_in_hours);
+    let invitation = Invitation::new(user.email.clone(), email, expires_at, max_uses);
+
+    state.invitation_store
+        .write()
+        .await
+        .add_invitation(invitation.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // The invitee has no account yet, so write in the language the inviter uses
+    if let Some(email) = &invitation.email {
+        let message = InvitationEmail {
+            inviter: user.email.as_ref().expose_secret(),
+            code: invitation.code.as_ref().expose_secret(),
+            expires_at: invitation.expires_at,
+        }
+        .render(&state.settings.email.branding, user.locale.or(request_locale).unwrap_or_default())
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+        state.email_outbox
+            .write()
+            .await
+            .enqueue(OutboxEmail { expires_at: Some(invitation.expires_at), ..OutboxEmail::new(email.clone(), message) })
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(invitation)
+}
+
+// Inviters may revoke their own invitations, admins any invitation
+#[tracing::instrument(name = "Revoke invitation", skip_all)]
+pub async fn revoke_invitation(
+    State(state): State<AppState>,
+    ctx: RequestContext,
+    jar: CookieJar,
+    Json(request): Json<RevokeInvitationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = match get_authenticated_user(&jar, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await {
+        Ok(user) => remove_invitation(&state, &user, request).await.map(|()| user),
+        Err(e) => Err(e),
+    };
+
+    let actor = result.as_ref().ok().map(|user| user.email.as_ref().expose_secret().to_owned());
+    let event = ctx.audit_event(AuditEventType::RevokeInvitation, actor, &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    result?;
+
+    let response = Json(RevokeInvitationResponse {
+        message: "Invitation revoked".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+async fn remove_invitation(state: &AppState, user: &User, request: RevokeInvitationRequest) -> Result<(), AuthAPIError> {
+    let code = InvitationCode::parse(request.code)
+        .map_err(|_| AuthAPIError::InvalidInvitation)?;
+
+    let mut invitation_store = state.invitation_store.write().await;
+
+    let invitation = invitation_store
+        .get_invitation(&code)
+        .await
+        .map_err(map_invitation_store_error)?;
+
+    if user.role != UserRole::Admin && invitation.inviter != user.email {
+        return Err(AuthAPIError::InsufficientPermissions);
+    }
+
+    invitation_store
+        .revoke_invitation(&code)
+        .await
+        .map_err(map_invitation_store_error)
+}
+
+pub(crate) fn map_invitation_store_error(e: InvitationStoreError) -> AuthAPIError {
+    match e {
+        InvitationStoreError::InvitationNotFound | InvitationStoreError::InvitationNotUsable => {
+            AuthAPIError::InvalidInvitation
+        }
+        InvitationStoreError::UnexpectedError(e) => AuthAPIError::UnexpectedError(e),
+    }
+}
+
+const DEFAULT_EXPIRY_HOURS: i64 = 7 * 24;
+const MAX_EXPIRY_HOURS: i64 = 30 * 24;
@@ -1,13 +1,21 @@
 use crate::{
     app_state::app_state::AppState, 
     domain::{
-        data_stores::{LoginAttemptId, TwoFACode, UserStoreError}, email::Email, error::AuthAPIError, password::Password
+        data_stores::{AuditEventType, AuditOutcome, LoginAttemptId, OutboxEmail, TwoFACode, UserStoreError}, email::Email, error::AuthAPIError, locale::Locale, password::Password,
+        security_event::{SecurityEvent, SecurityEventType},
+    },
+    utils::{
+        audit::{record_audit_event, request_actor},
+        auth::{check_account_status, generate_auth_cookie, resolve_organisation, OrgClaim},
+        email_templates::{EmailTemplate, TwoFACodeEmail},
+        metrics::TwoFAEvent,
+        request_context::RequestContext,
     },
-    utils::auth::generate_auth_cookie,
 };
 
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use secrecy::{ExposeSecret, Secret};
 
@@ -15,6 +23,8 @@ use secrecy::{ExposeSecret, Secret};
 pub struct LoginRequest {
     pub email: Secret<String>,
     pub password: Secret<String>,
+    // Slug of the organisation to log in to, only needed by members of more than one
+    pub organisation: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -34,13 +44,40 @@ pub struct TwoFactorAuthResponse {
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
+    ctx: RequestContext,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let actor = request_actor(&request.email);
+    let result = handle_login(&state, jar, request, ctx.locale.unwrap_or_default()).await;
+
+    // With 2FA enabled the login only completes once the code is verified
+    match &result {
+        Ok((_, (_, Json(LoginResponse::RegularAuth)))) => {
+            state.webhook_dispatcher.dispatch(SecurityEvent::new(SecurityEventType::LoggedIn, actor.clone()));
+        }
+        Err(AuthAPIError::AccountSuspended) => {
+            state.webhook_dispatcher.dispatch(SecurityEvent::new(SecurityEventType::LoginRejectedSuspended, actor.clone()));
+        }
+        _ => {}
+    }
+
+    let mut event = ctx.audit_event(AuditEventType::Login, Some(actor), &result);
+    if let Ok((_, (_, Json(LoginResponse::TwoFactorAuth(_))))) = &result {
+        event.outcome = AuditOutcome::TwoFactorRequired;
+        state.metrics.two_fa(TwoFAEvent::Sent);
+    }
+    state.metrics.logged_in(&event.outcome);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    result
+}
+
+async fn handle_login(state: &AppState, jar: CookieJar, request: LoginRequest, request_locale: Locale) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthAPIError> {
     let email = Email::parse(request.email)
         .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let pwd = Password::parse(request.password)
+    let pwd = Password::parse_credential(request.password)
         .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
     let user_store = &state.user_store.read().await;
@@ -48,20 +85,27 @@ pub async fn login(
         .map_err(|_| AuthAPIError::IncorrectCredentials)?;
 
     match user_store.get_user(&email).await {
-        Ok(user) => match user.requires_2fa {
-            true => handle_2fa(&email, &state, jar).await,
-            false => handle_no_2fa(&email, jar).await,
-        },
+        Ok(user) => {
+            check_account_status(user.status)?;
+
+            // Resolved before sending a 2FA code so a wrong organisation fails early
+            let org = resolve_organisation(&**user_store, &email, request.organisation.as_deref()).await?;
+
+            match user.requires_2fa {
+                true => handle_2fa(&email, user.locale.unwrap_or(request_locale), state, jar).await,
+                false => handle_no_2fa(&email, org, state, jar).await,
+            }
+        }
         Err(e) => match e {
-            UserStoreError::UserNotFound | UserStoreError::InvalidCredentials => return Err(AuthAPIError::InvalidCredentials),
-            e => return Err(AuthAPIError::UnexpectedError(e.into()))
+            UserStoreError::UserNotFound | UserStoreError::InvalidCredentials => Err(AuthAPIError::InvalidCredentials),
+            e => Err(AuthAPIError::UnexpectedError(e.into()))
         }
     }
 }
 
 #[tracing::instrument(name = "handle_no_2fa", skip_all)]
-async fn handle_no_2fa(email: &Email, jar: CookieJar) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthAPIError> {
-    let auth_cookie = generate_auth_cookie(&email)
+async fn handle_no_2fa(email: &Email, org: Option<OrgClaim>, state: &AppState, jar: CookieJar) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthAPIError> {
+    let auth_cookie = generate_auth_cookie(&email, org, &state.settings.auth)
         .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
 
     let updated_jar = jar.add(auth_cookie);
@@ -71,7 +115,7 @@ async fn handle_no_2fa(email: &Email, jar: CookieJar) -> Result<(CookieJar, (Sta
 }
 
 #[tracing::instrument(name = "handle_2fa", skip_all)]
-async fn handle_2fa(email: &Email, state: &AppState, jar: CookieJar) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthAPIError> {
+async fn handle_2fa(email: &Email, locale: Locale, state: &AppState, jar: CookieJar) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthAPIError> {
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
 
@@ -80,10 +124,25 @@ async fn handle_2fa(email: &Email, state: &AppState, jar: CookieJar) -> Result<(
         .await
         .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
 
-    let email_client = state.email_client.read().await;
-    email_client.send_email(email, "2fa subject", two_fa_code.as_ref().expose_secret())
+    let message = TwoFACodeEmail {
+        code: two_fa_code.as_ref().expose_secret(),
+        valid_for_minutes: state.settings.two_fa.code_ttl.as_secs() / 60,
+    }
+    .render(&state.settings.email.branding, locale)
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    // Delivered by the outbox worker, so a slow email provider doesn't hold up the login. There
+    // is no point in retrying once the code has expired.
+    let outbox_email = OutboxEmail {
+        expires_at: Some(Utc::now() + state.settings.two_fa.code_ttl),
+        ..OutboxEmail::new(email.clone(), message)
+    };
+    state.email_outbox
+        .write()
         .await
-        .map_err(|e| AuthAPIError::UnexpectedError(e))?;
+        .enqueue(outbox_email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
 
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
@@ -4,21 +4,40 @@ use secrecy::Secret;
 
 use crate::{
     app_state::app_state::AppState, 
-    domain::error::AuthAPIError, 
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME}
+    domain::{data_stores::AuditEventType, error::AuthAPIError}, 
+    utils::{
+        audit::record_audit_event,
+        auth::{validate_token, Claims},
+        constants::JWT_COOKIE_NAME,
+        request_context::RequestContext,
+    }
 };
 
 #[tracing::instrument(name = "Logout", skip_all)]
 pub async fn logout(
     State(state): State<AppState>,
+    ctx: RequestContext,
     jar: CookieJar,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let result = ban_token(&state, &jar).await;
+
+    let actor = result.as_ref().ok().map(|claims| claims.sub.clone());
+    let event = ctx.audit_event(AuditEventType::Logout, actor, &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    result?;
+
+    let jar = jar.remove(Cookie::from(JWT_COOKIE_NAME));
+
+    Ok((jar, StatusCode::OK))
+}
+
+async fn ban_token(state: &AppState, jar: &CookieJar) -> Result<Claims, AuthAPIError> {
     let cookie = jar.get(JWT_COOKIE_NAME)
         .ok_or(AuthAPIError::MissingToken)?;
 
     let token = Secret::new(cookie.value().to_owned());
-    validate_token(&token, state.token_store.clone()).await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
+    let claims = validate_token(&token, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await?;
 
     state.token_store
         .write()
@@ -26,8 +45,7 @@ pub async fn logout(
         .add_token(token)
         .await
         .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state.metrics.token_banned();
 
-    let jar = jar.remove(Cookie::from(JWT_COOKIE_NAME));
-
-    Ok((jar, StatusCode::OK))
-}
\ No newline at end of file
+    Ok(claims)
+}
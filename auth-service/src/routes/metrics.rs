@@ -0,0 +1,13 @@
+use axum::{extract::State, http::header, response::IntoResponse};
+
+use crate::{app_state::app_state::AppState, domain::error::AuthAPIError};
+
+// Prometheus text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+#[tracing::instrument(name = "Metrics", skip_all)]
+pub async fn metrics(State(state): State<AppState>) -> Result<impl IntoResponse, AuthAPIError> {
+    let body = state.metrics.render().map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok(([(header::CONTENT_TYPE, CONTENT_TYPE)], body))
+}
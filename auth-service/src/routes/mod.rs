@@ -3,11 +3,27 @@ mod logout;
 mod signup;
 mod verify_2fa;
 mod verify_token;
-// mod delete_account;
+mod audit_log;
+mod email_outbox;
+mod dev_inbox;
+mod delete_account;
+mod invitations;
+mod organisations;
+mod set_locale;
+mod health;
+mod metrics;
 
 pub use login::*;
 pub use logout::*;
 pub use signup::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
-// pub use delete_account::*;
\ No newline at end of file
+pub use audit_log::*;
+pub use email_outbox::*;
+pub use dev_inbox::*;
+pub use delete_account::*;
+pub use invitations::*;
+pub use organisations::*;
+pub use set_locale::*;
+pub use health::*;
+pub use metrics::*;
\ No newline at end of file
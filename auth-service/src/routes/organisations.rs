@@ -0,0 +1,135 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{AuditEventType, UserStoreError},
+        email::Email,
+        error::AuthAPIError,
+        organisation::{parse_slug, OrgRole, Organisation},
+        user::{User, UserRole},
+    },
+    utils::{audit::record_audit_event, auth::get_authenticated_user, request_context::RequestContext},
+};
+
+#[derive(Deserialize)]
+pub struct CreateOrganisationRequest {
+    pub slug: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetMembershipRequest {
+    pub organisation: String,
+    pub email: Secret<String>,
+    #[serde(default)]
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SetMembershipResponse {
+    pub message: String,
+}
+
+// Only admins of the service itself may create organisations
+#[tracing::instrument(name = "Create organisation", skip_all)]
+pub async fn create_organisation(
+    State(state): State<AppState>,
+    ctx: RequestContext,
+    jar: CookieJar,
+    Json(request): Json<CreateOrganisationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = match get_authenticated_user(&jar, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await {
+        Ok(user) => add_organisation(&state, &user, request).await.map(|organisation| (user, organisation)),
+        Err(e) => Err(e),
+    };
+
+    let actor = result.as_ref().ok().map(|(user, _)| user.email.as_ref().expose_secret().to_owned());
+    let event = ctx.audit_event(AuditEventType::CreateOrganisation, actor, &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    let (_, organisation) = result?;
+
+    Ok((StatusCode::CREATED, Json(organisation)))
+}
+
+async fn add_organisation(state: &AppState, user: &User, request: CreateOrganisationRequest) -> Result<Organisation, AuthAPIError> {
+    if user.role != UserRole::Admin {
+        return Err(AuthAPIError::InsufficientPermissions);
+    }
+
+    let organisation = Organisation::new(&request.slug, request.name)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    match state.user_store.write().await.add_organisation(organisation.clone()).await {
+        Ok(()) => Ok(organisation),
+        Err(UserStoreError::OrganisationAlreadyExists) => Err(AuthAPIError::OrganisationAlreadyExists),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// Adds a user to an organisation or changes their role in it. Allowed for admins of the
+// service and for admins of that organisation.
+#[tracing::instrument(name = "Set organisation membership", skip_all)]
+pub async fn set_membership(
+    State(state): State<AppState>,
+    ctx: RequestContext,
+    jar: CookieJar,
+    Json(request): Json<SetMembershipRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = match get_authenticated_user(&jar, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await {
+        Ok(user) => add_member(&state, &user, request).await.map(|()| user),
+        Err(e) => Err(e),
+    };
+
+    let actor = result.as_ref().ok().map(|user| user.email.as_ref().expose_secret().to_owned());
+    let event = ctx.audit_event(AuditEventType::SetMembership, actor, &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    result?;
+
+    let response = Json(SetMembershipResponse {
+        message: "Membership updated".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+async fn add_member(state: &AppState, user: &User, request: SetMembershipRequest) -> Result<(), AuthAPIError> {
+    let email = Email::parse(request.email)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let slug = parse_slug(&request.organisation)
+        .map_err(|_| AuthAPIError::OrganisationNotFound)?;
+
+    let mut user_store = state.user_store.write().await;
+
+    let organisation = match user_store.get_organisation(&slug).await {
+        Ok(organisation) => organisation,
+        Err(UserStoreError::OrganisationNotFound) => return Err(AuthAPIError::OrganisationNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if user.role != UserRole::Admin {
+        let is_org_admin = user_store
+            .get_memberships(&user.email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+            .iter()
+            .any(|m| m.organisation.id == organisation.id && m.role == OrgRole::Admin);
+
+        if !is_org_admin {
+            return Err(AuthAPIError::InsufficientPermissions);
+        }
+    }
+
+    match user_store.set_membership(&email, organisation.id, request.role).await {
+        Ok(()) => Ok(()),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::UserNotFound),
+        Err(UserStoreError::OrganisationNotFound) => Err(AuthAPIError::OrganisationNotFound),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
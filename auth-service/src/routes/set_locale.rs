@@ -0,0 +1,68 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::app_state::AppState,
+    domain::{
+        data_stores::{AuditEventType, UserStoreError},
+        error::AuthAPIError,
+        locale::Locale,
+        user::User,
+    },
+    utils::{audit::record_audit_event, auth::get_authenticated_user, request_context::RequestContext},
+};
+
+#[derive(Deserialize)]
+pub struct SetLocaleRequest {
+    // None goes back to the language negotiated per request
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SetLocaleResponse {
+    pub message: String,
+}
+
+// The stored locale is for emails, which are sent without a request to negotiate from. API
+// responses, including this route's errors, always follow the request's Accept-Language header
+// so that the client's language wins and errors before login are localised the same way.
+#[tracing::instrument(name = "Set locale", skip_all)]
+pub async fn set_locale(
+    State(state): State<AppState>,
+    ctx: RequestContext,
+    jar: CookieJar,
+    Json(request): Json<SetLocaleRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = match get_authenticated_user(&jar, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await {
+        Ok(user) => update_locale(&state, &user, request).await.map(|()| user),
+        Err(e) => Err(e),
+    };
+
+    let actor = result.as_ref().ok().map(|user| user.email.as_ref().expose_secret().to_owned());
+    let event = ctx.audit_event(AuditEventType::SetLocale, actor, &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    result?;
+
+    let response = Json(SetLocaleResponse {
+        message: "Locale updated".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+async fn update_locale(state: &AppState, user: &User, request: SetLocaleRequest) -> Result<(), AuthAPIError> {
+    let locale = request.locale
+        .as_deref()
+        .map(Locale::parse)
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    match state.user_store.write().await.update_locale(&user.email, locale).await {
+        Ok(()) => Ok(()),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::InvalidToken),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
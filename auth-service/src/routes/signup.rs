@@ -2,7 +2,17 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use serde::{Deserialize, Serialize};
 
 use crate::app_state::app_state::AppState;
-use crate::domain::{email::Email, error::AuthAPIError, password::Password, user::User};
+use crate::domain::{
+    data_stores::{AuditEventType, InvitationCode},
+    email::Email,
+    error::AuthAPIError,
+    locale::Locale,
+    password::Password,
+    security_event::{SecurityEvent, SecurityEventType},
+    user::User,
+};
+use crate::routes::invitations::map_invitation_store_error;
+use crate::utils::{audit::{record_audit_event, request_actor}, request_context::RequestContext};
 use secrecy::Secret;
 
 #[derive(Deserialize)]
@@ -11,6 +21,11 @@ pub struct SignupRequest {
     pub password: Secret<String>,
     #[serde(rename = "requires2FA")]
     pub requires_2fa: bool,
+    // Required when signup is invite-only
+    #[serde(rename = "inviteCode")]
+    pub invite_code: Option<Secret<String>>,
+    // Language for emails, defaults to the one negotiated from Accept-Language
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -21,15 +36,63 @@ pub struct SignupResponse {
 #[tracing::instrument(name = "Signup", skip_all)]
 pub async fn signup(
     State(state): State<AppState>,
+    ctx: RequestContext,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
+    let actor = request_actor(&request.email);
+    let result = create_user(&state, request, ctx.locale).await;
+
+    if result.is_ok() {
+        state.webhook_dispatcher.dispatch(SecurityEvent::new(SecurityEventType::SignedUp, actor.clone()));
+        state.metrics.signed_up();
+    }
+
+    let event = ctx.audit_event(AuditEventType::Signup, Some(actor), &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    result
+}
+
+async fn create_user(state: &AppState, request: SignupRequest, request_locale: Option<Locale>) -> Result<(StatusCode, Json<SignupResponse>), AuthAPIError> {
     let email = Email::parse(request.email)
         .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let pwd = Password::parse(request.password)
-        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let locale = match request.locale {
+        Some(locale) => Some(Locale::parse(&locale).map_err(|_| AuthAPIError::InvalidCredentials)?),
+        None => request_locale,
+    };
+
+    let invite_code = request.invite_code
+        .map(InvitationCode::parse)
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidInvitation)?;
+
+    if state.settings.signup.invite_only && invite_code.is_none() {
+        return Err(AuthAPIError::InvitationRequired);
+    }
+
+    state.email_domain_policy
+        .check(&email)
+        .map_err(AuthAPIError::EmailDomainRejected)?;
 
-    let user = User::new(email, pwd, request.requires_2fa);
+    let pwd = Password::parse(request.password, &state.settings.passwords.policy, &email)
+        .map_err(AuthAPIError::PasswordPolicyViolation)?;
+
+    let breached = state.breached_password_store
+        .read()
+        .await
+        .contains_password(&pwd)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if breached {
+        return Err(AuthAPIError::BreachedPassword);
+    }
+
+    let user = User {
+        locale,
+        ..User::new(email, pwd, request.requires_2fa)
+    };
 
     let mut user_store = state.user_store.write().await;
 
@@ -56,9 +119,25 @@ pub async fn signup(
         return Err(AuthAPIError::UserAlreadyExists);
     }
 
-    user_store.add_user(user)
-        .await
-        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    // Redeemed last so that a signup rejected for any other reason does not use up the invitation
+    if let Some(code) = &invite_code {
+        state.invitation_store
+            .write()
+            .await
+            .redeem_invitation(code, &user.email)
+            .await
+            .map_err(map_invitation_store_error)?;
+    }
+
+    if let Err(e) = user_store.add_user(user).await {
+        // The user was never created, so the invitation must stay usable
+        if let Some(code) = &invite_code {
+            if let Err(e) = state.invitation_store.write().await.release_invitation(code).await {
+                tracing::error!("failed to release invitation after failed signup: {:?}", e);
+            }
+        }
+        return Err(AuthAPIError::UnexpectedError(e.into()));
+    }
 
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
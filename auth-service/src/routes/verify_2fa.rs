@@ -6,9 +6,16 @@ use secrecy::{ExposeSecret, Secret};
 use crate::{
     app_state::app_state::AppState, 
     domain::{
-        data_stores::{LoginAttemptId, TwoFACode}, 
-        email::Email, error::AuthAPIError
-    }, utils::auth::generate_auth_cookie
+        data_stores::{AuditEventType, LoginAttemptId, TwoFACode, UserStoreError}, 
+        email::Email, error::AuthAPIError,
+        security_event::{SecurityEvent, SecurityEventType},
+    }, 
+    utils::{
+        audit::{record_audit_event, request_actor},
+        auth::{check_account_status, generate_auth_cookie, resolve_organisation},
+        metrics::TwoFAEvent,
+        request_context::RequestContext,
+    },
 };
 
 #[derive(Clone, Debug, Deserialize)]
@@ -18,6 +25,8 @@ pub struct Verify2FARequest {
     login_attempt_id: Secret<String>,
     #[serde(rename = "2FACode")]
     two_fa_code: Secret<String>,
+    // Same as for the login that sent the code
+    organisation: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,9 +39,35 @@ pub struct Verify2FAResponse {
 #[tracing::instrument(name = "verify_2fa", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    ctx: RequestContext,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let actor = request_actor(&request.email);
+    let result = handle_verify_2fa(&state, jar, request).await;
+
+    state.metrics.two_fa(match result {
+        Ok(_) => TwoFAEvent::Verified,
+        Err(_) => TwoFAEvent::Failed,
+    });
+
+    match &result {
+        Ok(_) => {
+            state.webhook_dispatcher.dispatch(SecurityEvent::new(SecurityEventType::LoggedIn, actor.clone()));
+        }
+        Err(AuthAPIError::AccountSuspended) => {
+            state.webhook_dispatcher.dispatch(SecurityEvent::new(SecurityEventType::LoginRejectedSuspended, actor.clone()));
+        }
+        _ => {}
+    }
+
+    let event = ctx.audit_event(AuditEventType::Verify2FA, Some(actor), &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    result
+}
+
+async fn handle_verify_2fa(state: &AppState, jar: CookieJar, request: Verify2FARequest) -> Result<(CookieJar, StatusCode), AuthAPIError> {
     let email = Email::parse(request.email)
         .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
@@ -52,7 +87,19 @@ pub async fn verify_2fa(
         return Err(AuthAPIError::IncorrectCredentials);
     }
 
-    let cookie = generate_auth_cookie(&email)
+    // The account may have been suspended between login and 2FA verification
+    let user_store = state.user_store.read().await;
+    let user = match user_store.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    check_account_status(user.status)?;
+
+    let org = resolve_organisation(&*user_store, &email, request.organisation.as_deref()).await?;
+    drop(user_store);
+
+    let cookie = generate_auth_cookie(&email, org, &state.settings.auth)
         .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
     let updated_jar = jar.add(cookie);
 
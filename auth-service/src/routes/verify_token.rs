@@ -3,8 +3,8 @@ use secrecy::Secret;
 use serde::Deserialize;
 
 use crate::{
-    domain::error::AuthAPIError, 
-    utils::auth::validate_token, 
+    domain::{data_stores::AuditEventType, error::AuthAPIError}, 
+    utils::{audit::record_audit_event, auth::validate_token, request_context::RequestContext}, 
     app_state::app_state::AppState
 };
 
@@ -16,10 +16,16 @@ pub struct VerifyTokenRequest {
 #[tracing::instrument(name = "verify_token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
+    ctx: RequestContext,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    match validate_token(&request.token, state.token_store.clone()).await {
-        Ok(_) => Ok(StatusCode::OK),
-        Err(_) => Err(AuthAPIError::InvalidToken),
-    }
-}
\ No newline at end of file
+    let result = validate_token(&request.token, &state.settings.auth, state.token_store.clone(), state.user_store.clone()).await;
+
+    let actor = result.as_ref().ok().map(|claims| claims.sub.clone());
+    let event = ctx.audit_event(AuditEventType::VerifyToken, actor, &result);
+    record_audit_event(&state.audit_log_store, event).await;
+
+    result?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,198 @@
+use std::{collections::VecDeque, path::{Path, PathBuf}};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Context, Result};
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+
+use crate::{
+    app_state::app_state::DevMailboxType,
+    domain::{email::Email, email_client::{EmailClient, EmailMessage}},
+    services::data_stores::mime_message,
+};
+
+// An email caught by the dev email client
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevEmail {
+    pub id: Uuid,
+    pub recipient: Email,
+    pub message: EmailMessage,
+    pub sent_at: DateTime<Utc>,
+}
+
+// The emails sent since startup, only the most recent `MAX_EMAILS` are kept
+#[derive(Default)]
+pub struct DevMailbox {
+    emails: VecDeque<DevEmail>,
+}
+
+impl DevMailbox {
+    pub fn push(&mut self, email: DevEmail) {
+        if self.emails.len() >= MAX_EMAILS {
+            self.emails.pop_front();
+        }
+        self.emails.push_back(email);
+    }
+
+    // Newest first, optionally only the emails sent to `recipient`
+    pub fn list(&self, recipient: Option<&Email>) -> Vec<DevEmail> {
+        self.emails
+            .iter()
+            .rev()
+            .filter(|email| recipient.iter().all(|recipient| email.recipient == **recipient))
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<DevEmail> {
+        self.emails.iter().find(|email| email.id == id).cloned()
+    }
+}
+
+const MAX_EMAILS: usize = 500;
+
+// Sends nothing: every email is put into the dev mailbox and, with a directory, also saved as an
+// .eml file that mail clients can open. Lets 2FA codes be read during local development.
+pub struct DevEmailClient {
+    sender: Email,
+    mailbox: DevMailboxType,
+    directory: Option<PathBuf>,
+}
+
+impl DevEmailClient {
+    pub fn new(sender: Email, mailbox: DevMailboxType, directory: Option<PathBuf>) -> Self {
+        Self { sender, mailbox, directory }
+    }
+
+    async fn write_eml(&self, directory: &Path, email: &DevEmail) -> Result<PathBuf> {
+        let eml = mime_message(&self.sender, &email.recipient, &email.message)?.formatted();
+
+        tokio::fs::create_dir_all(directory)
+            .await
+            .wrap_err_with(|| format!("Failed to create dev mailbox directory {}", directory.display()))?;
+
+        let path = directory.join(format!("{}-{}.eml", email.sent_at.format("%Y%m%dT%H%M%S"), email.id));
+        tokio::fs::write(&path, eml)
+            .await
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(path)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for DevEmailClient {
+    #[tracing::instrument(name = "Saving email to dev mailbox", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let email = DevEmail {
+            id: Uuid::new_v4(),
+            recipient: recipient.clone(),
+            message: message.clone(),
+            sent_at: Utc::now(),
+        };
+
+        if let Some(directory) = &self.directory {
+            let path = self.write_eml(directory, &email).await?;
+            tracing::info!("email to {} saved to {}", recipient.as_ref().expose_secret(), path.display());
+        }
+
+        self.mailbox.write().await.push(email);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use secrecy::Secret;
+    use tokio::sync::RwLock;
+
+    use super::*;
+
+    fn email(input: &str) -> Email {
+        Email::parse(Secret::new(input.to_owned())).unwrap()
+    }
+
+    fn message(subject: &str) -> EmailMessage {
+        EmailMessage {
+            subject: subject.to_owned(),
+            html_body: "<p>Your code is 123456</p>".to_owned(),
+            text_body: "Your code is 123456".to_owned(),
+        }
+    }
+
+    fn dev_email(recipient: &str, subject: &str) -> DevEmail {
+        DevEmail { id: Uuid::new_v4(), recipient: email(recipient), message: message(subject), sent_at: Utc::now() }
+    }
+
+    #[tokio::test]
+    async fn send_email_writes_an_eml_file() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let mailbox: DevMailboxType = Arc::new(RwLock::new(DevMailbox::default()));
+        let email_client = DevEmailClient::new(email("sender@example.com"), mailbox.clone(), Some(directory.clone()));
+
+        email_client.send_email(&email("user@example.com"), &message("Login code")).await.unwrap();
+
+        let emails = mailbox.read().await.list(None);
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].message, message("Login code"));
+
+        let files: Vec<PathBuf> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].to_string_lossy().ends_with(&format!("{}.eml", emails[0].id)));
+
+        let eml = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(eml.contains("From: sender@example.com\r\n"));
+        assert!(eml.contains("To: user@example.com\r\n"));
+        assert!(eml.contains("Subject: Login code\r\n"));
+        assert!(eml.contains("Date: "));
+        assert!(eml.contains("Content-Type: multipart/alternative"));
+        assert!(eml.contains("Your code is 123456"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn send_email_without_a_directory_only_keeps_the_email() {
+        let mailbox: DevMailboxType = Arc::new(RwLock::new(DevMailbox::default()));
+        let email_client = DevEmailClient::new(email("sender@example.com"), mailbox.clone(), None);
+
+        email_client.send_email(&email("user@example.com"), &message("Login code")).await.unwrap();
+
+        assert_eq!(mailbox.read().await.list(None).len(), 1);
+    }
+
+    #[test]
+    fn test_mailbox_lists_newest_first() {
+        let mut mailbox = DevMailbox::default();
+        let first = dev_email("user@example.com", "First");
+        let second = dev_email("user@example.com", "Second");
+        let other = dev_email("other@example.com", "Other");
+        mailbox.push(first.clone());
+        mailbox.push(other.clone());
+        mailbox.push(second.clone());
+
+        assert_eq!(mailbox.list(Some(&email("user@example.com"))), vec![second.clone(), first]);
+        assert_eq!(mailbox.list(None).len(), 3);
+        assert_eq!(mailbox.get(other.id), Some(other));
+        assert_eq!(mailbox.get(Uuid::new_v4()), None);
+    }
+
+    #[test]
+    fn test_mailbox_drops_oldest_emails() {
+        let mut mailbox = DevMailbox::default();
+        let oldest = dev_email("user@example.com", "Oldest");
+        mailbox.push(oldest.clone());
+        for _ in 0..MAX_EMAILS {
+            mailbox.push(dev_email("user@example.com", "Newer"));
+        }
+
+        assert_eq!(mailbox.list(None).len(), MAX_EMAILS);
+        assert_eq!(mailbox.get(oldest.id), None);
+    }
+}
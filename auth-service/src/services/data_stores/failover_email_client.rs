@@ -0,0 +1,308 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{eyre, Result};
+
+use crate::domain::{email::Email, email_client::{EmailClient, EmailMessage}};
+
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreakerPolicy {
+    // Consecutive failures after which a provider is skipped
+    pub failure_threshold: u32,
+    // How long a provider is skipped before it gets another try
+    pub open_duration: Duration,
+}
+
+// Tracks the health of one provider. Once open, the provider is skipped until `open_duration`
+// has passed, then a single failure opens it again while a success closes it.
+struct CircuitBreaker {
+    policy: CircuitBreakerPolicy,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(policy: CircuitBreakerPolicy) -> Self {
+        Self { policy, state: Mutex::new(BreakerState::default()) }
+    }
+
+    fn is_open(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.open_until.is_some_and(|open_until| Instant::now() < open_until)
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    // Returns true when this failure opened the breaker
+    fn record_failure(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.consecutive_failures < self.policy.failure_threshold {
+            return false;
+        }
+        state.open_until = Some(Instant::now() + self.policy.open_duration);
+        true
+    }
+}
+
+struct Provider {
+    name: String,
+    client: Box<dyn EmailClient + Send + Sync>,
+    breaker: CircuitBreaker,
+}
+
+// Sends through an ordered list of providers, moving on to the next one when a provider errors
+// or times out. Providers with an open circuit breaker are skipped while a healthy one is left.
+pub struct FailoverEmailClient {
+    providers: Vec<Provider>,
+    timeout: Duration,
+}
+
+impl FailoverEmailClient {
+    pub fn new(
+        providers: Vec<(String, Box<dyn EmailClient + Send + Sync>)>,
+        policy: CircuitBreakerPolicy,
+        timeout: Duration,
+    ) -> Self {
+        let providers = providers
+            .into_iter()
+            .map(|(name, client)| Provider { name, client, breaker: CircuitBreaker::new(policy) })
+            .collect();
+
+        Self { providers, timeout }
+    }
+
+    // Sends the email and returns the name of the provider that delivered it
+    #[tracing::instrument(name = "Sending email with failover", skip_all)]
+    pub async fn deliver(&self, recipient: &Email, message: &EmailMessage) -> Result<&str> {
+        let mut candidates: Vec<&Provider> = self.providers.iter().filter(|p| !p.breaker.is_open()).collect();
+        // Every breaker is open, trying them anyway beats failing without a single attempt
+        if candidates.is_empty() {
+            candidates = self.providers.iter().collect();
+        }
+
+        let mut last_error = eyre!("No email providers are configured");
+        for provider in candidates {
+            let outcome = match tokio::time::timeout(self.timeout, provider.client.send_email(recipient, message)).await {
+                Ok(outcome) => outcome,
+                Err(_) => Err(eyre!("Timed out after {:?}", self.timeout)),
+            };
+
+            match outcome {
+                Ok(()) => {
+                    provider.breaker.record_success();
+                    return Ok(&provider.name);
+                }
+                Err(e) => {
+                    tracing::warn!("email provider {} failed: {:?}", provider.name, e);
+                    if provider.breaker.record_failure() {
+                        tracing::error!("email provider {} is unhealthy, skipping it for {:?}", provider.name, provider.breaker.policy.open_duration);
+                    }
+                    last_error = e.wrap_err(format!("Email provider {} failed", provider.name));
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for FailoverEmailClient {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let provider = self.deliver(recipient, message).await?;
+        tracing::info!("email delivered by {}", provider);
+        Ok(())
+    }
+
+    // Healthy while any provider is, emails still go out through that one
+    async fn health_check(&self) -> Result<()> {
+        let mut last_error = eyre!("No email providers are configured");
+        for provider in &self.providers {
+            match provider.client.health_check().await {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = e.wrap_err(format!("Email provider {} is unhealthy", provider.name)),
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+    use secrecy::Secret;
+    use wiremock::{matchers::{method, path}, Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::{services::data_stores::PostmarkEmailClient, utils::constants::test};
+
+    fn email(input: &str) -> Email {
+        Email::parse(Secret::new(input.to_owned())).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Subject".to_owned(),
+            html_body: "<p>Body</p>".to_owned(),
+            text_body: "Body".to_owned(),
+        }
+    }
+
+    fn postmark(server: &MockServer) -> Box<dyn EmailClient + Send + Sync> {
+        let http_client = Client::builder()
+            .timeout(test::email_client::TIMEOUT)
+            .build()
+            .unwrap();
+        Box::new(PostmarkEmailClient::new(
+            server.uri(),
+            email(test::email_client::SENDER),
+            Secret::new("token".to_owned()),
+            http_client,
+        ))
+    }
+
+    fn policy() -> CircuitBreakerPolicy {
+        CircuitBreakerPolicy {
+            failure_threshold: test::email_failover::FAILURE_THRESHOLD,
+            open_duration: test::email_failover::OPEN_DURATION,
+        }
+    }
+
+    fn failover_client(primary: &MockServer, secondary: &MockServer) -> FailoverEmailClient {
+        FailoverEmailClient::new(
+            vec![("primary".to_owned(), postmark(primary)), ("secondary".to_owned(), postmark(secondary))],
+            policy(),
+            test::email_failover::TIMEOUT,
+        )
+    }
+
+    async fn respond(server: &MockServer, response: ResponseTemplate, expected: u64) {
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(response)
+            .expect(expected)
+            .mount(server)
+            .await;
+    }
+
+    async fn deliver(email_client: &FailoverEmailClient) -> Result<String> {
+        email_client
+            .deliver(&email("user@example.com"), &message())
+            .await
+            .map(str::to_owned)
+    }
+
+    #[tokio::test]
+    async fn deliver_uses_the_first_healthy_provider() {
+        let (primary, secondary) = (MockServer::start().await, MockServer::start().await);
+        respond(&primary, ResponseTemplate::new(200), 1).await;
+        respond(&secondary, ResponseTemplate::new(200), 0).await;
+
+        let email_client = failover_client(&primary, &secondary);
+
+        assert_eq!(deliver(&email_client).await.unwrap(), "primary");
+    }
+
+    #[tokio::test]
+    async fn deliver_fails_over_when_a_provider_errors() {
+        let (primary, secondary) = (MockServer::start().await, MockServer::start().await);
+        respond(&primary, ResponseTemplate::new(500), 1).await;
+        respond(&secondary, ResponseTemplate::new(200), 1).await;
+
+        let email_client = failover_client(&primary, &secondary);
+
+        assert_eq!(deliver(&email_client).await.unwrap(), "secondary");
+    }
+
+    #[tokio::test]
+    async fn deliver_fails_over_when_a_provider_times_out() {
+        let (primary, secondary) = (MockServer::start().await, MockServer::start().await);
+        respond(&primary, ResponseTemplate::new(200).set_delay(Duration::from_secs(180)), 1).await;
+        respond(&secondary, ResponseTemplate::new(200), 1).await;
+
+        let email_client = failover_client(&primary, &secondary);
+
+        assert_eq!(deliver(&email_client).await.unwrap(), "secondary");
+    }
+
+    #[tokio::test]
+    async fn deliver_skips_a_provider_with_an_open_circuit() {
+        let (primary, secondary) = (MockServer::start().await, MockServer::start().await);
+        let threshold = test::email_failover::FAILURE_THRESHOLD;
+        // The primary only sees the sends that open its breaker
+        respond(&primary, ResponseTemplate::new(503), u64::from(threshold)).await;
+        respond(&secondary, ResponseTemplate::new(200), u64::from(threshold) + 2).await;
+
+        let email_client = failover_client(&primary, &secondary);
+
+        for _ in 0..threshold + 2 {
+            assert_eq!(deliver(&email_client).await.unwrap(), "secondary");
+        }
+    }
+
+    #[tokio::test]
+    async fn deliver_retries_a_provider_once_the_circuit_closes() {
+        let (primary, secondary) = (MockServer::start().await, MockServer::start().await);
+        let threshold = test::email_failover::FAILURE_THRESHOLD;
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(u64::from(threshold))
+            .expect(u64::from(threshold))
+            .mount(&primary)
+            .await;
+        respond(&primary, ResponseTemplate::new(200), 1).await;
+        respond(&secondary, ResponseTemplate::new(200), u64::from(threshold)).await;
+
+        let email_client = failover_client(&primary, &secondary);
+
+        for _ in 0..threshold {
+            assert_eq!(deliver(&email_client).await.unwrap(), "secondary");
+        }
+
+        tokio::time::sleep(test::email_failover::OPEN_DURATION).await;
+        assert_eq!(deliver(&email_client).await.unwrap(), "primary");
+    }
+
+    #[tokio::test]
+    async fn deliver_fails_when_every_provider_fails() {
+        let (primary, secondary) = (MockServer::start().await, MockServer::start().await);
+        respond(&primary, ResponseTemplate::new(500), 1).await;
+        respond(&secondary, ResponseTemplate::new(500), 1).await;
+
+        let email_client = failover_client(&primary, &secondary);
+
+        let error = deliver(&email_client).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("secondary"));
+    }
+
+    #[tokio::test]
+    async fn health_check_passes_while_any_provider_is_healthy() {
+        let (primary, secondary) = (MockServer::start().await, MockServer::start().await);
+        Mock::given(path("/server")).respond_with(ResponseTemplate::new(500)).mount(&primary).await;
+        Mock::given(path("/server")).respond_with(ResponseTemplate::new(200)).mount(&secondary).await;
+
+        assert!(failover_client(&primary, &secondary).health_check().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn health_check_fails_when_every_provider_is_unhealthy() {
+        let (primary, secondary) = (MockServer::start().await, MockServer::start().await);
+        Mock::given(path("/server")).respond_with(ResponseTemplate::new(500)).mount(&primary).await;
+        Mock::given(path("/server")).respond_with(ResponseTemplate::new(500)).mount(&secondary).await;
+
+        let error = failover_client(&primary, &secondary).health_check().await.unwrap_err();
+        assert!(format!("{:#}", error).contains("secondary"));
+    }
+}
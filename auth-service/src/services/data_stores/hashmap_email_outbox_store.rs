@@ -0,0 +1,199 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::data_stores::{DeliveryStatus, EmailOutboxStore, EmailOutboxStoreError, OutboxEmail, OutboxQuery};
+
+#[derive(Default)]
+pub struct HashmapEmailOutboxStore {
+    emails: HashMap<Uuid, OutboxEmail>,
+}
+
+impl HashmapEmailOutboxStore {
+    fn email_mut(&mut self, id: Uuid) -> Result<&mut OutboxEmail, EmailOutboxStoreError> {
+        self.emails.get_mut(&id).ok_or(EmailOutboxStoreError::EmailNotFound)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for HashmapEmailOutboxStore {
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        self.emails.insert(email.id, email);
+        Ok(())
+    }
+
+    async fn claim_due(&mut self, limit: i64, lease_until: DateTime<Utc>) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let now = Utc::now();
+        let mut due: Vec<&mut OutboxEmail> = self.emails.values_mut().filter(|e| e.is_due(now)).collect();
+        due.sort_by_key(|e| e.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|email| {
+                email.attempts += 1;
+                email.next_attempt_at = lease_until;
+                email.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_sent(&mut self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+        let email = self.email_mut(id)?;
+        email.status = DeliveryStatus::Sent;
+        email.sent_at = Some(Utc::now());
+        email.last_error = None;
+        email.message.html_body.clear();
+        email.message.text_body.clear();
+        Ok(())
+    }
+
+    async fn schedule_retry(&mut self, id: Uuid, error: &str, retry_at: DateTime<Utc>) -> Result<(), EmailOutboxStoreError> {
+        let email = self.email_mut(id)?;
+        email.status = DeliveryStatus::Pending;
+        email.last_error = Some(error.to_owned());
+        email.next_attempt_at = retry_at;
+        Ok(())
+    }
+
+    async fn mark_dead(&mut self, id: Uuid, error: &str) -> Result<(), EmailOutboxStoreError> {
+        let email = self.email_mut(id)?;
+        email.status = DeliveryStatus::Dead;
+        email.last_error = Some(error.to_owned());
+        email.message.html_body.clear();
+        email.message.text_body.clear();
+        Ok(())
+    }
+
+    async fn get_email(&self, id: Uuid) -> Result<OutboxEmail, EmailOutboxStoreError> {
+        self.emails
+            .get(&id)
+            .cloned()
+            .ok_or(EmailOutboxStoreError::EmailNotFound)
+    }
+
+    async fn query(&self, query: &OutboxQuery) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let mut emails: Vec<OutboxEmail> = self
+            .emails
+            .values()
+            .filter(|e| query.matches(e))
+            .cloned()
+            .collect();
+
+        emails.sort_by_key(|e| Reverse(e.created_at));
+        emails.truncate(query.limit.max(0) as usize);
+
+        Ok(emails)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::{email::Email, email_client::EmailMessage};
+
+    fn outbox_email(recipient: &str) -> OutboxEmail {
+        let message = EmailMessage {
+            subject: "Subject".to_owned(),
+            html_body: "<p>Body</p>".to_owned(),
+            text_body: "Body".to_owned(),
+        };
+        OutboxEmail::new(Email::parse(Secret::new(recipient.to_owned())).unwrap(), message)
+    }
+
+    #[tokio::test]
+    async fn test_claim_due_leases_emails() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let email = outbox_email("user@example.com");
+        store.enqueue(email.clone()).await.unwrap();
+
+        let lease_until = Utc::now() + Duration::minutes(5);
+        let claimed = store.claim_due(10, lease_until).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, email.id);
+        assert_eq!(claimed[0].attempts, 1);
+
+        // Leased emails are not handed out twice
+        assert!(store.claim_due(10, lease_until).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_claim_due_respects_limit() {
+        let mut store = HashmapEmailOutboxStore::default();
+        for _ in 0..3 {
+            store.enqueue(outbox_email("user@example.com")).await.unwrap();
+        }
+
+        let lease_until = Utc::now() + Duration::minutes(5);
+        assert_eq!(store.claim_due(2, lease_until).await.unwrap().len(), 2);
+        assert_eq!(store.claim_due(2, lease_until).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_and_dead_letter() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let email = outbox_email("user@example.com");
+        store.enqueue(email.clone()).await.unwrap();
+
+        store.schedule_retry(email.id, "timed out", Utc::now() - Duration::seconds(1)).await.unwrap();
+        let retried = store.get_email(email.id).await.unwrap();
+        assert_eq!(retried.status, DeliveryStatus::Pending);
+        assert_eq!(retried.last_error.as_deref(), Some("timed out"));
+        assert_eq!(store.claim_due(10, Utc::now()).await.unwrap().len(), 1);
+
+        store.mark_dead(email.id, "rejected").await.unwrap();
+        let dead = store.get_email(email.id).await.unwrap();
+        assert_eq!(dead.status, DeliveryStatus::Dead);
+        assert_eq!(dead.last_error.as_deref(), Some("rejected"));
+        assert_eq!(dead.message.subject, "Subject");
+        assert!(dead.message.html_body.is_empty() && dead.message.text_body.is_empty());
+        assert!(store.claim_due(10, Utc::now()).await.unwrap().is_empty());
+
+        assert_eq!(
+            store.mark_dead(Uuid::new_v4(), "rejected").await,
+            Err(EmailOutboxStoreError::EmailNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mark_sent_drops_bodies() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let email = outbox_email("user@example.com");
+        store.enqueue(email.clone()).await.unwrap();
+
+        store.mark_sent(email.id).await.unwrap();
+
+        let sent = store.get_email(email.id).await.unwrap();
+        assert_eq!(sent.status, DeliveryStatus::Sent);
+        assert!(sent.sent_at.is_some());
+        assert_eq!(sent.message.subject, "Subject");
+        assert!(sent.message.html_body.is_empty() && sent.message.text_body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query() {
+        let mut store = HashmapEmailOutboxStore::default();
+        let first = outbox_email("user@example.com");
+        let mut second = outbox_email("user@example.com");
+        second.created_at = first.created_at + Duration::seconds(1);
+        store.enqueue(first.clone()).await.unwrap();
+        store.enqueue(second.clone()).await.unwrap();
+        store.enqueue(outbox_email("other@example.com")).await.unwrap();
+        store.mark_dead(first.id, "rejected").await.unwrap();
+
+        let query = OutboxQuery {
+            recipient: Some(first.recipient.clone()),
+            limit: 10,
+            ..Default::default()
+        };
+        let emails = store.query(&query).await.unwrap();
+        assert_eq!(emails.iter().map(|e| e.id).collect::<Vec<_>>(), vec![second.id, first.id]);
+
+        let query = OutboxQuery { status: Some(DeliveryStatus::Dead), limit: 10, ..Default::default() };
+        assert_eq!(store.query(&query).await.unwrap().len(), 1);
+    }
+}
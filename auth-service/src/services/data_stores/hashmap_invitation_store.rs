@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    data_stores::{Invitation, InvitationCode, InvitationStore, InvitationStoreError},
+    email::Email,
+};
+
+#[derive(Default)]
+pub struct HashmapInvitationStore {
+    invitations: HashMap<String, Invitation>,
+}
+
+#[async_trait::async_trait]
+impl InvitationStore for HashmapInvitationStore {
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), InvitationStoreError> {
+        let code = invitation.code.as_ref().expose_secret().to_owned();
+        self.invitations.insert(code, invitation);
+        Ok(())
+    }
+
+    async fn get_invitation(&self, code: &InvitationCode) -> Result<Invitation, InvitationStoreError> {
+        self.invitations
+            .get(code.as_ref().expose_secret())
+            .cloned()
+            .ok_or(InvitationStoreError::InvitationNotFound)
+    }
+
+    async fn redeem_invitation(&mut self, code: &InvitationCode, email: &Email) -> Result<(), InvitationStoreError> {
+        let invitation = self.invitations
+            .get_mut(code.as_ref().expose_secret())
+            .ok_or(InvitationStoreError::InvitationNotFound)?;
+
+        if !invitation.is_usable_by(email) {
+            return Err(InvitationStoreError::InvitationNotUsable);
+        }
+
+        invitation.uses += 1;
+        Ok(())
+    }
+
+    async fn release_invitation(&mut self, code: &InvitationCode) -> Result<(), InvitationStoreError> {
+        let invitation = self.invitations
+            .get_mut(code.as_ref().expose_secret())
+            .ok_or(InvitationStoreError::InvitationNotFound)?;
+
+        invitation.uses = (invitation.uses - 1).max(0);
+        Ok(())
+    }
+
+    async fn revoke_invitation(&mut self, code: &InvitationCode) -> Result<(), InvitationStoreError> {
+        match self.invitations.remove(code.as_ref().expose_secret()) {
+            Some(_) => Ok(()),
+            None => Err(InvitationStoreError::InvitationNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email(input: &str) -> Email {
+        Email::parse(Secret::new(input.to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_invitation() {
+        let mut store = HashmapInvitationStore::default();
+        let invitation = Invitation::new(email("admin@example.com"), None, Utc::now() + Duration::hours(1), 1);
+
+        store.add_invitation(invitation.clone()).await.unwrap();
+
+        assert_eq!(store.get_invitation(&invitation.code).await, Ok(invitation));
+        assert_eq!(
+            store.get_invitation(&InvitationCode::default()).await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redeem_invitation_counts_uses() {
+        let mut store = HashmapInvitationStore::default();
+        let invitation = Invitation::new(email("admin@example.com"), None, Utc::now() + Duration::hours(1), 2);
+        store.add_invitation(invitation.clone()).await.unwrap();
+
+        assert_eq!(store.redeem_invitation(&invitation.code, &email("a@example.com")).await, Ok(()));
+        assert_eq!(store.redeem_invitation(&invitation.code, &email("b@example.com")).await, Ok(()));
+        assert_eq!(
+            store.redeem_invitation(&invitation.code, &email("c@example.com")).await,
+            Err(InvitationStoreError::InvitationNotUsable)
+        );
+        assert_eq!(store.get_invitation(&invitation.code).await.unwrap().uses, 2);
+    }
+
+    #[tokio::test]
+    async fn test_release_invitation_gives_back_a_use() {
+        let mut store = HashmapInvitationStore::default();
+        let invitation = Invitation::new(email("admin@example.com"), None, Utc::now() + Duration::hours(1), 1);
+        store.add_invitation(invitation.clone()).await.unwrap();
+
+        assert_eq!(store.redeem_invitation(&invitation.code, &email("a@example.com")).await, Ok(()));
+        assert_eq!(store.release_invitation(&invitation.code).await, Ok(()));
+        assert_eq!(store.redeem_invitation(&invitation.code, &email("b@example.com")).await, Ok(()));
+        assert_eq!(
+            store.release_invitation(&InvitationCode::default()).await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_invitation() {
+        let mut store = HashmapInvitationStore::default();
+        let invitation = Invitation::new(email("admin@example.com"), None, Utc::now() + Duration::hours(1), 1);
+        store.add_invitation(invitation.clone()).await.unwrap();
+
+        assert_eq!(store.revoke_invitation(&invitation.code).await, Ok(()));
+        assert_eq!(
+            store.redeem_invitation(&invitation.code, &email("a@example.com")).await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+    }
+}
@@ -1,18 +1,25 @@
 use std::collections::HashMap;
 
+use uuid::Uuid;
+
 use crate::domain::data_stores::{UserStore, UserStoreError};
+use crate::domain::organisation::{Membership, OrgRole, Organisation};
 use crate::domain::password::Password;
-use crate::domain::user::User;
+use crate::domain::user::{AccountStatus, User, UserRole};
 use crate::domain::email::Email;
+use crate::domain::locale::Locale;
 
 #[derive(Default, Debug)]
 pub struct HashmapUserStore {
-    pub users: HashMap<Email, User>
+    pub users: HashMap<Email, User>,
+    // Keyed by slug
+    pub organisations: HashMap<String, Organisation>,
+    pub memberships: HashMap<Email, HashMap<Uuid, OrgRole>>,
 }
 
 impl HashmapUserStore {
     pub fn new() -> Self {
-        Self { users: HashMap::new() }
+        Self { users: HashMap::new(), organisations: HashMap::new(), memberships: HashMap::new() }
     }
 }
 
@@ -49,11 +56,94 @@ impl UserStore for HashmapUserStore {
     }
 
     async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        self.memberships.remove(email);
+
         match self.users.remove(email) {
             Some(_) => Ok(()),
             None => Err(UserStoreError::UserNotFound)
         }
     }
+
+    async fn update_status(&mut self, email: &Email, status: AccountStatus) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.status = status;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound)
+        }
+    }
+
+    async fn update_role(&mut self, email: &Email, role: UserRole) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.role = role;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound)
+        }
+    }
+
+    async fn update_locale(&mut self, email: &Email, locale: Option<Locale>) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.locale = locale;
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound)
+        }
+    }
+
+    async fn add_organisation(&mut self, organisation: Organisation) -> Result<(), UserStoreError> {
+        if self.organisations.contains_key(&organisation.slug) {
+            return Err(UserStoreError::OrganisationAlreadyExists);
+        }
+
+        self.organisations.insert(organisation.slug.clone(), organisation);
+        Ok(())
+    }
+
+    async fn get_organisation(&self, slug: &str) -> Result<Organisation, UserStoreError> {
+        match self.organisations.get(slug) {
+            Some(organisation) => Ok(organisation.to_owned()),
+            None => Err(UserStoreError::OrganisationNotFound)
+        }
+    }
+
+    async fn set_membership(&mut self, email: &Email, organisation_id: Uuid, role: OrgRole) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        if !self.organisations.values().any(|o| o.id == organisation_id) {
+            return Err(UserStoreError::OrganisationNotFound);
+        }
+
+        self.memberships
+            .entry(email.clone())
+            .or_default()
+            .insert(organisation_id, role);
+        Ok(())
+    }
+
+    async fn get_memberships(&self, email: &Email) -> Result<Vec<Membership>, UserStoreError> {
+        let Some(roles) = self.memberships.get(email) else {
+            return Ok(Vec::new());
+        };
+
+        let mut memberships: Vec<Membership> = self.organisations
+            .values()
+            .filter_map(|organisation| {
+                roles.get(&organisation.id).map(|role| Membership {
+                    organisation: organisation.clone(),
+                    role: *role,
+                })
+            })
+            .collect();
+        memberships.sort_by(|a, b| a.organisation.slug.cmp(&b.organisation.slug));
+
+        Ok(memberships)
+    }
 }
 
 #[cfg(test)]
@@ -65,8 +155,8 @@ mod tests {
     #[tokio::test]
     async fn test_add_user() {
         let mut map = HashmapUserStore::new();
-        let email = Email::parse(Secret::new("foo@com".to_string())).unwrap();
-        let pwd = Password::parse(Secret::new("foobarbaz".to_string())).unwrap();
+        let email = Email::parse(Secret::new("foo@example.com".to_string())).unwrap();
+        let pwd = Password::parse_credential(Secret::new("foobarbaz".to_string())).unwrap();
         let user = User::new(email, pwd, false);
 
         // Test adding a new user
@@ -81,8 +171,8 @@ mod tests {
     #[tokio::test]
     async fn test_get_user() {
         let mut map = HashmapUserStore::new();
-        let email = Email::parse(Secret::new("foo@com".to_string())).unwrap();
-        let pwd = Password::parse(Secret::new("foobarbaz".to_string())).unwrap();
+        let email = Email::parse(Secret::new("foo@example.com".to_string())).unwrap();
+        let pwd = Password::parse_credential(Secret::new("foobarbaz".to_string())).unwrap();
         let user = User::new(email.clone(), pwd, false);
 
         // Test getting a user that exists
@@ -101,8 +191,8 @@ mod tests {
     #[tokio::test]
     async fn test_validate_user() {
         let mut map = HashmapUserStore::new();
-        let email = Email::parse(Secret::new("foo@com".to_string())).unwrap();
-        let pwd = Password::parse(Secret::new("foobarbaz".to_string())).unwrap();
+        let email = Email::parse(Secret::new("foo@example.com".to_string())).unwrap();
+        let pwd = Password::parse_credential(Secret::new("foobarbaz".to_string())).unwrap();
         let user = User::new(email.clone(), pwd.clone(), false);
 
         // Test validating a user that exists with correct password
@@ -111,7 +201,7 @@ mod tests {
         assert_eq!(res, Ok(()));
 
         // Test validating a user that exists with incorrect password
-        let wrong_password = Password::parse(Secret::new("wrongpassword".to_string())).unwrap();
+        let wrong_password = Password::parse_credential(Secret::new("wrongpassword".to_string())).unwrap();
         let res = map.validate_user(&email, &wrong_password).await;
         assert_eq!(res, Err(UserStoreError::InvalidCredentials));
 
@@ -129,8 +219,8 @@ mod tests {
     #[tokio::test]
     async fn test_delete_user() {
         let mut map = HashmapUserStore::new();
-        let email = Email::parse(Secret::new("foo@com".to_string())).unwrap();
-        let pwd = Password::parse(Secret::new("foobarbaz".to_string())).unwrap();
+        let email = Email::parse(Secret::new("foo@example.com".to_string())).unwrap();
+        let pwd = Password::parse_credential(Secret::new("foobarbaz".to_string())).unwrap();
         let user = User::new(email.clone(), pwd, false);
 
         // Test getting a user that exists
@@ -145,4 +235,102 @@ mod tests {
         let res = map.get_user(&user.email).await;
         assert_eq!(res, Err(UserStoreError::UserNotFound));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_update_status() {
+        let mut map = HashmapUserStore::new();
+        let email = Email::parse(Secret::new("foo@example.com".to_string())).unwrap();
+        let pwd = Password::parse_credential(Secret::new("foobarbaz".to_string())).unwrap();
+        let user = User::new(email.clone(), pwd, false);
+        assert_eq!(user.status, AccountStatus::Active);
+
+        // Test suspending a user that exists
+        map.users.insert(email.clone(), user);
+        let res = map.update_status(&email, AccountStatus::Suspended).await;
+        assert_eq!(res, Ok(()));
+        assert_eq!(map.get_user(&email).await.unwrap().status, AccountStatus::Suspended);
+
+        // Test suspending a user that doesn't exist
+        let res = map
+            .update_status(
+                &Email::parse(Secret::new("nonexistent@example.com".to_string())).unwrap(),
+                AccountStatus::Suspended,
+            )
+            .await;
+        assert_eq!(res, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_update_locale() {
+        let mut map = HashmapUserStore::new();
+        let email = Email::parse(Secret::new("foo@example.com".to_string())).unwrap();
+        let pwd = Password::parse_credential(Secret::new("foobarbaz".to_string())).unwrap();
+        map.users.insert(email.clone(), User::new(email.clone(), pwd, false));
+
+        let res = map.update_locale(&email, Some(Locale::De)).await;
+        assert_eq!(res, Ok(()));
+        assert_eq!(map.get_user(&email).await.unwrap().locale, Some(Locale::De));
+
+        let res = map.update_locale(&email, None).await;
+        assert_eq!(res, Ok(()));
+        assert_eq!(map.get_user(&email).await.unwrap().locale, None);
+
+        let res = map
+            .update_locale(&Email::parse(Secret::new("nonexistent@example.com".to_string())).unwrap(), None)
+            .await;
+        assert_eq!(res, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_add_organisation() {
+        let mut map = HashmapUserStore::new();
+        let organisation = Organisation::new("acme", "Acme".to_owned()).unwrap();
+
+        assert_eq!(map.add_organisation(organisation.clone()).await, Ok(()));
+        assert_eq!(map.get_organisation("acme").await, Ok(organisation));
+
+        // Slugs are unique
+        let duplicate = Organisation::new("acme", "Acme Again".to_owned()).unwrap();
+        assert_eq!(map.add_organisation(duplicate).await, Err(UserStoreError::OrganisationAlreadyExists));
+        assert_eq!(map.get_organisation("other").await, Err(UserStoreError::OrganisationNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_membership() {
+        let mut map = HashmapUserStore::new();
+        let email = Email::parse(Secret::new("foo@example.com".to_string())).unwrap();
+        let pwd = Password::parse_credential(Secret::new("foobarbaz".to_string())).unwrap();
+        map.add_user(User::new(email.clone(), pwd, false)).await.unwrap();
+
+        let acme = Organisation::new("acme", "Acme".to_owned()).unwrap();
+        let globex = Organisation::new("globex", "Globex".to_owned()).unwrap();
+        map.add_organisation(acme.clone()).await.unwrap();
+        map.add_organisation(globex.clone()).await.unwrap();
+
+        assert_eq!(map.get_memberships(&email).await, Ok(vec![]));
+
+        map.set_membership(&email, globex.id, OrgRole::Member).await.unwrap();
+        map.set_membership(&email, acme.id, OrgRole::Member).await.unwrap();
+        // Setting it again changes the role
+        map.set_membership(&email, acme.id, OrgRole::Admin).await.unwrap();
+
+        assert_eq!(
+            map.get_memberships(&email).await,
+            Ok(vec![
+                Membership { organisation: acme, role: OrgRole::Admin },
+                Membership { organisation: globex, role: OrgRole::Member },
+            ])
+        );
+
+        let res = map.set_membership(&email, Uuid::new_v4(), OrgRole::Member).await;
+        assert_eq!(res, Err(UserStoreError::OrganisationNotFound));
+
+        let nonexistent = Email::parse(Secret::new("nonexistent@example.com".to_string())).unwrap();
+        let res = map.set_membership(&nonexistent, Uuid::new_v4(), OrgRole::Member).await;
+        assert_eq!(res, Err(UserStoreError::UserNotFound));
+
+        // Memberships go with the user
+        map.delete_user(&email).await.unwrap();
+        assert_eq!(map.get_memberships(&email).await, Ok(vec![]));
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::HashSet;
+
+use crate::domain::{
+    data_stores::{password_sha1, BreachedPasswordStore, BreachedPasswordStoreError},
+    password::Password,
+};
+
+#[derive(Default, Clone)]
+pub struct HashsetBreachedPasswordStore {
+    pub hashes: HashSet<String>,
+}
+
+impl HashsetBreachedPasswordStore {
+    pub fn new() -> Self {
+        Self { hashes: HashSet::new() }
+    }
+
+    pub fn add_password(&mut self, password: &Password) {
+        self.hashes.insert(password_sha1(password));
+    }
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordStore for HashsetBreachedPasswordStore {
+    async fn contains_password(&self, password: &Password) -> Result<bool, BreachedPasswordStoreError> {
+        Ok(self.hashes.contains(&password_sha1(password)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_contains_password() {
+        let mut store = HashsetBreachedPasswordStore::new();
+        let breached = Password::parse_credential(Secret::new("password".to_owned())).unwrap();
+        let other = Password::parse_credential(Secret::new("correct horse battery staple".to_owned())).unwrap();
+
+        store.add_password(&breached);
+
+        assert!(store.contains_password(&breached).await.unwrap());
+        assert!(!store.contains_password(&other).await.unwrap());
+    }
+}
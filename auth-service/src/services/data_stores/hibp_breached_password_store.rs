@@ -0,0 +1,157 @@
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+
+use crate::domain::{
+    data_stores::{password_sha1, BreachedPasswordStore, BreachedPasswordStoreError},
+    password::Password,
+};
+
+// Looks passwords up in a Have I Been Pwned "ordered by hash" download, i.e. one
+// `<SHA-1>:<count>` line per password sorted by hash. The corpus is far too large
+// to hold in memory, so every lookup is a binary search over the file on disk.
+pub struct HibpBreachedPasswordStore {
+    path: PathBuf,
+}
+
+impl HibpBreachedPasswordStore {
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        File::open(&path)
+            .wrap_err_with(|| format!("failed to open breached password corpus {}", path.display()))?;
+
+        Ok(Self { path })
+    }
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordStore for HibpBreachedPasswordStore {
+    #[tracing::instrument(name = "Checking password against breach corpus", skip_all)]
+    async fn contains_password(&self, password: &Password) -> Result<bool, BreachedPasswordStoreError> {
+        let path = self.path.clone();
+        let hash = password_sha1(password);
+
+        tokio::task::spawn_blocking(move || search_sorted_file(&path, &hash))
+            .await
+            .map_err(|e| BreachedPasswordStoreError::UnexpectedError(e.into()))?
+            .map_err(BreachedPasswordStoreError::UnexpectedError)
+    }
+}
+
+// Binary search over byte offsets. Every probe seeks into the middle of the remaining
+// range, skips the partial line it landed in and compares the next complete line.
+// Invariant: the hash can only be on a line starting in `lo..hi`, and `lo` is a line start.
+fn search_sorted_file(path: &Path, hash: &str) -> Result<bool> {
+    let file = File::open(path).wrap_err("failed to open breached password corpus")?;
+    let mut lo = 0;
+    let mut hi = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+
+        let start = if mid == lo {
+            reader.seek(SeekFrom::Start(lo))?;
+            lo
+        } else {
+            reader.seek(SeekFrom::Start(mid - 1))?;
+            let mut partial = Vec::new();
+            mid - 1 + reader.read_until(b'\n', &mut partial)? as u64
+        };
+
+        if start >= hi {
+            hi = mid;
+            continue;
+        }
+
+        line.clear();
+        let end = start + reader.read_line(&mut line)? as u64;
+
+        let line_hash = line
+            .split(':')
+            .next()
+            .map(str::trim)
+            .filter(|h| !h.is_empty())
+            .ok_or_else(|| eyre!("malformed line in breached password corpus at byte {}", start))?;
+
+        match line_hash.to_ascii_uppercase().as_str().cmp(hash) {
+            Ordering::Equal => return Ok(true),
+            Ordering::Less => lo = end,
+            Ordering::Greater => hi = start,
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn password(s: &str) -> Password {
+        Password::parse_credential(Secret::new(s.to_owned())).unwrap()
+    }
+
+    fn corpus(passwords: &[&str]) -> PathBuf {
+        let mut lines: Vec<String> = passwords
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("{}:{}\r\n", password_sha1(&password(p)), i + 1))
+            .collect();
+        lines.sort();
+
+        let path = std::env::temp_dir().join(format!("hibp-{}.txt", Uuid::new_v4()));
+        let mut file = File::create(&path).unwrap();
+        file.write_all(lines.concat().as_bytes()).unwrap();
+
+        path
+    }
+
+    #[tokio::test]
+    async fn test_contains_password() {
+        let breached = [
+            "password", "12345678", "qwertyuiop", "iloveyou1", "password123",
+            "sunshine", "football", "baseball1", "letmein!", "trustno1!",
+        ];
+        let path = corpus(&breached);
+        let store = HibpBreachedPasswordStore::new(&path).unwrap();
+
+        for p in breached {
+            assert!(store.contains_password(&password(p)).await.unwrap(), "{} should be breached", p);
+        }
+
+        for p in ["correct horse battery staple", "00000000", "zzzzzzzz"] {
+            assert!(!store.contains_password(&password(p)).await.unwrap(), "{} should not be breached", p);
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_empty_corpus() {
+        let path = corpus(&[]);
+        let store = HibpBreachedPasswordStore::new(&path).unwrap();
+
+        assert!(!store.contains_password(&password("password")).await.unwrap());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_new_fails_for_missing_file() {
+        let path = std::env::temp_dir().join(format!("hibp-{}.txt", Uuid::new_v4()));
+
+        assert!(HibpBreachedPasswordStore::new(path).is_err());
+    }
+}
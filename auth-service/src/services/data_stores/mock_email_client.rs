@@ -1,18 +1,18 @@
 use color_eyre::eyre::Result;
 use secrecy::ExposeSecret;
 
-use crate::domain::{email::Email, email_client::EmailClient};
+use crate::domain::{email::Email, email_client::{EmailClient, EmailMessage}};
 
 pub struct MockEmailClient;
 
 #[async_trait::async_trait]
 impl EmailClient for MockEmailClient {
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         tracing::debug!(
             "Sending email to {} with subject: {} and content: {}",
             recipient.as_ref().expose_secret(),
-            subject,
-            content
+            message.subject,
+            message.text_body
         );
 
         Ok(())
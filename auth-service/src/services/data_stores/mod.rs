@@ -6,6 +6,17 @@ mod postgres_user_store;
 mod redis_banned_token_store;
 mod redis_two_fa_code_store;
 mod postmark_email_client;
+mod smtp_email_client;
+mod failover_email_client;
+mod dev_email_client;
+mod vec_audit_log_store;
+mod postgres_audit_log_store;
+mod hashset_breached_password_store;
+mod hibp_breached_password_store;
+mod hashmap_invitation_store;
+mod postgres_invitation_store;
+mod hashmap_email_outbox_store;
+mod postgres_email_outbox_store;
 
 pub use hashmap_user_store::*;
 pub use hashset_banned_token_store::*;
@@ -14,4 +25,15 @@ pub use mock_email_client::*;
 pub use postgres_user_store::*;
 pub use redis_banned_token_store::*;
 pub use redis_two_fa_code_store::*;
-pub use postmark_email_client::*;
\ No newline at end of file
+pub use postmark_email_client::*;
+pub use smtp_email_client::*;
+pub use failover_email_client::*;
+pub use dev_email_client::*;
+pub use vec_audit_log_store::*;
+pub use postgres_audit_log_store::*;
+pub use hashset_breached_password_store::*;
+pub use hibp_breached_password_store::*;
+pub use hashmap_invitation_store::*;
+pub use postgres_invitation_store::*;
+pub use hashmap_email_outbox_store::*;
+pub use postgres_email_outbox_store::*;
\ No newline at end of file
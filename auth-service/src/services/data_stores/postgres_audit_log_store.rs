@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::data_stores::{
+        AuditEvent, AuditEventType, AuditLogStore, AuditLogStoreError, AuditOutcome, AuditQuery,
+    },
+    utils::constants::PG_AUDIT_LOG_TABLE_NAME,
+};
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AuditLogRow {
+    pub id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub event_type: String,
+    pub outcome: String,
+    pub actor: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub detail: Option<String>,
+}
+
+impl TryFrom<AuditLogRow> for AuditEvent {
+    type Error = AuditLogStoreError;
+
+    fn try_from(row: AuditLogRow) -> Result<Self, Self::Error> {
+        let event_type = AuditEventType::parse(&row.event_type)
+            .map_err(|e| AuditLogStoreError::UnexpectedError(eyre!(e)))?;
+
+        let outcome = AuditOutcome::parse(&row.outcome)
+            .map_err(|e| AuditLogStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(AuditEvent {
+            id: row.id,
+            occurred_at: row.occurred_at,
+            event_type,
+            outcome,
+            actor: row.actor,
+            ip: row.ip,
+            user_agent: row.user_agent,
+            request_id: row.request_id,
+            detail: row.detail,
+        })
+    }
+}
+
+pub struct PostgresAuditLogStore {
+    pool: PgPool,
+}
+
+impl PostgresAuditLogStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for PostgresAuditLogStore {
+    #[tracing::instrument(name = "Recording audit event in PostgreSQL", skip_all)]
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        let sql = format!(
+            "insert into {} (id, occurred_at, event_type, outcome, actor, ip, user_agent, request_id, detail) \
+             values ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            PG_AUDIT_LOG_TABLE_NAME
+        );
+        sqlx::query(&sql)
+            .bind(event.id)
+            .bind(event.occurred_at)
+            .bind(event.event_type.as_ref())
+            .bind(event.outcome.as_ref())
+            .bind(event.actor)
+            .bind(event.ip)
+            .bind(event.user_agent)
+            .bind(event.request_id)
+            .bind(event.detail)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Querying audit events from PostgreSQL", skip_all)]
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        let sql = format!(
+            "select * from {} \
+             where ($1::text is null or lower(actor) = lower($1)) \
+             and ($2::timestamptz is null or occurred_at >= $2) \
+             and ($3::timestamptz is null or occurred_at < $3) \
+             order by occurred_at desc limit $4",
+            PG_AUDIT_LOG_TABLE_NAME
+        );
+        sqlx::query_as::<_, AuditLogRow>(&sql)
+            .bind(query.actor.as_deref())
+            .bind(query.from)
+            .bind(query.to)
+            .bind(query.limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?
+            .into_iter()
+            .map(AuditEvent::try_from)
+            .collect()
+    }
+}
@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{postgres::PgQueryResult, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        data_stores::{DeliveryStatus, EmailOutboxStore, EmailOutboxStoreError, OutboxEmail, OutboxQuery},
+        email::Email,
+        email_client::EmailMessage,
+    },
+    utils::constants::PG_EMAIL_OUTBOX_TABLE_NAME,
+};
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OutboxEmailRow {
+    pub id: Uuid,
+    pub recipient: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<OutboxEmailRow> for OutboxEmail {
+    type Error = EmailOutboxStoreError;
+
+    fn try_from(row: OutboxEmailRow) -> Result<Self, Self::Error> {
+        let recipient = Email::parse(Secret::new(row.recipient))
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(eyre!(e)))?;
+
+        let status = DeliveryStatus::parse(&row.status)
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(OutboxEmail {
+            id: row.id,
+            recipient,
+            message: EmailMessage {
+                subject: row.subject,
+                html_body: row.html_body,
+                text_body: row.text_body,
+            },
+            status,
+            attempts: row.attempts,
+            last_error: row.last_error,
+            created_at: row.created_at,
+            next_attempt_at: row.next_attempt_at,
+            sent_at: row.sent_at,
+            expires_at: row.expires_at,
+        })
+    }
+}
+
+// Updates address a single email by id, nothing affected means it does not exist
+fn ensure_found(result: PgQueryResult) -> Result<(), EmailOutboxStoreError> {
+    if result.rows_affected() == 0 {
+        return Err(EmailOutboxStoreError::EmailNotFound);
+    }
+
+    Ok(())
+}
+
+pub struct PostgresEmailOutboxStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for PostgresEmailOutboxStore {
+    #[tracing::instrument(name = "Adding email to outbox in PostgreSQL", skip_all)]
+    async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        let sql = format!(
+            "insert into {} (id, recipient, subject, html_body, text_body, status, attempts, last_error, created_at, next_attempt_at, sent_at, expires_at) \
+             values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+            PG_EMAIL_OUTBOX_TABLE_NAME
+        );
+        sqlx::query(&sql)
+            .bind(email.id)
+            .bind(email.recipient.as_ref().expose_secret())
+            .bind(email.message.subject)
+            .bind(email.message.html_body)
+            .bind(email.message.text_body)
+            .bind(email.status.as_ref())
+            .bind(email.attempts)
+            .bind(email.last_error)
+            .bind(email.created_at)
+            .bind(email.next_attempt_at)
+            .bind(email.sent_at)
+            .bind(email.expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Claiming due emails from outbox in PostgreSQL", skip_all)]
+    async fn claim_due(&mut self, limit: i64, lease_until: DateTime<Utc>) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        // Rows locked by another worker are skipped rather than waited for
+        let sql = format!(
+            "update {table} set attempts = attempts + 1, next_attempt_at = $2 \
+             where id in ( \
+                 select id from {table} where status = 'pending' and next_attempt_at <= now() \
+                 order by next_attempt_at limit $1 for update skip locked \
+             ) \
+             returning *",
+            table = PG_EMAIL_OUTBOX_TABLE_NAME
+        );
+        sqlx::query_as::<_, OutboxEmailRow>(&sql)
+            .bind(limit)
+            .bind(lease_until)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?
+            .into_iter()
+            .map(OutboxEmail::try_from)
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Marking outbox email as sent in PostgreSQL", skip_all)]
+    async fn mark_sent(&mut self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+        let sql = format!(
+            "update {} set status = 'sent', sent_at = now(), last_error = null, html_body = '', text_body = '' \
+             where id = $1",
+            PG_EMAIL_OUTBOX_TABLE_NAME
+        );
+        let result = sqlx::query(&sql)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        ensure_found(result)
+    }
+
+    #[tracing::instrument(name = "Scheduling outbox email retry in PostgreSQL", skip_all)]
+    async fn schedule_retry(&mut self, id: Uuid, error: &str, retry_at: DateTime<Utc>) -> Result<(), EmailOutboxStoreError> {
+        let sql = format!(
+            "update {} set status = 'pending', last_error = $2, next_attempt_at = $3 where id = $1",
+            PG_EMAIL_OUTBOX_TABLE_NAME
+        );
+        let result = sqlx::query(&sql)
+            .bind(id)
+            .bind(error)
+            .bind(retry_at)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        ensure_found(result)
+    }
+
+    #[tracing::instrument(name = "Dead-lettering outbox email in PostgreSQL", skip_all)]
+    async fn mark_dead(&mut self, id: Uuid, error: &str) -> Result<(), EmailOutboxStoreError> {
+        let sql = format!(
+            "update {} set status = 'dead', last_error = $2, html_body = '', text_body = '' where id = $1",
+            PG_EMAIL_OUTBOX_TABLE_NAME
+        );
+        let result = sqlx::query(&sql)
+            .bind(id)
+            .bind(error)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        ensure_found(result)
+    }
+
+    #[tracing::instrument(name = "Retrieving outbox email from PostgreSQL", skip_all)]
+    async fn get_email(&self, id: Uuid) -> Result<OutboxEmail, EmailOutboxStoreError> {
+        let sql = format!("select * from {} where id = $1", PG_EMAIL_OUTBOX_TABLE_NAME);
+        sqlx::query_as::<_, OutboxEmailRow>(&sql)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?
+            .ok_or(EmailOutboxStoreError::EmailNotFound)?
+            .try_into()
+    }
+
+    #[tracing::instrument(name = "Querying outbox emails from PostgreSQL", skip_all)]
+    async fn query(&self, query: &OutboxQuery) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let sql = format!(
+            "select * from {} \
+             where ($1::text is null or lower(recipient) = lower($1)) \
+             and ($2::text is null or status = $2) \
+             order by created_at desc limit $3",
+            PG_EMAIL_OUTBOX_TABLE_NAME
+        );
+        sqlx::query_as::<_, OutboxEmailRow>(&sql)
+            .bind(query.recipient.as_ref().map(|email| email.as_ref().expose_secret()))
+            .bind(query.status.as_ref().map(|status| status.as_ref()))
+            .bind(query.limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?
+            .into_iter()
+            .map(OutboxEmail::try_from)
+            .collect()
+    }
+}
@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::{
+    domain::{
+        data_stores::{Invitation, InvitationCode, InvitationStore, InvitationStoreError},
+        email::Email,
+    },
+    utils::constants::PG_INVITATIONS_TABLE_NAME,
+};
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct InvitationRow {
+    pub code: String,
+    pub inviter: String,
+    pub email: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub max_uses: i32,
+    pub uses: i32,
+}
+
+impl TryFrom<InvitationRow> for Invitation {
+    type Error = InvitationStoreError;
+
+    fn try_from(row: InvitationRow) -> Result<Self, Self::Error> {
+        let code = InvitationCode::parse(Secret::new(row.code))
+            .map_err(|e| InvitationStoreError::UnexpectedError(eyre!(e)))?;
+
+        let inviter = Email::parse(Secret::new(row.inviter))
+            .map_err(|e| InvitationStoreError::UnexpectedError(eyre!(e)))?;
+
+        let email = row.email
+            .map(|email| Email::parse(Secret::new(email)))
+            .transpose()
+            .map_err(|e| InvitationStoreError::UnexpectedError(eyre!(e)))?;
+
+        Ok(Invitation {
+            code,
+            inviter,
+            email,
+            expires_at: row.expires_at,
+            max_uses: row.max_uses,
+            uses: row.uses,
+        })
+    }
+}
+
+pub struct PostgresInvitationStore {
+    pool: PgPool,
+}
+
+impl PostgresInvitationStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl InvitationStore for PostgresInvitationStore {
+    #[tracing::instrument(name = "Adding invitation to PostgreSQL", skip_all)]
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), InvitationStoreError> {
+        let sql = format!(
+            "insert into {} (code, inviter, email, expires_at, max_uses, uses) values ($1, $2, $3, $4, $5, $6)",
+            PG_INVITATIONS_TABLE_NAME
+        );
+        sqlx::query(&sql)
+            .bind(invitation.code.as_ref().expose_secret())
+            .bind(invitation.inviter.as_ref().expose_secret())
+            .bind(invitation.email.as_ref().map(|email| email.as_ref().expose_secret()))
+            .bind(invitation.expires_at)
+            .bind(invitation.max_uses)
+            .bind(invitation.uses)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving invitation from PostgreSQL", skip_all)]
+    async fn get_invitation(&self, code: &InvitationCode) -> Result<Invitation, InvitationStoreError> {
+        let sql = format!("select * from {} where code = $1", PG_INVITATIONS_TABLE_NAME);
+        sqlx::query_as::<_, InvitationRow>(&sql)
+            .bind(code.as_ref().expose_secret())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?
+            .ok_or(InvitationStoreError::InvitationNotFound)?
+            .try_into()
+    }
+
+    #[tracing::instrument(name = "Redeeming invitation in PostgreSQL", skip_all)]
+    async fn redeem_invitation(&mut self, code: &InvitationCode, email: &Email) -> Result<(), InvitationStoreError> {
+        // A single conditional update, so concurrent signups can not exceed max_uses
+        let sql = format!(
+            "update {} set uses = uses + 1 \
+             where code = $1 and uses < max_uses and expires_at > now() \
+             and (email is null or lower(email) = lower($2))",
+            PG_INVITATIONS_TABLE_NAME
+        );
+        let result = sqlx::query(&sql)
+            .bind(code.as_ref().expose_secret())
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            // Tell a missing invitation apart from one that can not be used (anymore)
+            self.get_invitation(code).await?;
+            return Err(InvitationStoreError::InvitationNotUsable);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Releasing invitation in PostgreSQL", skip_all)]
+    async fn release_invitation(&mut self, code: &InvitationCode) -> Result<(), InvitationStoreError> {
+        let sql = format!("update {} set uses = uses - 1 where code = $1 and uses > 0", PG_INVITATIONS_TABLE_NAME);
+        let result = sqlx::query(&sql)
+            .bind(code.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(InvitationStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking invitation in PostgreSQL", skip_all)]
+    async fn revoke_invitation(&mut self, code: &InvitationCode) -> Result<(), InvitationStoreError> {
+        let sql = format!("delete from {} where code = $1", PG_INVITATIONS_TABLE_NAME);
+        let result = sqlx::query(&sql)
+            .bind(code.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(InvitationStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -1,28 +1,113 @@
 use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
+    password_hash::{Output, SaltString}, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
+    Version,
 };
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use pbkdf2::{Algorithm as Pbkdf2Algorithm, Pbkdf2};
+use sha2::Sha256;
 use color_eyre::eyre::{eyre, Context, Result};
 use serde::{Serialize, Deserialize};
 use sqlx::PgPool;
 use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
 
-use crate::{domain::{data_stores::{UserStore, UserStoreError}, email::Email, password::Password, user::User}, utils::constants::PG_TABLE_NAME};
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        email::Email,
+        locale::Locale,
+        organisation::{Membership, OrgRole, Organisation},
+        password::Password,
+        user::{AccountStatus, User, UserRole},
+    },
+    utils::constants::{PG_MEMBERSHIPS_TABLE_NAME, PG_ORGANISATIONS_TABLE_NAME, PG_TABLE_NAME},
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
 pub struct Users {
     pub email: String,
     pub password_hash: String,
     pub requires_2fa: bool,
+    pub status: String,
+    pub role: String,
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OrganisationRow {
+    pub id: Uuid,
+    pub slug: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MembershipRow {
+    pub id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub role: String,
+}
+
+// Argon2id cost parameters new hashes are computed with. Hashes computed with anything
+// else are upgraded the next time their owner logs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct PasswordHashParams {
+    pub memory_cost: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordHashParams {
+    fn default() -> Self {
+        Self { memory_cost: 15000, iterations: 2, parallelism: 1 }
+    }
+}
+
+impl PasswordHashParams {
+    fn argon2(&self) -> Result<Argon2<'static>> {
+        let params = Params::new(self.memory_cost, self.iterations, self.parallelism, None)?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    fn matches(&self, password_hash: &Secret<String>) -> bool {
+        let Ok(hash) = PasswordHash::new(password_hash.expose_secret()) else {
+            return false;
+        };
+
+        if hash.algorithm != Algorithm::Argon2id.ident() || hash.version != Some(Version::V0x13.into()) {
+            return false;
+        }
+
+        Params::try_from(&hash).is_ok_and(|params| {
+            params.m_cost() == self.memory_cost
+                && params.t_cost() == self.iterations
+                && params.p_cost() == self.parallelism
+        })
+    }
 }
 
 pub struct PostgresUserStore {
     pool: PgPool,
+    hash_params: PasswordHashParams,
 }
 
 impl PostgresUserStore {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, hash_params: PasswordHashParams) -> Self {
+        Self { pool, hash_params }
+    }
+
+    #[tracing::instrument(name = "Upgrading password hash in PostgreSQL", skip_all)]
+    async fn rehash_password(&self, email: &Email, password: &Password) -> Result<()> {
+        let password_hash = compute_password_hash(password.as_ref().to_owned(), self.hash_params).await?;
+
+        let sql = format!("update {} set password_hash = $1 where lower(email) = lower($2)", PG_TABLE_NAME);
+        sqlx::query(&sql)
+            .bind(password_hash.expose_secret())
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
     }
 }
 
@@ -30,25 +115,32 @@ impl PostgresUserStore {
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        let password = compute_password_hash(user.password.as_ref().to_owned())
+        let password = compute_password_hash(user.password.as_ref().to_owned(), self.hash_params)
             .await
             .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-        let sql = format!("insert into {} (email, password_hash, requires_2fa) values ($1, $2, $3)", PG_TABLE_NAME);
+        let sql = format!("insert into {} (email, password_hash, requires_2fa, status, role, locale) values ($1, $2, $3, $4, $5, $6)", PG_TABLE_NAME);
         sqlx::query(&sql)
             .bind(user.email.as_ref().expose_secret())
             .bind(password.expose_secret())
             .bind(user.requires_2fa)
+            .bind(user.status.as_ref())
+            .bind(user.role.as_ref())
+            .bind(user.locale.as_ref().map(Locale::as_ref))
             .execute(&self.pool)
             .await
-            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+            .map_err(|e| match e.as_database_error() {
+                // Same address in a different case, see the unique index on lower(email)
+                Some(db_error) if db_error.is_unique_violation() => UserStoreError::UserAlreadyExists,
+                _ => UserStoreError::UnexpectedError(e.into()),
+            })?;
 
         Ok(())
     }
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)] 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        let sql = format!("select * from {} where email = $1", PG_TABLE_NAME);
+        let sql = format!("select * from {} where lower(email) = lower($1)", PG_TABLE_NAME);
         let query = sqlx::query_as::<_, Users>(&sql);
         query
             .bind(email.as_ref().expose_secret())
@@ -59,10 +151,22 @@ impl UserStore for PostgresUserStore {
                 let email = Email::parse(Secret::new(u.email))
                     .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
 
-                let password = Password::parse(Secret::new(u.password_hash))
+                let password = Password::parse_credential(Secret::new(u.password_hash))
                     .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?;
 
-                Ok(User::new(email, password, u.requires_2fa))
+                let status = AccountStatus::parse(&u.status)
+                    .map_err(UserStoreError::UnexpectedError)?;
+
+                let role = UserRole::parse(&u.role)
+                    .map_err(UserStoreError::UnexpectedError)?;
+
+                let locale = u.locale
+                    .as_deref()
+                    .map(Locale::parse)
+                    .transpose()
+                    .map_err(UserStoreError::UnexpectedError)?;
+
+                Ok(User { email, password, requires_2fa: u.requires_2fa, status, role, locale })
             })
             .ok_or(UserStoreError::UserNotFound)?
     }
@@ -70,17 +174,182 @@ impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Validating user credentials in PostgreSQL", skip_all)]
     async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError> {
         let user = self.get_user(email).await?;
+        let password_hash = user.password.as_ref().to_owned();
 
         verify_password_hash(
-            user.password.as_ref().to_owned(),
+            password_hash.clone(),
             password.as_ref().to_owned(),
         )
         .await
-        .map_err(|_| UserStoreError::InvalidCredentials)
+        .map_err(|_| UserStoreError::InvalidCredentials)?;
+
+        // The plaintext is only ever available here, so this is where outdated hashes get upgraded.
+        // Failing to do so must not fail the login itself.
+        if !self.hash_params.matches(&password_hash) {
+            if let Err(e) = self.rehash_password(email, password).await {
+                tracing::warn!("failed to upgrade password hash: {:?}", e);
+            }
+        }
+
+        Ok(())
     }
 
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
     async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
-        todo!()
+        let sql = format!("delete from {} where lower(email) = lower($1)", PG_TABLE_NAME);
+        let res = sqlx::query(&sql)
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user status in PostgreSQL", skip_all)]
+    async fn update_status(&mut self, email: &Email, status: AccountStatus) -> Result<(), UserStoreError> {
+        let sql = format!("update {} set status = $1 where lower(email) = lower($2)", PG_TABLE_NAME);
+        let res = sqlx::query(&sql)
+            .bind(status.as_ref())
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user role in PostgreSQL", skip_all)]
+    async fn update_role(&mut self, email: &Email, role: UserRole) -> Result<(), UserStoreError> {
+        let sql = format!("update {} set role = $1 where lower(email) = lower($2)", PG_TABLE_NAME);
+        let res = sqlx::query(&sql)
+            .bind(role.as_ref())
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user locale in PostgreSQL", skip_all)]
+    async fn update_locale(&mut self, email: &Email, locale: Option<Locale>) -> Result<(), UserStoreError> {
+        let sql = format!("update {} set locale = $1 where lower(email) = lower($2)", PG_TABLE_NAME);
+        let res = sqlx::query(&sql)
+            .bind(locale.as_ref().map(Locale::as_ref))
+            .bind(email.as_ref().expose_secret())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Adding organisation to PostgreSQL", skip_all)]
+    async fn add_organisation(&mut self, organisation: Organisation) -> Result<(), UserStoreError> {
+        let sql = format!("insert into {} (id, slug, name) values ($1, $2, $3)", PG_ORGANISATIONS_TABLE_NAME);
+        sqlx::query(&sql)
+            .bind(organisation.id)
+            .bind(&organisation.slug)
+            .bind(&organisation.name)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e.as_database_error() {
+                Some(db_error) if db_error.is_unique_violation() => UserStoreError::OrganisationAlreadyExists,
+                _ => UserStoreError::UnexpectedError(e.into()),
+            })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving organisation from PostgreSQL", skip_all)]
+    async fn get_organisation(&self, slug: &str) -> Result<Organisation, UserStoreError> {
+        let sql = format!("select id, slug, name from {} where slug = $1", PG_ORGANISATIONS_TABLE_NAME);
+        sqlx::query_as::<_, OrganisationRow>(&sql)
+            .bind(slug)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+            .map(|row| Organisation { id: row.id, slug: row.slug, name: row.name })
+            .ok_or(UserStoreError::OrganisationNotFound)
+    }
+
+    #[tracing::instrument(name = "Setting organisation membership in PostgreSQL", skip_all)]
+    async fn set_membership(&mut self, email: &Email, organisation_id: Uuid, role: OrgRole) -> Result<(), UserStoreError> {
+        // Selecting the stored address keeps the foreign key intact whatever case `email` is in
+        let sql = format!(
+            "insert into {} (organisation_id, email, role) \
+             select $1, email, $3 from {} where lower(email) = lower($2) \
+             on conflict (organisation_id, email) do update set role = excluded.role",
+            PG_MEMBERSHIPS_TABLE_NAME, PG_TABLE_NAME
+        );
+        let res = sqlx::query(&sql)
+            .bind(organisation_id)
+            .bind(email.as_ref().expose_secret())
+            .bind(role.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e.as_database_error() {
+                Some(db_error) if db_error.is_foreign_key_violation() => UserStoreError::OrganisationNotFound,
+                _ => UserStoreError::UnexpectedError(e.into()),
+            })?;
+
+        if res.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving organisation memberships from PostgreSQL", skip_all)]
+    async fn get_memberships(&self, email: &Email) -> Result<Vec<Membership>, UserStoreError> {
+        let sql = format!(
+            "select o.id, o.slug, o.name, m.role from {} m join {} o on o.id = m.organisation_id \
+             where lower(m.email) = lower($1) order by o.slug",
+            PG_MEMBERSHIPS_TABLE_NAME, PG_ORGANISATIONS_TABLE_NAME
+        );
+        sqlx::query_as::<_, MembershipRow>(&sql)
+            .bind(email.as_ref().expose_secret())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+            .into_iter()
+            .map(|row| {
+                let role = OrgRole::parse(&row.role)
+                    .map_err(UserStoreError::UnexpectedError)?;
+
+                Ok(Membership {
+                    organisation: Organisation { id: row.id, slug: row.slug, name: row.name },
+                    role,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Checking PostgreSQL health", skip_all)]
+    async fn health_check(&self) -> Result<(), UserStoreError> {
+        sqlx::query("select 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
     }
 }
 
@@ -90,10 +359,30 @@ async fn verify_password_hash(
     password_candidate: Secret<String>, 
 ) -> Result<()> {
     let res = tokio::task::spawn_blocking(move || {
-        let expected_password_hash: PasswordHash<'_> = PasswordHash::new(&expected_password_hash.expose_secret())?;
+        let expected_password_hash = expected_password_hash.expose_secret();
+        let password_candidate = password_candidate.expose_secret().as_bytes();
+
+        // Users imported from the previous system still carry bcrypt or PBKDF2 hashes until their first login
+        if is_bcrypt_hash(expected_password_hash) {
+            return verify_bcrypt_hash(expected_password_hash, password_candidate);
+        }
 
-        Argon2::default()
-            .verify_password(password_candidate.expose_secret().as_bytes(), &expected_password_hash)
+        if is_passlib_pbkdf2_hash(expected_password_hash) {
+            return verify_passlib_pbkdf2_hash(expected_password_hash, password_candidate);
+        }
+
+        let expected_password_hash: PasswordHash<'_> = PasswordHash::new(expected_password_hash)?;
+
+        // `Pbkdf2` would also accept PBKDF2-SHA1, which the previous system never used
+        if let Ok(algorithm) = Pbkdf2Algorithm::try_from(expected_password_hash.algorithm) {
+            if !matches!(algorithm, Pbkdf2Algorithm::Pbkdf2Sha256 | Pbkdf2Algorithm::Pbkdf2Sha512) {
+                return Err(eyre!("unsupported password hash algorithm {}", expected_password_hash.algorithm));
+            }
+        }
+
+        // Algorithm, version and cost parameters are taken from the hash, not from the default instances
+        expected_password_hash
+            .verify_password(&[&Argon2::default(), &Pbkdf2], password_candidate)
             .wrap_err("failed to verify password hash")
     })
     .await;
@@ -101,20 +390,66 @@ async fn verify_password_hash(
     res?
 }
 
+// Modular crypt format: $2b$<cost>$<22 character salt><31 character hash>
+fn is_bcrypt_hash(password_hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|prefix| password_hash.starts_with(prefix))
+}
+
+fn verify_bcrypt_hash(password_hash: &str, password_candidate: &[u8]) -> Result<()> {
+    if !bcrypt::verify(password_candidate, password_hash)? {
+        return Err(eyre!("failed to verify bcrypt password hash"));
+    }
+
+    Ok(())
+}
+
+// Passlib's modular crypt flavour of PBKDF2, as opposed to the PHC string
+// `$pbkdf2-sha256$i=<rounds>,l=<length>$...` which `Pbkdf2` verifies directly:
+// $pbkdf2-sha256$<rounds>$<salt>$<checksum>, salt and checksum in "adapted" base64
+fn is_passlib_pbkdf2_hash(password_hash: &str) -> bool {
+    let mut parts = password_hash.split('$');
+
+    parts.next() == Some("")
+        && parts.next() == Some("pbkdf2-sha256")
+        && parts.next().is_some_and(|rounds| !rounds.is_empty() && rounds.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn verify_passlib_pbkdf2_hash(password_hash: &str, password_candidate: &[u8]) -> Result<()> {
+    let [_, _, rounds, salt, checksum] = password_hash.split('$').collect::<Vec<_>>()[..] else {
+        return Err(eyre!("malformed PBKDF2 password hash"));
+    };
+
+    let rounds: u32 = rounds.parse()?;
+    let salt = decode_adapted_base64(salt)?;
+    let expected = decode_adapted_base64(checksum)?;
+
+    let mut derived = vec![0u8; expected.len()];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password_candidate, &salt, rounds, &mut derived);
+
+    // Output compares in constant time
+    if Output::new(&derived)? != Output::new(&expected)? {
+        return Err(eyre!("failed to verify PBKDF2 password hash"));
+    }
+
+    Ok(())
+}
+
+// Standard base64 without padding, with '.' in place of '+'
+fn decode_adapted_base64(input: &str) -> Result<Vec<u8>> {
+    Ok(STANDARD_NO_PAD.decode(input.replace('.', "+"))?)
+}
+
 #[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
+async fn compute_password_hash(password: Secret<String>, hash_params: PasswordHashParams) -> Result<Secret<String>> {
     let current_span: tracing::Span = tracing::Span::current();
 
     let res = tokio::task::spawn_blocking(move || {
         current_span.in_scope(|| {
             let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-            let password_hash = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
-            )
-            .hash_password(password.expose_secret().as_bytes(), &salt)?
-            .to_string();
+            let password_hash = hash_params
+                .argon2()?
+                .hash_password(password.expose_secret().as_bytes(), &salt)?
+                .to_string();
     
             Ok(Secret::new(password_hash))
         })
@@ -122,4 +457,71 @@ async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String
     .await;
 
     res?
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use pbkdf2::Params as Pbkdf2Params;
+
+    use super::*;
+
+    const PASSWORD: &str = "password123";
+    // Generated with passlib's pbkdf2_sha256, 1000 rounds
+    const PASSLIB_PBKDF2_HASH: &str = "$pbkdf2-sha256$1000$....AXNhbHRzYWx0MTIzNA$Tb3MMmnM8ChA16.iuLjuXtaRdBLsoJrd3n4kroP2eD4";
+
+    async fn verify(password_hash: &str, password: &str) -> Result<()> {
+        verify_password_hash(Secret::new(password_hash.to_owned()), Secret::new(password.to_owned())).await
+    }
+
+    #[tokio::test]
+    async fn test_verify_argon2_hash() {
+        let password_hash = compute_password_hash(Secret::new(PASSWORD.to_owned()), PasswordHashParams::default())
+            .await
+            .unwrap();
+
+        assert!(verify(password_hash.expose_secret(), PASSWORD).await.is_ok());
+        assert!(verify(password_hash.expose_secret(), "wrong-password").await.is_err());
+        assert!(PasswordHashParams::default().matches(&password_hash));
+    }
+
+    #[tokio::test]
+    async fn test_verify_bcrypt_hash() {
+        let password_hash = bcrypt::hash(PASSWORD, 4).unwrap();
+
+        assert!(verify(&password_hash, PASSWORD).await.is_ok());
+        assert!(verify(&password_hash, "wrong-password").await.is_err());
+        assert!(!PasswordHashParams::default().matches(&Secret::new(password_hash)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_pbkdf2_phc_hash() {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let params = Pbkdf2Params { rounds: 1000, output_length: 32 };
+        let password_hash = Pbkdf2
+            .hash_password_customized(PASSWORD.as_bytes(), None, None, params, &salt)
+            .unwrap()
+            .to_string();
+
+        assert!(password_hash.starts_with("$pbkdf2-sha256$i=1000,l=32$"));
+        assert!(verify(&password_hash, PASSWORD).await.is_ok());
+        assert!(verify(&password_hash, "wrong-password").await.is_err());
+        assert!(!PasswordHashParams::default().matches(&Secret::new(password_hash)));
+    }
+
+    #[tokio::test]
+    async fn test_reject_pbkdf2_sha1_phc_hash() {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let mut derived = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<sha1::Sha1>(PASSWORD.as_bytes(), salt.as_str().as_bytes(), 1000, &mut derived);
+        let password_hash = format!("$pbkdf2$i=1000,l=32${}${}", salt.as_str(), Output::new(&derived).unwrap());
+
+        assert!(verify(&password_hash, PASSWORD).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_passlib_pbkdf2_hash() {
+        assert!(verify(PASSLIB_PBKDF2_HASH, PASSWORD).await.is_ok());
+        assert!(verify(PASSLIB_PBKDF2_HASH, "wrong-password").await.is_err());
+        assert!(verify("$pbkdf2-sha256$1000$not-base64$", PASSWORD).await.is_err());
+    }
+}
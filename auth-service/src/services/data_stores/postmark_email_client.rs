@@ -2,7 +2,10 @@ use color_eyre::eyre::Result;
 use reqwest::{Client, Url};
 use secrecy::{ExposeSecret, Secret};
 
-use crate::domain::{email::Email, email_client::EmailClient}; 
+use crate::{
+    domain::{email::Email, email_client::{EmailClient, EmailMessage}},
+    utils::telemetry::trace_context_headers,
+};
 
 pub struct PostmarkEmailClient {
     http_client: Client, 
@@ -20,7 +23,7 @@ impl PostmarkEmailClient {
 #[async_trait::async_trait]
 impl EmailClient for PostmarkEmailClient {
     #[tracing::instrument(name = "Sending email", skip_all)]
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
         let base = Url::parse(&self.base_url)?;
         let url = base.join("/email")?;
 
@@ -28,9 +31,9 @@ impl EmailClient for PostmarkEmailClient {
         let request_body = SendEmailRequest {
             from: self.sender.as_ref().expose_secret(),
             to: recipient.as_ref().expose_secret(),
-            subject,
-            html_body: content,
-            text_body: content,
+            subject: &message.subject,
+            html_body: &message.html_body,
+            text_body: &message.text_body,
             message_stream: MESSAGE_STREAM,
         };
 
@@ -38,6 +41,7 @@ impl EmailClient for PostmarkEmailClient {
         let request = self
             .http_client
             .post(url)
+            .headers(trace_context_headers())
             .header(
                 POSTMARK_AUTH_HEADER,
                 self.authorization_token.expose_secret(), // Securely expose the authorization token
@@ -49,6 +53,22 @@ impl EmailClient for PostmarkEmailClient {
 
         Ok(())
     }
+
+    // Fetches the server the token belongs to, which fails if Postmark is down or rejects the token
+    #[tracing::instrument(name = "Checking Postmark health", skip_all)]
+    async fn health_check(&self) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join("/server")?;
+
+        self.http_client
+            .get(url)
+            .headers(trace_context_headers())
+            .header(POSTMARK_AUTH_HEADER, self.authorization_token.expose_secret())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
 }
 
 // Constants for message stream and authorization header
@@ -91,6 +111,15 @@ mod tests {
         Paragraph(1..10).fake()
     }
 
+    // Helper function to generate a test message
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: subject(),
+            html_body: format!("<p>{}</p>", content()),
+            text_body: content(),
+        }
+    }
+
     // Helper function to generate a test email
     fn email() -> Email {
         Email::parse(Secret::new(SafeEmail().fake())).unwrap()
@@ -143,7 +172,7 @@ mod tests {
 
         // Execute the send_email function and check the outcome
         let outcome = email_client
-            .send_email(&email(), &subject(), &content())
+            .send_email(&email(), &message())
             .await;
 
         assert!(outcome.is_ok());
@@ -164,7 +193,7 @@ mod tests {
 
         // Execute the send_email function and check the outcome
         let outcome = email_client
-            .send_email(&email(), &subject(), &content())
+            .send_email(&email(), &message())
             .await;
 
         assert!(outcome.is_err());
@@ -186,9 +215,41 @@ mod tests {
 
         // Execute the send_email function and check the outcome
         let outcome = email_client
-            .send_email(&email(), &subject(), &content())
+            .send_email(&email(), &message())
             .await;
 
         assert!(outcome.is_err());
     }
-}
\ No newline at end of file
+
+    // Test to ensure the health check authenticates against the server endpoint
+    #[tokio::test]
+    async fn health_check_fetches_the_server() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists(POSTMARK_AUTH_HEADER))
+            .and(path("/server"))
+            .and(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(email_client.health_check().await.is_ok());
+    }
+
+    // Test to handle a rejected token
+    #[tokio::test]
+    async fn health_check_fails_if_the_server_returns_401() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(email_client.health_check().await.is_err());
+    }
+}
@@ -1,22 +1,21 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use color_eyre::eyre::{Context, Result};
 use redis::{Commands, Connection};
 use secrecy::{ExposeSecret, Secret};
 use tokio::sync::RwLock;
 
-use crate::{
-    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
-};
+use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
 
 pub struct RedisBannedTokenStore {
     conn: Arc<RwLock<Connection>>,
+    // Tokens only need to stay banned until they would have expired anyway
+    token_ttl: Duration,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: Arc<RwLock<Connection>>, token_ttl: Duration) -> Self {
+        Self { conn, token_ttl }
     }
 }
 
@@ -27,10 +26,7 @@ impl BannedTokenStore for RedisBannedTokenStore {
         let key = get_key(token.expose_secret());
         let value = true;
 
-        let secs: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .wrap_err("failed to cast TOKEN_TTL_SECONDS to u64")
-            .map_err(BannedTokenStoreError::UnexpectedError)?;
+        let secs = self.token_ttl.as_secs();
 
         let _: () = self
             .conn
@@ -57,10 +53,82 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
         Ok(is_banned)
     }
+
+    #[tracing::instrument(name = "Checking banned token store health", skip_all)]
+    async fn health_check(&self) -> Result<(), BannedTokenStoreError> {
+        ping_redis(self.conn.clone())
+            .await
+            .map_err(BannedTokenStoreError::UnexpectedError)
+    }
+}
+
+// The PING blocks until Redis answers or the connection's read timeout passes, so it runs on the
+// blocking pool where it can't hold up a runtime worker and the probe's own timeout can fire
+pub(crate) async fn ping_redis(conn: Arc<RwLock<Connection>>) -> Result<()> {
+    let _: String =
+        tokio::task::spawn_blocking(move || redis::cmd("PING").query(&mut *conn.blocking_write()))
+            .await?
+            .wrap_err("failed to ping Redis")?;
+
+    Ok(())
 }
 
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
 
 fn get_key(token: &str) -> String {
     format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{get_redis_client, get_redis_connection};
+
+    // Answers the CLIENT SETINFO handshake sent on connect, then nothing until the socket is closed
+    fn unresponsive_redis(hold_for: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0u8; 1024];
+            let _ = stream.read(&mut buffer);
+            let _ = stream.write_all(b"+OK\r\n+OK\r\n");
+            std::thread::sleep(hold_for);
+        });
+
+        address
+    }
+
+    fn store(conn: Connection) -> RedisBannedTokenStore {
+        RedisBannedTokenStore::new(Arc::new(RwLock::new(conn)), Duration::from_secs(60))
+    }
+
+    #[tokio::test]
+    async fn test_health_check_gives_up_on_unresponsive_redis() {
+        let address = unresponsive_redis(Duration::from_secs(30));
+        let store = store(get_redis_connection(address, Duration::from_millis(200)).unwrap());
+
+        let result = tokio::time::timeout(Duration::from_secs(5), store.health_check())
+            .await
+            .expect("Health check did not time out");
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_health_check_does_not_block_the_runtime() {
+        // Without a read timeout only closing the socket ends the PING
+        let address = unresponsive_redis(Duration::from_secs(1));
+        let conn = get_redis_client(address).unwrap().get_connection().unwrap();
+        let store = store(conn);
+
+        // Runs on the single test runtime thread, so this only fires if the PING is off it
+        let result = tokio::time::timeout(Duration::from_millis(200), store.health_check()).await;
+        assert!(result.is_err());
+    }
+}
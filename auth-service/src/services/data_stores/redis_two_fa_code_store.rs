@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use color_eyre::eyre::Context;
 use redis::{Commands, Connection};
@@ -11,13 +11,17 @@ use crate::domain::{
     email::Email,
 };
 
+use super::ping_redis;
+
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<Connection>>,
+    // Also how long the code in the 2FA email is advertised as valid
+    code_ttl: Duration,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: Arc<RwLock<Connection>>, code_ttl: Duration) -> Self {
+        Self { conn, code_ttl }
     }
 }
 
@@ -32,11 +36,8 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
             .wrap_err("failed to serialize 2FA tuple")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
         
-        let secs: u64 = TEN_MINUTES_IN_SECONDS
-            .try_into()
-            .wrap_err("failed to convert TEN_MINUTES_IN_SECONDS")
-            .map_err(TwoFACodeStoreError::UnexpectedError)?;
-        
+        let secs = self.code_ttl.as_secs();
+
         let _: () = self
             .conn
             .write()
@@ -84,14 +85,20 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
             Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    #[tracing::instrument(name = "Checking 2FA code store health", skip_all)]
+    async fn health_check(&self) -> Result<(), TwoFACodeStoreError> {
+        ping_redis(self.conn.clone())
+            .await
+            .map_err(TwoFACodeStoreError::UnexpectedError)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
-const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 
 fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret().to_lowercase())
 }
\ No newline at end of file
@@ -0,0 +1,359 @@
+use std::time::Duration;
+
+use color_eyre::eyre::{eyre, Result};
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::{authentication::Credentials, PoolConfig},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::domain::{email::Email, email_client::{EmailClient, EmailMessage}};
+
+// How the connection to the relay is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    // Plain connection, only meant for relays on localhost or in tests
+    None,
+    // Upgrade a plain connection with STARTTLS, refusing relays that don't offer it
+    #[default]
+    StartTls,
+    // TLS from the first byte, aka SMTPS
+    Tls,
+}
+
+impl SmtpTls {
+    fn default_port(&self) -> u16 {
+        match self {
+            Self::None => 25,
+            Self::StartTls => 587,
+            Self::Tls => 465,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    // Defaults to the usual port of the TLS mode
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    // Authenticates with the password from SMTP_PASSWORD when set
+    #[serde(default)]
+    pub username: Option<String>,
+    // Connections kept open to the relay and reused between emails
+    #[serde(default = "default_pool_size")]
+    pub pool_size: u32,
+}
+
+fn default_pool_size() -> u32 {
+    4
+}
+
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Email,
+    timeout: Duration,
+}
+
+impl SmtpEmailClient {
+    pub fn new(config: &SmtpConfig, password: Option<Secret<String>>, sender: Email, timeout: Duration) -> Result<Self> {
+        let builder = match config.tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?,
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+        };
+
+        let mut builder = builder
+            .port(config.port.unwrap_or(config.tls.default_port()))
+            .timeout(Some(timeout))
+            .pool_config(PoolConfig::new().max_size(config.pool_size));
+
+        if let Some(username) = &config.username {
+            let password = password.map(|p| p.expose_secret().to_owned()).unwrap_or_default();
+            builder = builder.credentials(Credentials::new(username.to_owned(), password));
+        }
+
+        Ok(Self { transport: builder.build(), sender, timeout })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email over SMTP", skip_all)]
+    async fn send_email(&self, recipient: &Email, message: &EmailMessage) -> Result<()> {
+        let email = mime_message(&self.sender, recipient, message)?;
+
+        // lettre only bounds single commands, a relay that accepts the connection but never
+        // greets would stall the request otherwise
+        tokio::time::timeout(self.timeout, self.transport.send(email))
+            .await
+            .map_err(|_| eyre!("SMTP relay did not respond within {:?}", self.timeout))??;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking SMTP relay health", skip_all)]
+    async fn health_check(&self) -> Result<()> {
+        let connected = tokio::time::timeout(self.timeout, self.transport.test_connection())
+            .await
+            .map_err(|_| eyre!("SMTP relay did not respond within {:?}", self.timeout))??;
+
+        match connected {
+            true => Ok(()),
+            false => Err(eyre!("SMTP relay refused the connection")),
+        }
+    }
+}
+
+// Builds the multipart/alternative message with the plain-text and HTML parts
+pub(crate) fn mime_message(sender: &Email, recipient: &Email, message: &EmailMessage) -> Result<Message> {
+    let from: Mailbox = sender.as_ref().expose_secret().parse()?;
+    let to: Mailbox = recipient.as_ref().expose_secret().parse()?;
+
+    let email = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(&message.subject)
+        .multipart(MultiPart::alternative_plain_html(
+            message.text_body.to_owned(),
+            message.html_body.to_owned(),
+        ))?;
+
+    Ok(email)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::utils::constants::test;
+
+    use super::*;
+
+    #[derive(Debug, Default, Clone)]
+    struct ReceivedEmail {
+        credentials: Option<String>,
+        from: String,
+        to: Vec<String>,
+        data: String,
+    }
+
+    #[derive(Default)]
+    struct Inbox {
+        connections: usize,
+        emails: Vec<ReceivedEmail>,
+    }
+
+    // Just enough of an SMTP server to accept mail from lettre, optionally rejecting recipients
+    struct FakeSmtpServer {
+        port: u16,
+        inbox: Arc<Mutex<Inbox>>,
+    }
+
+    impl FakeSmtpServer {
+        async fn start(reject_recipients: bool) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let inbox = Arc::new(Mutex::new(Inbox::default()));
+
+            let server_inbox = inbox.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    server_inbox.lock().unwrap().connections += 1;
+                    tokio::spawn(Self::handle(stream, server_inbox.clone(), reject_recipients));
+                }
+            });
+
+            Self { port, inbox }
+        }
+
+        async fn handle(stream: TcpStream, inbox: Arc<Mutex<Inbox>>, reject_recipients: bool) {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut email = ReceivedEmail::default();
+            let mut credentials = None;
+
+            writer.write_all(b"220 localhost ESMTP fake\r\n").await.unwrap();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let command = line.to_ascii_uppercase();
+                let reply: &[u8] = if command.starts_with("EHLO") {
+                    b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n"
+                } else if let Some(response) = line.strip_prefix("AUTH PLAIN ") {
+                    let decoded = STANDARD.decode(response).unwrap();
+                    credentials = Some(String::from_utf8(decoded).unwrap().replace('\0', " ").trim().to_owned());
+                    b"235 Authenticated\r\n"
+                } else if command.starts_with("MAIL FROM:") {
+                    email.from = line[10..].to_owned();
+                    b"250 OK\r\n"
+                } else if command.starts_with("RCPT TO:") {
+                    if reject_recipients {
+                        b"550 No such user\r\n"
+                    } else {
+                        email.to.push(line[8..].to_owned());
+                        b"250 OK\r\n"
+                    }
+                } else if command == "DATA" {
+                    if writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await.is_err() {
+                        return;
+                    }
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if line == "." {
+                            break;
+                        }
+                        email.data.push_str(&line);
+                        email.data.push('\n');
+                    }
+                    email.credentials = credentials.clone();
+                    inbox.lock().unwrap().emails.push(std::mem::take(&mut email));
+                    b"250 Queued\r\n"
+                } else if command == "QUIT" {
+                    let _ = writer.write_all(b"221 Bye\r\n").await;
+                    return;
+                } else {
+                    b"250 OK\r\n"
+                };
+                if writer.write_all(reply).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        fn config(&self, tls: SmtpTls, username: Option<&str>) -> SmtpConfig {
+            SmtpConfig {
+                host: "127.0.0.1".to_owned(),
+                port: Some(self.port),
+                tls,
+                username: username.map(str::to_owned),
+                pool_size: 2,
+            }
+        }
+
+        fn emails(&self) -> Vec<ReceivedEmail> {
+            self.inbox.lock().unwrap().emails.clone()
+        }
+
+        fn connections(&self) -> usize {
+            self.inbox.lock().unwrap().connections
+        }
+    }
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            subject: "Your code".to_owned(),
+            html_body: "<p>Your code is 123456</p>".to_owned(),
+            text_body: "Your code is 123456".to_owned(),
+        }
+    }
+
+    fn email_client(config: &SmtpConfig, password: Option<&str>) -> SmtpEmailClient {
+        SmtpEmailClient::new(
+            config,
+            password.map(|p| Secret::new(p.to_owned())),
+            email(test::email_client::SENDER),
+            test::email_client::TIMEOUT,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_a_multipart_message() {
+        let server = FakeSmtpServer::start(false).await;
+        let email_client = email_client(&server.config(SmtpTls::None, Some("mailer")), Some("hunter2"));
+
+        let outcome = email_client.send_email(&email("user@example.com"), &message()).await;
+        assert!(outcome.is_ok(), "{:?}", outcome);
+
+        let emails = server.emails();
+        assert_eq!(emails.len(), 1);
+        let received = &emails[0];
+        assert_eq!(received.credentials.as_deref(), Some("mailer hunter2"));
+        assert_eq!(received.from, format!("<{}>", test::email_client::SENDER));
+        assert_eq!(received.to, vec!["<user@example.com>".to_owned()]);
+        assert!(received.data.contains("Subject: Your code"));
+        assert!(received.data.contains("multipart/alternative"));
+        assert!(received.data.contains("Content-Type: text/plain"));
+        assert!(received.data.contains("Content-Type: text/html"));
+        assert!(received.data.contains("Your code is 123456"));
+    }
+
+    #[tokio::test]
+    async fn send_email_reuses_pooled_connections() {
+        let server = FakeSmtpServer::start(false).await;
+        let email_client = email_client(&server.config(SmtpTls::None, None), None);
+
+        for _ in 0..3 {
+            email_client.send_email(&email("user@example.com"), &message()).await.unwrap();
+            // Connections go back to the pool in the background
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        assert_eq!(server.emails().len(), 3);
+        assert!(server.emails().iter().all(|e| e.credentials.is_none()));
+        assert_eq!(server.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_recipient_is_rejected() {
+        let server = FakeSmtpServer::start(true).await;
+        let email_client = email_client(&server.config(SmtpTls::None, None), None);
+
+        let outcome = email_client.send_email(&email("user@example.com"), &message()).await;
+
+        assert!(outcome.is_err());
+        assert!(server.emails().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_refuses_relays_without_starttls() {
+        let server = FakeSmtpServer::start(false).await;
+        let email_client = email_client(&server.config(SmtpTls::StartTls, Some("mailer")), Some("hunter2"));
+
+        let outcome = email_client.send_email(&email("user@example.com"), &message()).await;
+
+        assert!(outcome.is_err());
+        assert!(server.emails().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_never_greets() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = SmtpConfig {
+            host: "127.0.0.1".to_owned(),
+            port: Some(listener.local_addr().unwrap().port()),
+            tls: SmtpTls::None,
+            username: None,
+            pool_size: 1,
+        };
+        let email_client = email_client(&config, None);
+
+        let outcome = email_client.send_email(&email("user@example.com"), &message()).await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[test]
+    fn config_defaults_to_starttls_on_the_submission_port() {
+        let config: SmtpConfig = serde_json::from_str(r#"{ "host": "smtp.example.com" }"#).unwrap();
+
+        assert_eq!(config.tls, SmtpTls::StartTls);
+        assert_eq!(config.tls.default_port(), 587);
+        assert_eq!(config.username, None);
+        assert_eq!(config.pool_size, default_pool_size());
+    }
+}
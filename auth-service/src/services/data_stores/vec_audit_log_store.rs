@@ -0,0 +1,88 @@
+use std::cmp::Reverse;
+
+use crate::domain::data_stores::{AuditEvent, AuditLogStore, AuditLogStoreError, AuditQuery};
+
+#[derive(Default, Debug)]
+pub struct VecAuditLogStore {
+    pub events: Vec<AuditEvent>,
+}
+
+impl VecAuditLogStore {
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for VecAuditLogStore {
+    async fn record(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        self.events.push(event);
+        Ok(())
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        let mut events: Vec<AuditEvent> = self
+            .events
+            .iter()
+            .filter(|e| query.matches(e))
+            .cloned()
+            .collect();
+
+        events.sort_by_key(|e| Reverse(e.occurred_at));
+        events.truncate(query.limit.max(0) as usize);
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::domain::data_stores::{AuditEventType, AuditOutcome};
+
+    fn event(actor: &str, minutes_ago: i64) -> AuditEvent {
+        let mut event = AuditEvent::new(AuditEventType::Login, AuditOutcome::Success);
+        event.actor = Some(actor.to_owned());
+        event.occurred_at = Utc::now() - Duration::minutes(minutes_ago);
+        event
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let mut store = VecAuditLogStore::new();
+        let event = event("foo@example.com", 0);
+
+        let res = store.record(event.clone()).await;
+
+        assert!(res.is_ok());
+        assert_eq!(store.events, vec![event]);
+    }
+
+    #[tokio::test]
+    async fn test_query_filters_by_actor_and_time_range() {
+        let mut store = VecAuditLogStore::new();
+        store.events = vec![
+            event("foo@example.com", 30),
+            event("foo@example.com", 10),
+            event("bar@example.com", 10),
+            event("Foo@example.com", 1),
+        ];
+
+        let query = AuditQuery {
+            actor: Some("foo@example.com".to_owned()),
+            from: Some(Utc::now() - Duration::minutes(20)),
+            to: None,
+            limit: 100,
+        };
+        let res = store.query(&query).await.unwrap();
+
+        // Newest first, only the matching actor in any case inside the time range
+        assert_eq!(res, vec![store.events[3].clone(), store.events[1].clone()]);
+
+        let query = AuditQuery { limit: 1, ..query };
+        let res = store.query(&query).await.unwrap();
+        assert_eq!(res, vec![store.events[3].clone()]);
+    }
+}
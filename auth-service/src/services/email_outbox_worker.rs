@@ -0,0 +1,399 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use tokio::task::JoinHandle;
+
+use crate::{
+    app_state::app_state::{EmailClientType, EmailOutboxStoreType, MetricsType},
+    domain::data_stores::{EmailOutboxStoreError, OutboxEmail},
+    utils::{retry::RetryPolicy, shutdown::ShutdownHandle},
+};
+
+// Delivers the emails handlers put into the outbox, retrying with back-off and dead-lettering
+// emails that still fail after `max_attempts`
+pub struct EmailOutboxWorker {
+    outbox: EmailOutboxStoreType,
+    email_client: EmailClientType,
+    retry_policy: RetryPolicy,
+    // The longest sending one email can take, see `EmailSettings::send_timeout`
+    send_timeout: Duration,
+    metrics: MetricsType,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(outbox: EmailOutboxStoreType, email_client: EmailClientType, retry_policy: RetryPolicy, send_timeout: Duration, metrics: MetricsType) -> Self {
+        Self { outbox, email_client, retry_policy, send_timeout, metrics }
+    }
+
+    // Polls the outbox until shutdown, a full batch is followed up right away. The batch being
+    // delivered when shutdown is triggered is finished first.
+    pub fn spawn(self, poll_interval: Duration, shutdown: ShutdownHandle) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            while !shutdown.is_shutdown() {
+                match self.run_once().await {
+                    Ok(claimed) if claimed >= BATCH_SIZE as usize => continue,
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("failed to process email outbox: {:?}", e),
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(poll_interval) => {}
+                    _ = shutdown.wait() => {}
+                }
+            }
+        })
+    }
+
+    // Attempts up to one batch of due emails, returns how many were claimed. Emails are claimed one
+    // at a time so a lease only has to outlast a single send, however many emails come before it.
+    #[tracing::instrument(name = "Processing email outbox", skip_all)]
+    pub async fn run_once(&self) -> Result<usize> {
+        let mut claimed = 0;
+
+        while claimed < BATCH_SIZE {
+            let lease_until = Utc::now() + self.send_timeout + LEASE_MARGIN;
+            let Some(email) = self.outbox.write().await.claim_due(1, lease_until).await?.pop() else {
+                break;
+            };
+            claimed += 1;
+
+            // A failure to record one delivery leaves that email to be retried once its lease runs
+            // out, it must not hold up the rest of the batch
+            if let Err(e) = self.deliver(&email).await {
+                tracing::error!("failed to record delivery of email {}: {:?}", email.id, e);
+            }
+        }
+
+        Ok(claimed as usize)
+    }
+
+    #[tracing::instrument(name = "Delivering outbox email", skip_all, fields(email_id = %email.id, attempt = email.attempts))]
+    async fn deliver(&self, email: &OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        if email.is_expired(Utc::now()) {
+            tracing::warn!("giving up on email {}, it expired before it could be delivered", email.id);
+            return self.outbox.write().await.mark_dead(email.id, EXPIRED_ERROR).await;
+        }
+
+        let result = self.email_client
+            .read()
+            .await
+            .send_email(&email.recipient, &email.message)
+            .await;
+
+        if result.is_err() {
+            self.metrics.email_send_failed();
+        }
+
+        let mut outbox = self.outbox.write().await;
+        let attempts = email.attempts.max(0) as u32;
+
+        match result {
+            Ok(()) => outbox.mark_sent(email.id).await,
+            Err(e) if attempts >= self.retry_policy.max_attempts => {
+                tracing::error!("giving up on email {} after {} attempts: {:?}", email.id, attempts, e);
+                outbox.mark_dead(email.id, &format!("{:#}", e)).await
+            }
+            Err(e) => {
+                let backoff = self.retry_policy.backoff(attempts);
+                let retry_at = Utc::now() + backoff;
+                if email.is_expired(retry_at) {
+                    tracing::error!("giving up on email {}, it expires before the next attempt: {:?}", email.id, e);
+                    return outbox.mark_dead(email.id, &format!("{:#}", e)).await;
+                }
+
+                tracing::warn!("email delivery attempt {} failed, retrying in {:?}: {:?}", attempts, backoff, e);
+                outbox.schedule_retry(email.id, &format!("{:#}", e), retry_at).await
+            }
+        }
+    }
+}
+
+const BATCH_SIZE: i64 = 50;
+const EXPIRED_ERROR: &str = "Expired before it could be delivered";
+// Added to the send timeout for the lease of a claimed email, covers recording the outcome
+const LEASE_MARGIN: Duration = Duration::from_secs(30);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use chrono::DateTime;
+    use color_eyre::eyre::eyre;
+    use secrecy::Secret;
+    use tokio::sync::RwLock;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        domain::{
+            data_stores::{DeliveryStatus, EmailOutboxStore, OutboxQuery},
+            email::Email,
+            email_client::{EmailClient, EmailMessage},
+        },
+        services::data_stores::HashmapEmailOutboxStore,
+        utils::{constants::test, metrics::Metrics},
+    };
+
+    // Fails the first `failures` sends, then succeeds
+    struct FlakyEmailClient {
+        failures: u32,
+        calls: Arc<AtomicU32>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for FlakyEmailClient {
+        async fn send_email(&self, _recipient: &Email, _message: &EmailMessage) -> Result<()> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if call <= self.failures {
+                return Err(eyre!("provider unavailable"));
+            }
+            Ok(())
+        }
+    }
+
+    // Records how many outbox emails are still unclaimed whenever it sends one
+    struct UnclaimedCountingEmailClient {
+        outbox: EmailOutboxStoreType,
+        unclaimed: Arc<std::sync::Mutex<Vec<usize>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for UnclaimedCountingEmailClient {
+        async fn send_email(&self, _recipient: &Email, _message: &EmailMessage) -> Result<()> {
+            let query = OutboxQuery { status: Some(DeliveryStatus::Pending), limit: 100, ..Default::default() };
+            let pending = self.outbox.read().await.query(&query).await?;
+            self.unclaimed.lock().unwrap().push(pending.iter().filter(|e| e.attempts == 0).count());
+            Ok(())
+        }
+    }
+
+    // Fails to record the delivery of one email
+    struct BrokenOutboxStore {
+        inner: HashmapEmailOutboxStore,
+        broken: Uuid,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailOutboxStore for BrokenOutboxStore {
+        async fn enqueue(&mut self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+            self.inner.enqueue(email).await
+        }
+
+        async fn claim_due(&mut self, limit: i64, lease_until: DateTime<Utc>) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+            self.inner.claim_due(limit, lease_until).await
+        }
+
+        async fn mark_sent(&mut self, id: Uuid) -> Result<(), EmailOutboxStoreError> {
+            if id == self.broken {
+                return Err(EmailOutboxStoreError::UnexpectedError(eyre!("connection reset")));
+            }
+            self.inner.mark_sent(id).await
+        }
+
+        async fn schedule_retry(&mut self, id: Uuid, error: &str, retry_at: DateTime<Utc>) -> Result<(), EmailOutboxStoreError> {
+            self.inner.schedule_retry(id, error, retry_at).await
+        }
+
+        async fn mark_dead(&mut self, id: Uuid, error: &str) -> Result<(), EmailOutboxStoreError> {
+            self.inner.mark_dead(id, error).await
+        }
+
+        async fn get_email(&self, id: Uuid) -> Result<OutboxEmail, EmailOutboxStoreError> {
+            self.inner.get_email(id).await
+        }
+
+        async fn query(&self, query: &OutboxQuery) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+            self.inner.query(query).await
+        }
+    }
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: test::email_outbox::MAX_ATTEMPTS,
+            initial_backoff: test::email_outbox::INITIAL_BACKOFF,
+            max_backoff: test::email_outbox::MAX_BACKOFF,
+        }
+    }
+
+    fn outbox_email() -> OutboxEmail {
+        OutboxEmail::new(
+            Email::parse(Secret::new("user@example.com".to_owned())).unwrap(),
+            EmailMessage {
+                subject: "Subject".to_owned(),
+                html_body: "<p>Body</p>".to_owned(),
+                text_body: "Body".to_owned(),
+            },
+        )
+    }
+
+    async fn setup(failures: u32) -> (EmailOutboxWorker, EmailOutboxStoreType, Arc<AtomicU32>, OutboxEmail) {
+        setup_with(failures, outbox_email()).await
+    }
+
+    async fn setup_with(failures: u32, email: OutboxEmail) -> (EmailOutboxWorker, EmailOutboxStoreType, Arc<AtomicU32>, OutboxEmail) {
+        let calls = Arc::new(AtomicU32::new(0));
+        let email_client: EmailClientType = Arc::new(RwLock::new(FlakyEmailClient { failures, calls: calls.clone() }));
+        let outbox: EmailOutboxStoreType = Arc::new(RwLock::new(HashmapEmailOutboxStore::default()));
+
+        outbox.write().await.enqueue(email.clone()).await.unwrap();
+
+        let metrics = Arc::new(Metrics::new().unwrap());
+
+        (EmailOutboxWorker::new(outbox.clone(), email_client, retry_policy(), test::email_client::TIMEOUT, metrics), outbox, calls, email)
+    }
+
+    // Runs the worker until the email is no longer pending
+    async fn run_until_settled(worker: &EmailOutboxWorker, outbox: &EmailOutboxStoreType, email: &OutboxEmail) -> OutboxEmail {
+        for _ in 0..100 {
+            worker.run_once().await.unwrap();
+            let current = outbox.read().await.get_email(email.id).await.unwrap();
+            if current.status != DeliveryStatus::Pending {
+                return current;
+            }
+            tokio::time::sleep(test::email_outbox::INITIAL_BACKOFF).await;
+        }
+        panic!("Email was never settled");
+    }
+
+    #[tokio::test]
+    async fn run_once_sends_due_emails() {
+        let (worker, outbox, calls, email) = setup(0).await;
+
+        assert_eq!(worker.run_once().await.unwrap(), 1);
+        assert_eq!(worker.run_once().await.unwrap(), 0);
+
+        let sent = outbox.read().await.get_email(email.id).await.unwrap();
+        assert_eq!(sent.status, DeliveryStatus::Sent);
+        assert_eq!(sent.attempts, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn run_once_backs_off_after_a_failure() {
+        let (worker, outbox, calls, email) = setup(1).await;
+
+        worker.run_once().await.unwrap();
+
+        let failed = outbox.read().await.get_email(email.id).await.unwrap();
+        assert_eq!(failed.status, DeliveryStatus::Pending);
+        assert_eq!(failed.last_error.as_deref(), Some("provider unavailable"));
+        assert!(failed.next_attempt_at > Utc::now());
+
+        // Not due again until the back-off has passed
+        assert_eq!(worker.run_once().await.unwrap(), 0);
+
+        let sent = run_until_settled(&worker, &outbox, &email).await;
+        assert_eq!(sent.status, DeliveryStatus::Sent);
+        assert_eq!(sent.attempts, 2);
+        assert_eq!(sent.last_error, None);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn run_once_dead_letters_after_max_attempts() {
+        let (worker, outbox, calls, email) = setup(u32::MAX).await;
+
+        let dead = run_until_settled(&worker, &outbox, &email).await;
+
+        assert_eq!(dead.status, DeliveryStatus::Dead);
+        assert_eq!(dead.attempts as u32, test::email_outbox::MAX_ATTEMPTS);
+        assert_eq!(dead.last_error.as_deref(), Some("provider unavailable"));
+        assert_eq!(calls.load(Ordering::SeqCst), test::email_outbox::MAX_ATTEMPTS);
+    }
+
+    #[tokio::test]
+    async fn run_once_dead_letters_expired_emails_without_sending() {
+        let email = OutboxEmail { expires_at: Some(Utc::now()), ..outbox_email() };
+        let (worker, outbox, calls, email) = setup_with(0, email).await;
+
+        assert_eq!(worker.run_once().await.unwrap(), 1);
+
+        let dead = outbox.read().await.get_email(email.id).await.unwrap();
+        assert_eq!(dead.status, DeliveryStatus::Dead);
+        assert_eq!(dead.last_error.as_deref(), Some(EXPIRED_ERROR));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn run_once_does_not_retry_past_expiry() {
+        // Expires before the first back-off is over
+        let email = OutboxEmail { expires_at: Some(Utc::now() + chrono::Duration::seconds(1)), ..outbox_email() };
+        let (mut worker, outbox, calls, email) = setup_with(u32::MAX, email).await;
+        worker.retry_policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(60),
+            max_backoff: Duration::from_secs(60),
+            ..retry_policy()
+        };
+
+        worker.run_once().await.unwrap();
+
+        let dead = outbox.read().await.get_email(email.id).await.unwrap();
+        assert_eq!(dead.status, DeliveryStatus::Dead);
+        assert_eq!(dead.last_error.as_deref(), Some("provider unavailable"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn run_once_finishes_batch_after_a_store_error() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let email_client: EmailClientType = Arc::new(RwLock::new(FlakyEmailClient { failures: 0, calls: calls.clone() }));
+        let (broken, other) = (outbox_email(), outbox_email());
+
+        let mut store = BrokenOutboxStore { inner: HashmapEmailOutboxStore::default(), broken: broken.id };
+        store.enqueue(broken.clone()).await.unwrap();
+        store.enqueue(other.clone()).await.unwrap();
+        let outbox: EmailOutboxStoreType = Arc::new(RwLock::new(store));
+
+        let worker = EmailOutboxWorker::new(outbox.clone(), email_client, retry_policy(), test::email_client::TIMEOUT, Arc::new(Metrics::new().unwrap()));
+
+        assert_eq!(worker.run_once().await.unwrap(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(outbox.read().await.get_email(other.id).await.unwrap().status, DeliveryStatus::Sent);
+    }
+
+    #[tokio::test]
+    async fn run_once_leases_one_email_at_a_time() {
+        let outbox: EmailOutboxStoreType = Arc::new(RwLock::new(HashmapEmailOutboxStore::default()));
+        let emails = [outbox_email(), outbox_email(), outbox_email()];
+        for email in &emails {
+            outbox.write().await.enqueue(email.clone()).await.unwrap();
+        }
+
+        let unclaimed = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let email_client: EmailClientType = Arc::new(RwLock::new(UnclaimedCountingEmailClient { outbox: outbox.clone(), unclaimed: unclaimed.clone() }));
+        let worker = EmailOutboxWorker::new(outbox.clone(), email_client, retry_policy(), test::email_client::TIMEOUT, Arc::new(Metrics::new().unwrap()));
+
+        assert_eq!(worker.run_once().await.unwrap(), 3);
+
+        // The emails behind the one being sent stay free for other workers
+        assert_eq!(*unclaimed.lock().unwrap(), vec![2, 1, 0]);
+
+        // Each lease only covers a single send
+        let latest_lease_until = Utc::now() + test::email_client::TIMEOUT + LEASE_MARGIN;
+        for email in &emails {
+            let email = outbox.read().await.get_email(email.id).await.unwrap();
+            assert_eq!(email.status, DeliveryStatus::Sent);
+            assert!(email.next_attempt_at <= latest_lease_until);
+        }
+    }
+
+    #[tokio::test]
+    async fn spawned_worker_stops_on_shutdown() {
+        let (worker, outbox, _, email) = setup(0).await;
+        let shutdown = ShutdownHandle::default();
+
+        let handle = worker.spawn(Duration::from_secs(60), shutdown.clone());
+        // The first batch goes out right away, then the worker waits for the next poll
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        shutdown.shutdown();
+
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("Worker did not stop on shutdown")
+            .unwrap();
+        assert_eq!(outbox.read().await.get_email(email.id).await.unwrap().status, DeliveryStatus::Sent);
+    }
+}
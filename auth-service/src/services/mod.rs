@@ -1 +1,3 @@
-pub mod data_stores;
\ No newline at end of file
+pub mod data_stores;
+pub mod email_outbox_worker;
+pub mod webhook_dispatcher;
\ No newline at end of file
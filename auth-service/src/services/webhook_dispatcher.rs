@@ -0,0 +1,275 @@
+
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, Client, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use sha2::Sha256;
+use tokio_util::task::TaskTracker;
+use tracing::Instrument;
+
+use crate::{
+    domain::security_event::{SecurityEvent, SecurityEventType},
+    utils::retry::RetryPolicy,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookSubscription {
+    pub url: String,
+    pub secret: Secret<String>,
+    // An empty list subscribes to every event type
+    #[serde(default)]
+    pub events: Vec<SecurityEventType>,
+}
+
+impl WebhookSubscription {
+    pub fn accepts(&self, event_type: SecurityEventType) -> bool {
+        self.events.is_empty() || self.events.contains(&event_type)
+    }
+}
+
+pub struct WebhookDispatcher {
+    http_client: Client,
+    subscriptions: Vec<WebhookSubscription>,
+    retry_policy: RetryPolicy,
+    deliveries: TaskTracker,
+}
+
+impl WebhookDispatcher {
+    pub fn new(subscriptions: Vec<WebhookSubscription>, retry_policy: RetryPolicy, http_client: Client) -> Self {
+        Self { http_client, subscriptions, retry_policy, deliveries: TaskTracker::new() }
+    }
+
+    // Waits for the deliveries still running, including their retries, for use on shutdown
+    pub async fn drain(&self) {
+        self.deliveries.close();
+        self.deliveries.wait().await;
+    }
+
+    // Deliveries run in the background so a slow or failing receiver never holds up the request
+    pub fn dispatch(&self, event: SecurityEvent) {
+        for subscription in self.subscriptions.iter().filter(|s| s.accepts(event.event_type)) {
+            let http_client = self.http_client.clone();
+            let subscription = subscription.clone();
+            let retry_policy = self.retry_policy;
+            let event = event.clone();
+            let span = tracing::info_span!("Delivering webhook", event_type = event.event_type.as_ref());
+
+            self.deliveries.spawn(
+                async move {
+                    if let Err(e) = deliver(&http_client, &subscription, &event, retry_policy).await {
+                        tracing::error!("failed to deliver webhook to {}: {:?}", subscription.url, e);
+                    }
+                }
+                .instrument(span),
+            );
+        }
+    }
+}
+
+// Signature over "<timestamp>.<body>" so receivers can reject replayed deliveries
+pub fn sign_payload(secret: &Secret<String>, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    format!("{}{}", SIGNATURE_PREFIX, hex::encode(mac.finalize().into_bytes()))
+}
+
+async fn deliver(
+    http_client: &Client,
+    subscription: &WebhookSubscription,
+    event: &SecurityEvent,
+    retry_policy: RetryPolicy,
+) -> Result<()> {
+    let body = serde_json::to_vec(event)?;
+
+    let mut attempt = 1;
+    loop {
+        let error = match send(http_client, subscription, event, &body).await {
+            Ok(status) if status.is_success() => return Ok(()),
+            Ok(status) if !is_retryable(status) => {
+                return Err(eyre!("webhook receiver rejected the event with {}", status))
+            }
+            Ok(status) => eyre!("webhook receiver responded with {}", status),
+            Err(e) => eyre!(e),
+        };
+
+        if attempt >= retry_policy.max_attempts {
+            return Err(error.wrap_err(format!("giving up after {} attempts", attempt)));
+        }
+
+        let backoff = retry_policy.backoff(attempt);
+        tracing::warn!("webhook delivery attempt {} failed, retrying in {:?}: {:?}", attempt, backoff, error);
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+async fn send(
+    http_client: &Client,
+    subscription: &WebhookSubscription,
+    event: &SecurityEvent,
+    body: &[u8],
+) -> Result<StatusCode, reqwest::Error> {
+    let timestamp = Utc::now().timestamp();
+    let signature = sign_payload(&subscription.secret, timestamp, body);
+
+    let response = http_client
+        .post(&subscription.url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(WEBHOOK_ID_HEADER, event.id.to_string())
+        .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+        .header(WEBHOOK_SIGNATURE_HEADER, signature)
+        .body(body.to_vec())
+        .send()
+        .await?;
+
+    Ok(response.status())
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+pub const WEBHOOK_ID_HEADER: &str = "X-Webhook-Id";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+    use wiremock::matchers::{any, header_exists, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::utils::constants::test;
+
+    fn subscription(url: String, events: Vec<SecurityEventType>) -> WebhookSubscription {
+        WebhookSubscription {
+            url,
+            secret: Secret::new(test::webhooks::SECRET.to_owned()),
+            events,
+        }
+    }
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: test::webhooks::MAX_ATTEMPTS,
+            initial_backoff: test::webhooks::INITIAL_BACKOFF,
+            max_backoff: test::webhooks::MAX_BACKOFF,
+        }
+    }
+
+    fn http_client() -> Client {
+        Client::builder()
+            .timeout(test::webhooks::TIMEOUT)
+            .build()
+            .unwrap()
+    }
+
+    fn event() -> SecurityEvent {
+        SecurityEvent::new(SecurityEventType::LoggedIn, "foo@example.com".to_owned())
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        let signature = sign_payload(&Secret::new("secret".to_owned()), 1700000000, br#"{"a":1}"#);
+
+        assert_eq!(
+            signature,
+            "sha256=49f24e537407743fa4a0242bb63b94b9a47ee99cbbe071ccd8a22550ae411686"
+        );
+    }
+
+    #[test]
+    fn test_subscription_accepts() {
+        let all = subscription("http://localhost".to_owned(), vec![]);
+        let logins = subscription("http://localhost".to_owned(), vec![SecurityEventType::LoggedIn]);
+
+        assert!(all.accepts(SecurityEventType::AccountDeleted));
+        assert!(logins.accepts(SecurityEventType::LoggedIn));
+        assert!(!logins.accepts(SecurityEventType::SignedUp));
+    }
+
+    #[tokio::test]
+    async fn deliver_retries_until_the_receiver_succeeds() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(header_exists(WEBHOOK_SIGNATURE_HEADER))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = deliver(&http_client(), &subscription(mock_server.uri(), vec![]), &event(), retry_policy()).await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn deliver_gives_up_after_max_attempts() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(503))
+            .expect(u64::from(test::webhooks::MAX_ATTEMPTS))
+            .mount(&mock_server)
+            .await;
+
+        let outcome = deliver(&http_client(), &subscription(mock_server.uri(), vec![]), &event(), retry_policy()).await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn deliver_does_not_retry_client_errors() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = deliver(&http_client(), &subscription(mock_server.uri(), vec![]), &event(), retry_policy()).await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn drain_waits_for_retrying_deliveries() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let dispatcher = WebhookDispatcher::new(vec![subscription(mock_server.uri(), vec![])], retry_policy(), http_client());
+        dispatcher.dispatch(event());
+        dispatcher.drain().await;
+
+        mock_server.verify().await;
+    }
+}
@@ -0,0 +1,23 @@
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    app_state::app_state::AuditLogStoreType,
+    domain::{data_stores::AuditEvent, email::Email},
+};
+
+// A failure to write the audit trail is logged but never fails the request itself
+#[tracing::instrument(name = "Recording audit event", skip_all)]
+pub async fn record_audit_event(audit_log_store: &AuditLogStoreType, event: AuditEvent) {
+    if let Err(e) = audit_log_store.write().await.record(event).await {
+        tracing::error!("failed to record audit event: {:?}", e);
+    }
+}
+
+// The canonical form of an email from a request body, as stored on accounts, so events and webhooks
+// name the same actor however the address was typed. Input that doesn't parse is kept as given.
+pub fn request_actor(email: &Secret<String>) -> String {
+    match Email::parse(email.clone()) {
+        Ok(email) => email.as_ref().expose_secret().to_owned(),
+        Err(_) => email.expose_secret().to_owned(),
+    }
+}
@@ -1,18 +1,28 @@
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::{cookie::{Cookie, SameSite}, CookieJar};
 use chrono::Utc;
-use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use color_eyre::eyre::{eyre, Context, Result};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::{app_state::app_state::BannedTokenStoreType, domain::email::Email};
+use crate::{
+    app_state::app_state::{BannedTokenStoreType, UserStoreType},
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        email::Email,
+        error::AuthAPIError,
+        organisation::{parse_slug, Membership, OrgRole},
+        user::{AccountStatus, User},
+    },
+};
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::{constants::JWT_COOKIE_NAME, settings::AuthSettings};
 
 // Create cookie with a new JWT auth token
 #[tracing::instrument(name = "generate_auth_cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(email: &Email, org: Option<OrgClaim>, settings: &AuthSettings) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(email, org, settings)?;
     Ok(create_auth_cookie(token))
 }
 
@@ -28,19 +38,16 @@ fn create_auth_cookie(token: Secret<String>) -> Cookie<'static> {
     cookie
 }
 
-// This value determines how long the JWT auth token is valid for
-pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
-
 // Create JWT auth token
 #[tracing::instrument(name = "Generate auth token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<Secret<String>> {
-    let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
-        .wrap_err("failed to create 10 minute time delta")?;
+fn generate_auth_token(email: &Email, org: Option<OrgClaim>, settings: &AuthSettings) -> Result<Secret<String>> {
+    let delta = chrono::Duration::from_std(settings.token_ttl)
+        .wrap_err("failed to create token ttl time delta")?;
 
     // Create JWT expiration time
     let exp = Utc::now()
         .checked_add_signed(delta)
-        .ok_or(eyre!("failed to add 10 minutes to current time"))?
+        .ok_or(eyre!("failed to add token ttl to current time"))?
         .timestamp();
 
     // Cast exp to a usize, which is what Claims expects
@@ -50,37 +57,150 @@ fn generate_auth_token(email: &Email) -> Result<Secret<String>> {
 
     let sub = email.as_ref().expose_secret().clone();
 
-    let claims = Claims { sub, exp };
+    let claims = Claims { sub, exp, org };
 
-    create_token(&claims)
+    create_token(&claims, &settings.jwt_secret)
 }
 
 #[tracing::instrument(name = "validate_token", skip_all)]
-pub async fn validate_token(token: &Secret<String>, token_store: BannedTokenStoreType) -> Result<Claims> {
-    match token_store.read().await.contains_token(token).await {
-        Ok(value) => {
-            if value {
-                return Err(eyre!("token is banned"));
-            }
-        }
-        Err(e) => return Err(e.into()),
+pub async fn validate_token(
+    token: &Secret<String>,
+    settings: &AuthSettings,
+    token_store: BannedTokenStoreType,
+    user_store: UserStoreType,
+) -> Result<Claims, AuthAPIError> {
+    validate_token_for_user(token, settings, token_store, user_store)
+        .await
+        .map(|(claims, _)| claims)
+}
+
+// Resolve the user behind the JWT auth cookie
+#[tracing::instrument(name = "get_authenticated_user", skip_all)]
+pub async fn get_authenticated_user(
+    jar: &CookieJar,
+    settings: &AuthSettings,
+    token_store: BannedTokenStoreType,
+    user_store: UserStoreType,
+) -> Result<User, AuthAPIError> {
+    let cookie = jar.get(JWT_COOKIE_NAME)
+        .ok_or(AuthAPIError::MissingToken)?;
+
+    let token = Secret::new(cookie.value().to_owned());
+    validate_token_for_user(&token, settings, token_store, user_store)
+        .await
+        .map(|(_, user)| user)
+}
+
+async fn validate_token_for_user(
+    token: &Secret<String>,
+    settings: &AuthSettings,
+    token_store: BannedTokenStoreType,
+    user_store: UserStoreType,
+) -> Result<(Claims, User), AuthAPIError> {
+    let is_banned = token_store
+        .read()
+        .await
+        .contains_token(token)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if is_banned {
+        return Err(AuthAPIError::InvalidToken);
     }
 
-    decode::<Claims>(
+    let claims = decode::<Claims>(
         token.expose_secret(),
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
         &Validation::default(),
     )
     .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    // Look the subject up on every validation so that a suspension takes effect
+    // immediately for tokens that were issued before it
+    let email = Email::parse(Secret::new(claims.sub.clone()))
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let user = match user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    check_account_status(user.status)?;
+
+    let claims = check_org_claim(claims, &email, &user_store).await?;
+
+    Ok((claims, user))
+}
+
+// The organisation role in the token is only a snapshot from login, so it is replaced with the
+// current membership. Tokens scoped to an organisation the user has left are no longer valid.
+async fn check_org_claim(mut claims: Claims, email: &Email, user_store: &UserStoreType) -> Result<Claims, AuthAPIError> {
+    let Some(org) = &claims.org else {
+        return Ok(claims);
+    };
+
+    let membership = user_store
+        .read()
+        .await
+        .get_memberships(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .into_iter()
+        .find(|m| m.organisation.id == org.id)
+        .ok_or(AuthAPIError::InvalidToken)?;
+
+    claims.org = Some(OrgClaim::from(membership));
+    Ok(claims)
+}
+
+// Only active accounts may log in or keep using their tokens
+pub fn check_account_status(status: AccountStatus) -> Result<(), AuthAPIError> {
+    match status {
+        AccountStatus::Active => Ok(()),
+        AccountStatus::Suspended => Err(AuthAPIError::AccountSuspended),
+        AccountStatus::Pending => Err(AuthAPIError::AccountPending),
+    }
+}
+
+// Picks the organisation a login is scoped to: the one the user asked for, or the only one they
+// belong to. Users without any membership log in without an organisation.
+#[tracing::instrument(name = "resolve_organisation", skip_all)]
+pub async fn resolve_organisation(
+    user_store: &(dyn UserStore + Send + Sync),
+    email: &Email,
+    requested: Option<&str>,
+) -> Result<Option<OrgClaim>, AuthAPIError> {
+    let mut memberships = user_store
+        .get_memberships(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let membership = match requested {
+        Some(slug) => {
+            let slug = parse_slug(slug).map_err(|_| AuthAPIError::NotOrganisationMember)?;
+            memberships
+                .into_iter()
+                .find(|m| m.organisation.slug == slug)
+                .ok_or(AuthAPIError::NotOrganisationMember)?
+        }
+        None => match memberships.len() {
+            0 => return Ok(None),
+            1 => memberships.remove(0),
+            _ => return Err(AuthAPIError::OrganisationRequired),
+        },
+    };
+
+    Ok(Some(OrgClaim::from(membership)))
 }
 
 // Create JWT auth token by encoding claims using the JWT secret
-fn create_token(claims: &Claims) -> Result<Secret<String>> {
+fn create_token(claims: &Claims, jwt_secret: &Secret<String>) -> Result<Secret<String>> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &EncodingKey::from_secret(jwt_secret.expose_secret().as_bytes()),
     )
     .map(Secret::new)
     .wrap_err("failed to create token")
@@ -90,22 +210,57 @@ fn create_token(claims: &Claims) -> Result<Secret<String>> {
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    // The organisation the login is scoped to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<OrgClaim>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrgClaim {
+    pub id: Uuid,
+    pub slug: String,
+    pub role: OrgRole,
+}
+
+impl From<Membership> for OrgClaim {
+    fn from(membership: Membership) -> Self {
+        Self {
+            id: membership.organisation.id,
+            slug: membership.organisation.slug,
+            role: membership.role,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{sync::Arc, time::Duration};
     use tokio::sync::RwLock;
     use secrecy::Secret;
 
-    use crate::{domain::data_stores::BannedTokenStore, services::data_stores::HashsetBannedTokenStore};
+    use crate::{
+        domain::{data_stores::BannedTokenStore, organisation::Organisation, password::Password, user::User},
+        services::data_stores::{HashmapUserStore, HashsetBannedTokenStore},
+    };
 
     use super::*;
 
+    fn auth_settings() -> AuthSettings {
+        AuthSettings { jwt_secret: Secret::new("secret".to_owned()), token_ttl: Duration::from_secs(10 * 60) }
+    }
+
+    async fn user_store_with(email: &Email, status: AccountStatus) -> UserStoreType {
+        let password = Password::parse_credential(Secret::new("password123".to_string())).unwrap();
+        let mut user_store = HashmapUserStore::new();
+        user_store.add_user(User::new(email.clone(), password, false)).await.unwrap();
+        user_store.update_status(email, status).await.unwrap();
+        Arc::new(RwLock::new(user_store))
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&email, None, &auth_settings()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -128,17 +283,18 @@ mod tests {
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, None, &auth_settings()).unwrap();
         assert_eq!(result.expose_secret().split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, None, &auth_settings()).unwrap();
         let token_store = HashsetBannedTokenStore::new();
         let token_store = Arc::new(RwLock::new(token_store));
-        let result = validate_token(&token, token_store).await.unwrap();
+        let user_store = user_store_with(&email, AccountStatus::Active).await;
+        let result = validate_token(&token, &auth_settings(), token_store, user_store).await.unwrap();
         assert_eq!(result.sub, "test@example.com");
 
         let exp = Utc::now()
@@ -154,18 +310,92 @@ mod tests {
         let token = Secret::new("invalid_token".to_owned());
         let token_store = HashsetBannedTokenStore::new();
         let token_store = Arc::new(RwLock::new(token_store));
-        let result = validate_token(&token, token_store).await;
+        let user_store = Arc::new(RwLock::new(HashmapUserStore::new()));
+        let result = validate_token(&token, &auth_settings(), token_store, user_store).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
         let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, None, &auth_settings()).unwrap();
         let mut hs = HashsetBannedTokenStore::new();
         hs.add_token(token.clone()).await.unwrap();
         let banned_token_store = Arc::new(RwLock::new(hs));
-        let result = validate_token(&token, banned_token_store).await;
+        let user_store = user_store_with(&email, AccountStatus::Active).await;
+        let result = validate_token(&token, &auth_settings(), banned_token_store, user_store).await;
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_validate_token_with_suspended_account() {
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let token = generate_auth_token(&email, None, &auth_settings()).unwrap();
+        let token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::new()));
+        let user_store = user_store_with(&email, AccountStatus::Suspended).await;
+        let result = validate_token(&token, &auth_settings(), token_store, user_store).await;
+        assert!(matches!(result, Err(AuthAPIError::AccountSuspended)));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_org_claim() {
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::new()));
+        let user_store = user_store_with(&email, AccountStatus::Active).await;
+
+        let acme = Organisation::new("acme", "Acme".to_owned()).unwrap();
+        user_store.write().await.add_organisation(acme.clone()).await.unwrap();
+        user_store.write().await.set_membership(&email, acme.id, OrgRole::Admin).await.unwrap();
+
+        let org = OrgClaim { id: acme.id, slug: "acme".to_owned(), role: OrgRole::Admin };
+        let token = generate_auth_token(&email, Some(org.clone()), &auth_settings()).unwrap();
+        let result = validate_token(&token, &auth_settings(), token_store.clone(), user_store.clone()).await.unwrap();
+        assert_eq!(result.org, Some(org));
+
+        // A demotion takes effect for tokens issued before it
+        user_store.write().await.set_membership(&email, acme.id, OrgRole::Member).await.unwrap();
+        let result = validate_token(&token, &auth_settings(), token_store.clone(), user_store.clone()).await.unwrap();
+        assert_eq!(result.org.unwrap().role, OrgRole::Member);
+
+        let other = OrgClaim { id: Uuid::new_v4(), slug: "globex".to_owned(), role: OrgRole::Admin };
+        let token = generate_auth_token(&email, Some(other), &auth_settings()).unwrap();
+        let result = validate_token(&token, &auth_settings(), token_store, user_store).await;
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_organisation() {
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let password = Password::parse_credential(Secret::new("password123".to_string())).unwrap();
+        let mut user_store = HashmapUserStore::new();
+        user_store.add_user(User::new(email.clone(), password, false)).await.unwrap();
+
+        // No memberships, no organisation
+        assert_eq!(resolve_organisation(&user_store, &email, None).await.unwrap(), None);
+
+        let acme = Organisation::new("acme", "Acme".to_owned()).unwrap();
+        user_store.add_organisation(acme.clone()).await.unwrap();
+        user_store.set_membership(&email, acme.id, OrgRole::Member).await.unwrap();
+
+        // A single membership is picked automatically
+        let claim = resolve_organisation(&user_store, &email, None).await.unwrap().unwrap();
+        assert_eq!(claim, OrgClaim { id: acme.id, slug: "acme".to_owned(), role: OrgRole::Member });
+
+        let globex = Organisation::new("globex", "Globex".to_owned()).unwrap();
+        user_store.add_organisation(globex.clone()).await.unwrap();
+        user_store.set_membership(&email, globex.id, OrgRole::Admin).await.unwrap();
+
+        assert!(matches!(
+            resolve_organisation(&user_store, &email, None).await,
+            Err(AuthAPIError::OrganisationRequired)
+        ));
+
+        let claim = resolve_organisation(&user_store, &email, Some("Globex")).await.unwrap().unwrap();
+        assert_eq!(claim.role, OrgRole::Admin);
+
+        assert!(matches!(
+            resolve_organisation(&user_store, &email, Some("initech")).await,
+            Err(AuthAPIError::NotOrganisationMember)
+        ));
+    }
+}
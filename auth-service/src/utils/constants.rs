@@ -1,74 +1,85 @@
-use std::{env as std_env, sync::LazyLock};
-
-use dotenvy::dotenv;
-use secrecy::Secret;
-
-pub static JWT_SECRET: LazyLock<Secret<String>> = LazyLock::new(|| {
-    dotenv().ok();
-    let secret = std_env::var(env::JWT_SECRET_ENV_VAR)
-        .expect("JWT_SECRET must be set.");
-    if secret.is_empty() {
-        panic!("JWT_SECRET must not be empty.");
-    }
-    Secret::new(secret)
-});
-
-pub static DATABASE_URL: LazyLock<Secret<String>> = LazyLock::new(|| {
-    dotenv().ok();
-    let secret = std_env::var(env::DATABASE_URL_ENV_VAR)
-        .expect("DATABASE_URL_ENV_VAR must be set.");
-    if secret.is_empty() {
-        panic!("DATABASE_URL_ENV_VAR must not be empty.");
-    }
-    Secret::new(secret)
-});
-
-pub static REDIS_HOST_NAME: LazyLock<String> = LazyLock::new(|| {
-    dotenv().ok();
-    std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
-});
-
-pub static POSTMARK_AUTH_TOKEN: LazyLock<Secret<String>> = LazyLock::new(|| {
-    dotenv().ok();
-    let secret = std_env::var(env::POSTMARK_AUTH_TOKEN_ENV_VAR)
-        .expect("POSTMARK_AUTH_TOKEN must be set.");
-    if secret.is_empty() {
-        panic!("POSTMARK_AUTH_TOKEN must not be empty.");
-    }
-    Secret::new(secret)
-});
-
+// Environment variables override the settings from the config file, see `Settings::with_env`
 pub mod env {
+    pub const AUTH_SERVICE_CONFIG_ENV_VAR: &str = "AUTH_SERVICE_CONFIG";
+    pub const APP_ADDRESS_ENV_VAR: &str = "APP_ADDRESS";
+    pub const CORS_ALLOWED_ORIGINS_ENV_VAR: &str = "CORS_ALLOWED_ORIGINS";
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const EMAIL_PROVIDER_ENV_VAR: &str = "EMAIL_PROVIDER";
+    pub const EMAIL_SENDER_ENV_VAR: &str = "EMAIL_SENDER";
+    pub const SMTP_CONFIG_ENV_VAR: &str = "SMTP_CONFIG";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const DEV_MAILBOX_DIR_ENV_VAR: &str = "DEV_MAILBOX_DIR";
+    pub const WEBHOOK_SUBSCRIPTIONS_ENV_VAR: &str = "WEBHOOK_SUBSCRIPTIONS";
+    pub const BREACHED_PASSWORDS_PATH_ENV_VAR: &str = "BREACHED_PASSWORDS_PATH";
+    pub const PASSWORD_POLICY_ENV_VAR: &str = "PASSWORD_POLICY";
+    pub const PASSWORD_HASH_PARAMS_ENV_VAR: &str = "PASSWORD_HASH_PARAMS";
+    pub const EMAIL_DOMAIN_POLICY_ENV_VAR: &str = "EMAIL_DOMAIN_POLICY";
+    pub const DISPOSABLE_EMAIL_DOMAINS_PATH_ENV_VAR: &str = "DISPOSABLE_EMAIL_DOMAINS_PATH";
+    pub const INVITE_ONLY_SIGNUP_ENV_VAR: &str = "INVITE_ONLY_SIGNUP";
+    pub const EMAIL_BRANDING_ENV_VAR: &str = "EMAIL_BRANDING";
+    // The standard OpenTelemetry variables, so collectors can be configured the usual way
+    pub const OTLP_ENDPOINT_ENV_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+    pub const OTEL_SERVICE_NAME_ENV_VAR: &str = "OTEL_SERVICE_NAME";
+    pub const LOG_SINKS_ENV_VAR: &str = "LOG_SINKS";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
 pub const PG_TABLE_NAME: &str = "users";
-pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1"; 
+pub const PG_AUDIT_LOG_TABLE_NAME: &str = "audit_log";
+pub const PG_INVITATIONS_TABLE_NAME: &str = "invitations";
+pub const PG_ORGANISATIONS_TABLE_NAME: &str = "organisations";
+pub const PG_MEMBERSHIPS_TABLE_NAME: &str = "memberships";
+pub const PG_EMAIL_OUTBOX_TABLE_NAME: &str = "email_outbox";
 pub const LOG_NAME: &str = "auth.log";
 
-pub mod prod {
-    pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+pub mod test {
+    pub const APP_ADDRESS: &str = "127.0.0.1:0";
 
     pub mod email_client {
         use std::time::Duration;
 
-        pub const BASE_URL: &str = "https://api.postmarkapp.com/email";
-        pub const SENDER: &str = "bogdan@codeiron.io";
-        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+        pub const SENDER: &str = "test@email.com";
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
-}
 
-pub mod test {
-    pub const APP_ADDRESS: &str = "127.0.0.1:0";
-
-    pub mod email_client {
+    pub mod webhooks {
         use std::time::Duration;
 
-        pub const SENDER: &str = "test@email.com";
+        pub const SECRET: &str = "webhook-secret";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
+        pub const MAX_ATTEMPTS: u32 = 3;
+        pub const INITIAL_BACKOFF: Duration = std::time::Duration::from_millis(10);
+        pub const MAX_BACKOFF: Duration = std::time::Duration::from_millis(50);
+    }
+
+    pub mod email_outbox {
+        use std::time::Duration;
+
+        pub const MAX_ATTEMPTS: u32 = 3;
+        pub const INITIAL_BACKOFF: Duration = std::time::Duration::from_millis(10);
+        pub const MAX_BACKOFF: Duration = std::time::Duration::from_millis(50);
+    }
+
+    pub mod email_failover {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(100);
+        pub const FAILURE_THRESHOLD: u32 = 2;
+        pub const OPEN_DURATION: Duration = std::time::Duration::from_millis(50);
+    }
+
+    pub mod email_domains {
+        pub const DENIED: &str = "blocked.example.com";
+        pub const DISPOSABLE: &str = "mailinator.com";
+    }
+
+    pub mod cors {
+        pub const ALLOWED_ORIGIN: &str = "http://localhost:8000";
+        pub const ALLOWED_SUBDOMAINS: &str = "https://*.example.com";
+        pub const MAX_AGE_SECONDS: u64 = 600;
     }
 }
\ No newline at end of file
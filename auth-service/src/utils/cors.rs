@@ -0,0 +1,127 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use reqwest::Url;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use super::{constants::REQUEST_ID_HEADER, settings::CorsSettings};
+
+// An origin browsers may call the API from. `https://*.example.com` allows any subdomain of
+// example.com, but not example.com itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginPattern {
+    scheme: String,
+    // With the wildcard the host is the domain the subdomains must fall under
+    wildcard: bool,
+    host: String,
+}
+
+impl OriginPattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let (scheme, rest) = pattern
+            .split_once("://")
+            .ok_or_else(|| format!("missing scheme: {}", pattern))?;
+
+        let (wildcard, host) = match rest.strip_prefix("*.") {
+            Some(host) => (true, host),
+            None => (false, rest),
+        };
+        if host.contains('*') {
+            return Err(format!("only a leading *. wildcard is supported: {}", pattern));
+        }
+
+        // Browsers send origins serialized, only a pattern in the same form can ever match
+        let origin = format!("{}://{}", scheme, host);
+        let url = Url::parse(&origin).map_err(|_| format!("not an origin: {}", pattern))?;
+        if url.origin().ascii_serialization() != origin {
+            return Err(format!("not an origin: {}", pattern));
+        }
+
+        Ok(Self { scheme: scheme.to_owned(), wildcard, host: host.to_owned() })
+    }
+
+    pub fn matches(&self, origin: &str) -> bool {
+        let Some(rest) = origin
+            .strip_prefix(self.scheme.as_str())
+            .and_then(|rest| rest.strip_prefix("://"))
+        else {
+            return false;
+        };
+
+        if !self.wildcard {
+            return rest == self.host;
+        }
+
+        rest.strip_suffix(self.host.as_str())
+            .and_then(|subdomain| subdomain.strip_suffix('.'))
+            .is_some_and(|subdomain| subdomain.split('.').all(is_domain_label))
+    }
+}
+
+fn is_domain_label(label: &str) -> bool {
+    !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+// Builds the CORS layer from validated settings, cookies are always allowed
+pub fn cors_layer(settings: &CorsSettings) -> Result<CorsLayer, String> {
+    let origins = settings.allowed_origins
+        .iter()
+        .map(|origin| OriginPattern::parse(origin))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let methods = settings.allowed_methods
+        .iter()
+        .map(|method| Method::from_bytes(method.as_bytes()).map_err(|_| format!("invalid method: {}", method)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let headers = settings.allowed_headers
+        .iter()
+        .map(|header| HeaderName::from_bytes(header.as_bytes()).map_err(|_| format!("invalid header: {}", header)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let allow_origin = AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+        origin.to_str().is_ok_and(|origin| origins.iter().any(|pattern| pattern.matches(origin)))
+    });
+
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .max_age(settings.max_age)
+        // Lets browser clients read the request id to quote in support tickets
+        .expose_headers([HeaderName::from_static(REQUEST_ID_HEADER)])
+        // Allow cookies to be included in requests
+        .allow_credentials(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_origin() {
+        let pattern = OriginPattern::parse("http://localhost:8000").unwrap();
+        assert!(pattern.matches("http://localhost:8000"));
+        assert!(!pattern.matches("http://localhost:8001"));
+        assert!(!pattern.matches("https://localhost:8000"));
+        assert!(!pattern.matches("http://localhost:8000.evil.com"));
+    }
+
+    #[test]
+    fn test_wildcard_origin() {
+        let pattern = OriginPattern::parse("https://*.example.com").unwrap();
+        assert!(pattern.matches("https://app.example.com"));
+        assert!(pattern.matches("https://eu.app.example.com"));
+        assert!(!pattern.matches("https://example.com"));
+        assert!(!pattern.matches("https://.example.com"));
+        assert!(!pattern.matches("https://evilexample.com"));
+        assert!(!pattern.matches("https://app.example.com.evil.com"));
+        assert!(!pattern.matches("https://app.example.com:8443"));
+        assert!(!pattern.matches("http://app.example.com"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_patterns() {
+        for pattern in ["localhost:8000", "*", "https://*", "https://example.com/", "https://Example.com", "https://example.com:443", "https://app.*.example.com"] {
+            assert!(OriginPattern::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,417 @@
+use askama::Template;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::{
+    domain::{email_client::EmailMessage, locale::Locale},
+    utils::i18n::Translator,
+};
+
+// Per-deployment look of the emails, see `email.branding` in the settings
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Branding {
+    pub product_name: String,
+    pub product_url: String,
+    pub support_email: String,
+    pub logo_url: Option<String>,
+    // Hex colour used for buttons, codes and the header border
+    pub accent_color: String,
+}
+
+impl Default for Branding {
+    fn default() -> Self {
+        Self {
+            product_name: "Live Bootcamp".to_owned(),
+            product_url: "http://localhost:8000".to_owned(),
+            support_email: "support@example.com".to_owned(),
+            logo_url: None,
+            accent_color: "#2563eb".to_owned(),
+        }
+    }
+}
+
+impl Branding {
+    // The values end up in HTML attributes and inline styles, so only accept well-formed ones
+    pub fn validate(&self) -> Result<(), String> {
+        if self.product_name.trim().is_empty() {
+            return Err("product_name must not be empty".to_owned());
+        }
+
+        for url in std::iter::once(&self.product_url).chain(&self.logo_url) {
+            if Url::parse(url).is_err() {
+                return Err(format!("not a valid url: {}", url));
+            }
+        }
+
+        if !self.support_email.contains('@') {
+            return Err(format!("not a valid support email: {}", self.support_email));
+        }
+
+        let is_hex_color = self.accent_color.strip_prefix('#').is_some_and(|hex| {
+            matches!(hex.len(), 3 | 6) && hex.bytes().all(|b| b.is_ascii_hexdigit())
+        });
+        if !is_hex_color {
+            return Err(format!("not a valid hex colour: {}", self.accent_color));
+        }
+
+        Ok(())
+    }
+}
+
+// An email with an HTML and a plain-text variant under templates/emails. The wording comes
+// from the translation catalogs, under the email's `key`.
+pub trait EmailTemplate {
+    fn key(&self) -> &'static str;
+
+    // Values for the placeholders in the email's messages, `product` is always available
+    fn args(&self) -> Vec<(&'static str, String)>;
+    fn render_html(&self, branding: &Branding, t: &Translator) -> askama::Result<String>;
+    fn render_text(&self, branding: &Branding, t: &Translator) -> askama::Result<String>;
+
+    fn render(&self, branding: &Branding, locale: Locale) -> Result<EmailMessage> {
+        let mut args = self.args();
+        args.push(("product", branding.product_name.clone()));
+        let t = Translator::new(locale, args);
+
+        Ok(EmailMessage {
+            subject: t.get(&format!("{}.subject", self.key())),
+            html_body: self.render_html(branding, &t)?,
+            text_body: self.render_text(branding, &t)?,
+        })
+    }
+}
+
+pub struct TwoFACodeEmail<'a> {
+    pub code: &'a str,
+    pub valid_for_minutes: u64,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.html")]
+struct TwoFACodeHtml<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    email: &'a TwoFACodeEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.txt")]
+struct TwoFACodeText<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    email: &'a TwoFACodeEmail<'a>,
+}
+
+impl EmailTemplate for TwoFACodeEmail<'_> {
+    fn key(&self) -> &'static str {
+        "email.two_fa_code"
+    }
+
+    fn args(&self) -> Vec<(&'static str, String)> {
+        vec![("minutes", self.valid_for_minutes.to_string())]
+    }
+
+    fn render_html(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        TwoFACodeHtml { branding, t, email: self }.render()
+    }
+
+    fn render_text(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        TwoFACodeText { branding, t, email: self }.render()
+    }
+}
+
+pub struct VerificationEmail<'a> {
+    pub verification_url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/verification.html")]
+struct VerificationHtml<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    email: &'a VerificationEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/verification.txt")]
+struct VerificationText<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    email: &'a VerificationEmail<'a>,
+}
+
+impl EmailTemplate for VerificationEmail<'_> {
+    fn key(&self) -> &'static str {
+        "email.verification"
+    }
+
+    fn args(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    fn render_html(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        VerificationHtml { branding, t, email: self }.render()
+    }
+
+    fn render_text(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        VerificationText { branding, t, email: self }.render()
+    }
+}
+
+pub struct PasswordResetEmail<'a> {
+    pub reset_url: &'a str,
+    pub valid_for_minutes: u64,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.html")]
+struct PasswordResetHtml<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    email: &'a PasswordResetEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.txt")]
+struct PasswordResetText<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    email: &'a PasswordResetEmail<'a>,
+}
+
+impl EmailTemplate for PasswordResetEmail<'_> {
+    fn key(&self) -> &'static str {
+        "email.password_reset"
+    }
+
+    fn args(&self) -> Vec<(&'static str, String)> {
+        vec![("minutes", self.valid_for_minutes.to_string())]
+    }
+
+    fn render_html(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        PasswordResetHtml { branding, t, email: self }.render()
+    }
+
+    fn render_text(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        PasswordResetText { branding, t, email: self }.render()
+    }
+}
+
+// Security notices about the account, each with its own subject, title and message in the catalogs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notice {
+    AccountLocked,
+    PasswordChanged,
+}
+
+pub struct NoticeEmail {
+    pub notice: Notice,
+}
+
+#[derive(Template)]
+#[template(path = "emails/notice.html")]
+struct NoticeHtml<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    title: String,
+    message: String,
+}
+
+#[derive(Template)]
+#[template(path = "emails/notice.txt")]
+struct NoticeText<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    title: String,
+    message: String,
+}
+
+impl EmailTemplate for NoticeEmail {
+    fn key(&self) -> &'static str {
+        match self.notice {
+            Notice::AccountLocked => "email.notice.account_locked",
+            Notice::PasswordChanged => "email.notice.password_changed",
+        }
+    }
+
+    fn args(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    fn render_html(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        let title = t.get(&format!("{}.title", self.key()));
+        let message = t.get(&format!("{}.message", self.key()));
+        NoticeHtml { branding, t, title, message }.render()
+    }
+
+    fn render_text(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        let title = t.get(&format!("{}.title", self.key()));
+        let message = t.get(&format!("{}.message", self.key()));
+        NoticeText { branding, t, title, message }.render()
+    }
+}
+
+pub struct InvitationEmail<'a> {
+    pub inviter: &'a str,
+    pub code: &'a str,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/invitation.html")]
+struct InvitationHtml<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    email: &'a InvitationEmail<'a>,
+}
+
+#[derive(Template)]
+#[template(path = "emails/invitation.txt")]
+struct InvitationText<'a> {
+    branding: &'a Branding,
+    t: &'a Translator,
+    email: &'a InvitationEmail<'a>,
+}
+
+impl EmailTemplate for InvitationEmail<'_> {
+    fn key(&self) -> &'static str {
+        "email.invitation"
+    }
+
+    fn args(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("inviter", self.inviter.to_owned()),
+            ("expires_at", self.expires_at.format("%Y-%m-%d %H:%M UTC").to_string()),
+        ]
+    }
+
+    fn render_html(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        InvitationHtml { branding, t, email: self }.render()
+    }
+
+    fn render_text(&self, branding: &Branding, t: &Translator) -> askama::Result<String> {
+        InvitationText { branding, t, email: self }.render()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn branding() -> Branding {
+        Branding {
+            product_name: "Acme <Auth>".to_owned(),
+            product_url: "https://acme.example.com".to_owned(),
+            support_email: "help@acme.example.com".to_owned(),
+            logo_url: Some("https://acme.example.com/logo.png".to_owned()),
+            accent_color: "#ff6600".to_owned(),
+        }
+    }
+
+    fn assert_snapshots(name: &str, template: &impl EmailTemplate) {
+        assert_localized_snapshots(name, template, Locale::En);
+    }
+
+    fn assert_localized_snapshots(name: &str, template: &impl EmailTemplate, locale: Locale) {
+        let message = template.render(&branding(), locale).expect("Failed to render email");
+
+        insta::assert_snapshot!(format!("{}_subject", name), message.subject);
+        insta::assert_snapshot!(format!("{}_html", name), message.html_body);
+        insta::assert_snapshot!(format!("{}_text", name), message.text_body);
+    }
+
+    #[test]
+    fn test_two_fa_code_email() {
+        assert_snapshots("two_fa_code", &TwoFACodeEmail { code: "123456", valid_for_minutes: 10 });
+    }
+
+    #[test]
+    fn test_verification_email() {
+        let email = VerificationEmail { verification_url: "https://acme.example.com/verify?token=abc&id=1" };
+        assert_snapshots("verification", &email);
+    }
+
+    #[test]
+    fn test_password_reset_email() {
+        let email = PasswordResetEmail { reset_url: "https://acme.example.com/reset?token=abc", valid_for_minutes: 30 };
+        assert_snapshots("password_reset", &email);
+    }
+
+    #[test]
+    fn test_notice_email() {
+        assert_snapshots("notice", &NoticeEmail { notice: Notice::AccountLocked });
+    }
+
+    #[test]
+    fn test_invitation_email() {
+        let email = InvitationEmail {
+            inviter: "alice@example.com",
+            code: "AbCdEfGhIjKlMnOpQrStUvWx",
+            expires_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap(),
+        };
+        assert_snapshots("invitation", &email);
+    }
+
+    #[test]
+    fn test_localized_emails() {
+        let email = TwoFACodeEmail { code: "123456", valid_for_minutes: 10 };
+        assert_localized_snapshots("two_fa_code_de", &email, Locale::De);
+
+        let email = NoticeEmail { notice: Notice::PasswordChanged };
+        assert_localized_snapshots("notice_fr", &email, Locale::Fr);
+    }
+
+    #[test]
+    fn test_every_email_renders_in_every_locale() {
+        let expires_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap();
+
+        for locale in Locale::ALL {
+            let messages = [
+                TwoFACodeEmail { code: "123456", valid_for_minutes: 10 }.render(&branding(), locale),
+                VerificationEmail { verification_url: "https://acme.example.com/verify" }.render(&branding(), locale),
+                PasswordResetEmail { reset_url: "https://acme.example.com/reset", valid_for_minutes: 30 }.render(&branding(), locale),
+                NoticeEmail { notice: Notice::AccountLocked }.render(&branding(), locale),
+                NoticeEmail { notice: Notice::PasswordChanged }.render(&branding(), locale),
+                InvitationEmail { inviter: "alice@example.com", code: "AbCd", expires_at }.render(&branding(), locale),
+            ];
+
+            for message in messages {
+                let message = message.expect("Failed to render email");
+
+                // Every message was found in the catalogs and every placeholder filled in
+                for body in [&message.subject, &message.html_body, &message.text_body] {
+                    let has_key = body
+                        .match_indices("email.")
+                        .any(|(i, _)| body[i + 6..].starts_with(|c: char| c.is_ascii_lowercase()));
+                    assert!(!has_key, "Missing translation in {}: {}", locale.as_ref(), body);
+                    assert!(!body.contains('{'), "Unfilled placeholder in {}: {}", locale.as_ref(), body);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_branding_is_valid() {
+        assert!(Branding::default().validate().is_ok());
+        assert!(branding().validate().is_ok());
+    }
+
+    #[test]
+    fn test_branding_rejects_unsafe_values() {
+        let cases = [
+            Branding { accent_color: "red; background: url(x)".to_owned(), ..branding() },
+            Branding { accent_color: "#12345".to_owned(), ..branding() },
+            Branding { product_url: "not a url".to_owned(), ..branding() },
+            Branding { logo_url: Some("javascript".to_owned()), ..branding() },
+            Branding { support_email: "nobody".to_owned(), ..branding() },
+            Branding { product_name: " ".to_owned(), ..branding() },
+        ];
+
+        assert!(cases.iter().all(|b| b.validate().is_err()));
+    }
+}
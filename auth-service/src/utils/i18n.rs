@@ -0,0 +1,214 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use axum::{
+    extract::Request,
+    http::{header::ACCEPT_LANGUAGE, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::domain::{
+    email_domain_policy::EmailDomainError,
+    locale::Locale,
+    password_policy::{CharacterClass, PasswordPolicyError},
+};
+
+type Catalog = HashMap<String, String>;
+
+// Flat `key -> message` maps under locales/, compiled into the binary. Messages may contain
+// `{name}` placeholders which are filled in by `translate_with`.
+static CATALOGS: LazyLock<HashMap<Locale, Catalog>> = LazyLock::new(|| {
+    Locale::ALL
+        .into_iter()
+        .map(|locale| {
+            let catalog: Catalog = serde_json::from_str(catalog_source(locale))
+                .unwrap_or_else(|e| panic!("Translation catalog {} is not valid: {}", locale.as_ref(), e));
+            (locale, catalog)
+        })
+        .collect()
+});
+
+fn catalog_source(locale: Locale) -> &'static str {
+    match locale {
+        Locale::En => include_str!("../../locales/en.json"),
+        Locale::De => include_str!("../../locales/de.json"),
+        Locale::Es => include_str!("../../locales/es.json"),
+        Locale::Fr => include_str!("../../locales/fr.json"),
+    }
+}
+
+// Falls back to English for keys a catalog is missing, and to the key itself as a last resort
+pub fn translate(locale: Locale, key: &str) -> &str {
+    [locale, Locale::En]
+        .iter()
+        .find_map(|locale| CATALOGS.get(locale)?.get(key))
+        .map(String::as_str)
+        .unwrap_or(key)
+}
+
+pub fn translate_with(locale: Locale, key: &str, args: &[(&str, &str)]) -> String {
+    args.iter()
+        .fold(translate(locale, key).to_owned(), |message, (name, value)| {
+            message.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+// Looks messages up for one locale with a fixed set of placeholder values, handed to the
+// email templates as `t`
+pub struct Translator {
+    locale: Locale,
+    args: Vec<(&'static str, String)>,
+}
+
+impl Translator {
+    pub fn new(locale: Locale, args: Vec<(&'static str, String)>) -> Self {
+        Self { locale, args }
+    }
+
+    pub fn locale(&self) -> &str {
+        self.locale.as_ref()
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        let args: Vec<(&str, &str)> = self.args
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        translate_with(self.locale, key, &args)
+    }
+}
+
+pub trait Localize {
+    fn localize(&self, locale: Locale) -> String;
+}
+
+impl Localize for PasswordPolicyError {
+    fn localize(&self, locale: Locale) -> String {
+        match self {
+            Self::TooShort(min) => translate_with(locale, "error.password.too_short", &[("min", &min.to_string())]),
+            Self::TooLong(max) => translate_with(locale, "error.password.too_long", &[("max", &max.to_string())]),
+            Self::MissingCharacterClass(class) => {
+                let key = match class {
+                    CharacterClass::Lowercase => "error.password.class.lowercase",
+                    CharacterClass::Uppercase => "error.password.class.uppercase",
+                    CharacterClass::Digit => "error.password.class.digit",
+                    CharacterClass::Symbol => "error.password.class.symbol",
+                };
+                translate_with(locale, "error.password.missing_class", &[("class", translate(locale, key))])
+            }
+            Self::BannedWord => translate(locale, "error.password.banned_word").to_owned(),
+            Self::TooWeak { score, required } => translate_with(
+                locale,
+                "error.password.too_weak",
+                &[("score", &score.to_string()), ("required", &required.to_string())],
+            ),
+        }
+    }
+}
+
+impl Localize for EmailDomainError {
+    fn localize(&self, locale: Locale) -> String {
+        let key = match self {
+            Self::NotAllowed => "error.email_domain.not_allowed",
+            Self::Denied => "error.email_domain.denied",
+            Self::Disposable => "error.email_domain.disposable",
+        };
+        translate(locale, key).to_owned()
+    }
+}
+
+pub fn accept_language(headers: &HeaderMap) -> Option<Locale> {
+    headers
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Locale::negotiate)
+}
+
+tokio::task_local! {
+    static REQUEST_LOCALE: Locale;
+}
+
+// Locale negotiated for the request being handled, used for error responses which are built
+// without access to the request
+pub fn request_locale() -> Locale {
+    REQUEST_LOCALE.try_with(|locale| *locale).unwrap_or_default()
+}
+
+pub async fn set_request_locale(request: Request, next: Next) -> Response {
+    let locale = accept_language(request.headers()).unwrap_or_default();
+    REQUEST_LOCALE.scope(locale, next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_catalogs_have_same_keys_as_english() {
+        let english: HashSet<&String> = CATALOGS[&Locale::En].keys().collect();
+
+        for locale in Locale::ALL {
+            let keys: HashSet<&String> = CATALOGS[&locale].keys().collect();
+            assert_eq!(keys, english, "Catalog {} differs from English", locale.as_ref());
+        }
+    }
+
+    #[test]
+    fn test_catalogs_keep_placeholders() {
+        let placeholders = |message: &str| -> HashSet<String> {
+            message
+                .split('{')
+                .skip(1)
+                .filter_map(|rest| rest.split_once('}').map(|(name, _)| name.to_owned()))
+                .collect()
+        };
+
+        for (key, message) in &CATALOGS[&Locale::En] {
+            for locale in Locale::ALL {
+                assert_eq!(
+                    placeholders(translate(locale, key)),
+                    placeholders(message),
+                    "Placeholders of {} differ in {}",
+                    key,
+                    locale.as_ref()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_translate_falls_back() {
+        assert_eq!(translate(Locale::De, "error.user_not_found"), "Benutzer nicht gefunden");
+        assert_eq!(translate(Locale::En, "error.user_not_found"), "User not found");
+        assert_eq!(translate(Locale::Fr, "no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn test_translate_with_args() {
+        let message = translate_with(Locale::Es, "error.password.too_short", &[("min", "8")]);
+        assert_eq!(message, "La contraseña debe tener al menos 8 caracteres");
+    }
+
+    #[test]
+    fn test_english_matches_error_display() {
+        let errors = [
+            PasswordPolicyError::TooShort(8),
+            PasswordPolicyError::TooLong(128),
+            PasswordPolicyError::MissingCharacterClass(CharacterClass::Symbol),
+            PasswordPolicyError::BannedWord,
+            PasswordPolicyError::TooWeak { score: 1, required: 3 },
+        ];
+        assert!(errors.iter().all(|e| e.localize(Locale::En) == e.to_string()));
+
+        let errors = [EmailDomainError::NotAllowed, EmailDomainError::Denied, EmailDomainError::Disposable];
+        assert!(errors.iter().all(|e| e.localize(Locale::En) == e.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_request_locale_defaults_to_english() {
+        assert_eq!(request_locale(), Locale::En);
+        assert_eq!(REQUEST_LOCALE.scope(Locale::Fr, async { request_locale() }).await, Locale::Fr);
+    }
+}
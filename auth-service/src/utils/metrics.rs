@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use color_eyre::eyre::Result;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder};
+
+use crate::domain::data_stores::AuditOutcome;
+
+// The counters and histograms of one running service, rendered in Prometheus text format on
+// /metrics. Every Application gets its own registry so tests don't see each other's requests.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    signups: IntCounter,
+    logins: IntCounterVec,
+    two_fa_codes: IntCounterVec,
+    tokens_banned: IntCounter,
+    email_send_failures: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by method, route and status"),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by method and route"),
+            &["method", "route"],
+        )?;
+        let signups = IntCounter::new("auth_signups_total", "Users signed up")?;
+        let logins = IntCounterVec::new(
+            Opts::new("auth_logins_total", "Login attempts by outcome"),
+            &["outcome"],
+        )?;
+        let two_fa_codes = IntCounterVec::new(
+            Opts::new("auth_two_fa_codes_total", "2FA codes sent, verified and failed"),
+            &["event"],
+        )?;
+        let tokens_banned = IntCounter::new("auth_tokens_banned_total", "JWT auth tokens banned")?;
+        let email_send_failures = IntCounter::new("auth_email_send_failures_total", "Failed email delivery attempts")?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(signups.clone()))?;
+        registry.register(Box::new(logins.clone()))?;
+        registry.register(Box::new(two_fa_codes.clone()))?;
+        registry.register(Box::new(tokens_banned.clone()))?;
+        registry.register(Box::new(email_send_failures.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            signups,
+            logins,
+            two_fa_codes,
+            tokens_banned,
+            email_send_failures,
+        })
+    }
+
+    // Called from the TraceLayer once the response is ready
+    pub fn record_request(&self, response: &Response, latency: Duration) {
+        let (method, route) = match response.extensions().get::<RouteLabels>() {
+            Some(labels) => (labels.method.as_str(), labels.route.as_str()),
+            None => ("", UNMATCHED_ROUTE),
+        };
+        let status = response.status().as_u16().to_string();
+
+        self.http_requests.with_label_values(&[method, route, &status]).inc();
+        self.http_request_duration.with_label_values(&[method, route]).observe(latency.as_secs_f64());
+    }
+
+    pub fn signed_up(&self) {
+        self.signups.inc();
+    }
+
+    pub fn logged_in(&self, outcome: &AuditOutcome) {
+        self.logins.with_label_values(&[outcome.as_ref()]).inc();
+    }
+
+    pub fn two_fa(&self, event: TwoFAEvent) {
+        self.two_fa_codes.with_label_values(&[event.as_ref()]).inc();
+    }
+
+    pub fn token_banned(&self) {
+        self.tokens_banned.inc();
+    }
+
+    pub fn email_send_failed(&self) {
+        self.email_send_failures.inc();
+    }
+
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TwoFAEvent {
+    Sent,
+    Verified,
+    Failed,
+}
+
+impl AsRef<str> for TwoFAEvent {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Sent => "sent",
+            Self::Verified => "verified",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+// Requests that matched no route, such as 404s and the static assets, share one label so
+// arbitrary paths can't blow up the number of series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Debug, Clone)]
+struct RouteLabels {
+    method: Method,
+    route: String,
+}
+
+// The TraceLayer only sees the response when it finishes, so the route template the request
+// matched is handed over in the response extensions
+pub async fn set_route_labels(request: Request, next: Next) -> Response {
+    let labels = RouteLabels {
+        method: request.method().clone(),
+        route: request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str().to_owned())
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_owned()),
+    };
+
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(labels);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::StatusCode};
+
+    use super::*;
+
+    fn response(status: StatusCode, route: Option<&str>) -> Response {
+        let mut response = Response::builder().status(status).body(Body::empty()).unwrap();
+        if let Some(route) = route {
+            response.extensions_mut().insert(RouteLabels { method: Method::POST, route: route.to_owned() });
+        }
+        response
+    }
+
+    #[test]
+    fn test_records_requests_per_route() {
+        let metrics = Metrics::new().unwrap();
+
+        metrics.record_request(&response(StatusCode::OK, Some("/login")), Duration::from_millis(20));
+        metrics.record_request(&response(StatusCode::OK, Some("/login")), Duration::from_millis(30));
+        metrics.record_request(&response(StatusCode::NOT_FOUND, None), Duration::from_millis(1));
+
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains(r#"http_requests_total{method="POST",route="/login",status="200"} 2"#));
+        assert!(rendered.contains(r#"http_requests_total{method="",route="unmatched",status="404"} 1"#));
+        assert!(rendered.contains(r#"http_request_duration_seconds_count{method="POST",route="/login"} 2"#));
+    }
+
+    #[test]
+    fn test_records_domain_counters() {
+        let metrics = Metrics::new().unwrap();
+
+        metrics.signed_up();
+        metrics.logged_in(&AuditOutcome::Success);
+        metrics.logged_in(&AuditOutcome::Failure);
+        metrics.logged_in(&AuditOutcome::Failure);
+        metrics.two_fa(TwoFAEvent::Sent);
+        metrics.token_banned();
+        metrics.email_send_failed();
+
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains("auth_signups_total 1"));
+        assert!(rendered.contains(r#"auth_logins_total{outcome="failure"} 2"#));
+        assert!(rendered.contains(r#"auth_logins_total{outcome="success"} 1"#));
+        assert!(rendered.contains(r#"auth_two_fa_codes_total{event="sent"} 1"#));
+        assert!(rendered.contains("auth_tokens_banned_total 1"));
+        assert!(rendered.contains("auth_email_send_failures_total 1"));
+    }
+}
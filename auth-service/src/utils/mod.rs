@@ -1,3 +1,14 @@
 pub mod constants;
+pub mod settings;
 pub mod auth;
-pub mod tracing;
\ No newline at end of file
+pub mod cors;
+pub mod tracing;
+pub mod telemetry;
+pub mod rolling_file;
+pub mod shutdown;
+pub mod request_context;
+pub mod audit;
+pub mod email_templates;
+pub mod i18n;
+pub mod metrics;
+pub mod retry;
\ No newline at end of file
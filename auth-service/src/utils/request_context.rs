@@ -0,0 +1,78 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+
+use crate::domain::{
+    data_stores::{AuditEvent, AuditEventType, AuditOutcome},
+    error::AuthAPIError,
+    locale::Locale,
+};
+
+use super::{i18n::accept_language, tracing::RequestId};
+
+// Who made the request and from where, as recorded in the audit log
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    // Negotiated from Accept-Language, None if the client did not ask for a language we support
+    pub locale: Option<Locale>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RequestContext
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+
+        let request_id = parts
+            .extensions
+            .get::<RequestId>()
+            .map(|id| id.to_string());
+
+        let locale = accept_language(&parts.headers);
+
+        Ok(Self { ip, user_agent, request_id, locale })
+    }
+}
+
+impl RequestContext {
+    pub fn audit_event<T>(
+        &self,
+        event_type: AuditEventType,
+        actor: Option<String>,
+        result: &Result<T, AuthAPIError>,
+    ) -> AuditEvent {
+        let (outcome, detail) = match result {
+            Ok(_) => (AuditOutcome::Success, None),
+            Err(e) => (AuditOutcome::Failure, Some(e.to_string())),
+        };
+
+        AuditEvent {
+            actor,
+            ip: self.ip.clone(),
+            user_agent: self.user_agent.clone(),
+            request_id: self.request_id.clone(),
+            detail,
+            ..AuditEvent::new(event_type, outcome)
+        }
+    }
+}
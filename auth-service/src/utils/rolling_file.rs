@@ -0,0 +1,184 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::settings::LogRotation;
+
+// A log file that is moved aside to `<path>.<timestamp>` once it grows past the size limit or gets
+// older than the rotation period, keeping only the newest `max_files` of those
+pub struct RollingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+impl RollingFile {
+    // Appends to the file when it already exists
+    pub fn open(path: &Path, rotation: LogRotation) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        let opened_at = metadata
+            .created()
+            .or_else(|_| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+
+        Ok(Self { path: path.to_owned(), rotation, file, size: metadata.len(), opened_at })
+    }
+
+    fn should_rotate(&self, incoming: usize) -> bool {
+        // A single write larger than the limit still goes to one file
+        let too_big = self.rotation.max_size_mb
+            .is_some_and(|max_size_mb| self.size > 0 && self.size + incoming as u64 > max_size_mb * 1024 * 1024);
+        let too_old = self.rotation.period
+            .is_some_and(|period| self.opened_at.elapsed().is_ok_and(|age| age >= period));
+
+        too_big || too_old
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H-%M-%S%.3f");
+        fs::rename(&self.path, rotated_path(&self.path, &timestamp.to_string()))?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.opened_at = SystemTime::now();
+
+        self.remove_old_files()
+    }
+
+    fn remove_old_files(&self) -> io::Result<()> {
+        if self.rotation.max_files == 0 {
+            return Ok(());
+        }
+
+        let prefix = rotated_path(Path::new(file_name(&self.path)), "");
+        let prefix = prefix.to_string_lossy();
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let mut rotated: Vec<PathBuf> = fs::read_dir(directory)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(prefix.as_ref()))
+            .map(|entry| entry.path())
+            .collect();
+        // Timestamps sort in the order the files were rotated
+        rotated.sort();
+
+        let excess = rotated.len().saturating_sub(self.rotation.max_files);
+        for path in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name().and_then(|name| name.to_str()).unwrap_or_default()
+}
+
+fn rotated_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".");
+    rotated.push(suffix);
+    PathBuf::from(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn log_dir() -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("logs-{}", Uuid::new_v4()));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn rotated_files(directory: &Path) -> Vec<String> {
+        let mut files: Vec<String> = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("auth.log."))
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_appends_to_existing_file() {
+        let directory = log_dir();
+        let path = directory.join("auth.log");
+        fs::write(&path, "before\n").unwrap();
+
+        let mut file = RollingFile::open(&path, LogRotation::default()).unwrap();
+        file.write_all(b"after\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "before\nafter\n");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_rotates_by_size_and_keeps_max_files() {
+        let directory = log_dir();
+        let path = directory.join("auth.log");
+        let rotation = LogRotation { max_size_mb: Some(1), period: None, max_files: 2 };
+
+        let line = vec![b'x'; 600 * 1024];
+        let mut file = RollingFile::open(&path, rotation).unwrap();
+        for _ in 0..5 {
+            file.write_all(&line).unwrap();
+            // Rotated files are named to the millisecond
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        assert_eq!(rotated_files(&directory).len(), 2);
+        assert_eq!(fs::metadata(&path).unwrap().len(), line.len() as u64);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_rotates_by_age() {
+        let directory = log_dir();
+        let path = directory.join("auth.log");
+        let rotation = LogRotation { max_size_mb: None, period: Some(Duration::from_millis(50)), max_files: 0 };
+
+        let mut file = RollingFile::open(&path, rotation).unwrap();
+        file.write_all(b"first\n").unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+        file.write_all(b"second\n").unwrap();
+
+        let rotated = rotated_files(&directory);
+        assert_eq!(rotated.len(), 1);
+        assert_eq!(fs::read_to_string(directory.join(&rotated[0])).unwrap(), "first\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
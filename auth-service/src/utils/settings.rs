@@ -0,0 +1,869 @@
+use std::{
+    env as std_env,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use dotenvy::dotenv;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{de::DeserializeOwned, Deserialize};
+use thiserror::Error;
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    domain::{email::Email, email_client::EmailProvider, email_domain_policy::EmailDomainPolicy, password::MIN_PASSWORD_LENGTH, password_policy::PasswordPolicy},
+    services::{
+        data_stores::{PasswordHashParams, SmtpConfig},
+        webhook_dispatcher::WebhookSubscription,
+    },
+    utils::{constants::{env, LOG_NAME}, cors::cors_layer, email_templates::Branding, retry::RetryPolicy},
+};
+
+// Everything the service can be configured with. Read once at startup from a TOML file, with the
+// environment variables in `constants::env` on top, and validated before anything is built so a
+// bad deployment fails right away instead of on the first request that needs the setting.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub application: ApplicationSettings,
+    pub cors: CorsSettings,
+    pub auth: AuthSettings,
+    pub two_fa: TwoFASettings,
+    pub database: DatabaseSettings,
+    pub redis: RedisSettings,
+    pub email: EmailSettings,
+    pub webhooks: WebhookSettings,
+    pub passwords: PasswordSettings,
+    pub signup: SignupSettings,
+    pub health: HealthSettings,
+    pub telemetry: TelemetrySettings,
+    pub logging: LoggingSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApplicationSettings {
+    pub address: String,
+    // On SIGINT or SIGTERM, how long in-flight requests and then background work get to finish
+    #[serde(with = "humantime_serde")]
+    pub shutdown_timeout: Duration,
+}
+
+impl Default for ApplicationSettings {
+    fn default() -> Self {
+        Self { address: "0.0.0.0:3000".to_owned(), shutdown_timeout: Duration::from_secs(30) }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsSettings {
+    // Exact origins, or `https://*.example.com` for any subdomain
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    // How long browsers may cache a preflight response
+    #[serde(with = "humantime_serde")]
+    pub max_age: Duration,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["http://localhost:8000".to_owned()],
+            allowed_methods: vec!["GET".to_owned(), "POST".to_owned()],
+            allowed_headers: vec!["content-type".to_owned()],
+            max_age: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    pub jwt_secret: Secret<String>,
+    // How long a JWT auth token is valid for, banned tokens are kept for as long
+    #[serde(with = "humantime_serde")]
+    pub token_ttl: Duration,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        Self {
+            jwt_secret: Secret::new(String::new()),
+            token_ttl: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TwoFASettings {
+    // How long a login code emailed to the user stays valid
+    #[serde(with = "humantime_serde")]
+    pub code_ttl: Duration,
+}
+
+impl Default for TwoFASettings {
+    fn default() -> Self {
+        Self { code_ttl: Duration::from_secs(10 * 60) }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSettings {
+    pub url: Secret<String>,
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        Self { url: Secret::new(String::new()) }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedisSettings {
+    pub host_name: String,
+}
+
+impl Default for RedisSettings {
+    fn default() -> Self {
+        Self { host_name: "127.0.0.1".to_owned() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmailSettings {
+    // In order of preference, later providers are only used when earlier ones fail
+    pub providers: Vec<EmailProvider>,
+    pub sender: String,
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    pub branding: Branding,
+    pub postmark: PostmarkSettings,
+    // Required with the smtp provider
+    pub smtp: Option<SmtpConfig>,
+    // Only needed when the SMTP relay has a username
+    pub smtp_password: Option<Secret<String>>,
+    // Where the dev provider saves .eml files
+    pub dev_mailbox_dir: PathBuf,
+    pub failover: FailoverSettings,
+    pub outbox: OutboxSettings,
+}
+
+impl Default for EmailSettings {
+    fn default() -> Self {
+        Self {
+            providers: vec![EmailProvider::default()],
+            sender: String::new(),
+            timeout: Duration::from_secs(10),
+            branding: Branding::default(),
+            postmark: PostmarkSettings::default(),
+            smtp: None,
+            smtp_password: None,
+            dev_mailbox_dir: PathBuf::from("dev-mailbox"),
+            failover: FailoverSettings::default(),
+            outbox: OutboxSettings::default(),
+        }
+    }
+}
+
+impl EmailSettings {
+    // The longest a single send can take, when failing over that is every provider timing out in turn
+    pub fn send_timeout(&self) -> Duration {
+        match self.providers.len() {
+            0 | 1 => self.timeout,
+            providers => self.failover.timeout.min(self.timeout) * providers as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostmarkSettings {
+    pub base_url: String,
+    // Required with the postmark provider
+    pub auth_token: Option<Secret<String>>,
+}
+
+impl Default for PostmarkSettings {
+    fn default() -> Self {
+        Self { base_url: "https://api.postmarkapp.com".to_owned(), auth_token: None }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FailoverSettings {
+    // Per provider, a provider that takes longer counts as failed
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    pub failure_threshold: u32,
+    #[serde(with = "humantime_serde")]
+    pub open_duration: Duration,
+}
+
+impl Default for FailoverSettings {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(15),
+            failure_threshold: 3,
+            open_duration: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutboxSettings {
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+    pub retry: RetryPolicy,
+}
+
+impl Default for OutboxSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            retry: RetryPolicy {
+                max_attempts: 8,
+                initial_backoff: Duration::from_secs(5),
+                max_backoff: Duration::from_secs(15 * 60),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookSettings {
+    pub subscriptions: Vec<WebhookSubscription>,
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    pub retry: RetryPolicy,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            subscriptions: Vec::new(),
+            timeout: Duration::from_secs(10),
+            retry: RetryPolicy {
+                max_attempts: 5,
+                initial_backoff: Duration::from_secs(1),
+                max_backoff: Duration::from_secs(60),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasswordSettings {
+    pub policy: PasswordPolicy,
+    pub hash_params: PasswordHashParams,
+    // Without a corpus the breached password check accepts every password
+    pub breached_passwords_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignupSettings {
+    // Signup requires a valid invitation code when set
+    pub invite_only: bool,
+    pub email_domain_policy: EmailDomainPolicy,
+    // Without a list no address is treated as disposable
+    pub disposable_email_domains_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthSettings {
+    // Per dependency, one that takes longer counts as down
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    // Also probe the email provider on /health/ready, Postmark counts these requests
+    pub check_email_provider: bool,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self { timeout: Duration::from_secs(2), check_email_provider: false }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetrySettings {
+    // Base url of an OTLP/HTTP collector such as http://localhost:4318, spans are only exported
+    // when set. Incoming trace context is honoured and propagated either way.
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+    #[serde(with = "humantime_serde")]
+    pub export_timeout: Duration,
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: "auth-service".to_owned(),
+            export_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingSettings {
+    // Filter directives such as "info,sqlx=warn", RUST_LOG takes precedence
+    pub level: String,
+    pub sinks: Vec<LogSinkSettings>,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".to_owned(),
+            sinks: vec![
+                LogSinkSettings { kind: LogSinkKind::Stdout, ..LogSinkSettings::default() },
+                LogSinkSettings { kind: LogSinkKind::File, path: Some(PathBuf::from(LOG_NAME)), ..LogSinkSettings::default() },
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSinkSettings {
+    pub kind: LogSinkKind,
+    pub format: LogFormat,
+    // Required for file sinks
+    pub path: Option<PathBuf>,
+    pub rotation: LogRotation,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSinkKind {
+    #[default]
+    Stdout,
+    File,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Compact,
+    // One JSON object per line, for log shippers
+    Json,
+}
+
+// File sinks only, without a size or period the file is never rotated
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogRotation {
+    pub max_size_mb: Option<u64>,
+    #[serde(with = "humantime_serde")]
+    pub period: Option<Duration>,
+    // Rotated files to keep, 0 keeps them all
+    pub max_files: usize,
+}
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("Failed to read config file {}", .path.display())]
+    ReadFile { path: PathBuf, source: std::io::Error },
+
+    #[error("Config file {} is not valid", .path.display())]
+    ParseFile { path: PathBuf, source: toml::de::Error },
+
+    #[error("Environment variable {name} is not valid: {reason}")]
+    InvalidEnvVar { name: &'static str, reason: String },
+
+    #[error("Setting {name} is not valid: {reason}")]
+    Invalid { name: &'static str, reason: String },
+}
+
+impl Settings {
+    // Reads the file named by AUTH_SERVICE_CONFIG, or `DEFAULT_CONFIG_PATH` when it exists, then
+    // applies the environment and validates the result
+    pub fn load() -> Result<Self, SettingsError> {
+        dotenv().ok();
+
+        let settings = match std_env::var(env::AUTH_SERVICE_CONFIG_ENV_VAR) {
+            Ok(path) => Self::from_file(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?,
+            Err(_) => Self::default(),
+        };
+
+        settings
+            .with_env(|name| std_env::var(name).ok())?
+            .validated()
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, SettingsError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|source| SettingsError::ReadFile { path: path.to_owned(), source })?;
+
+        toml::from_str(&contents)
+            .map_err(|source| SettingsError::ParseFile { path: path.to_owned(), source })
+    }
+
+    // Overrides settings with the environment variables that are set. Structured settings take
+    // JSON, as they did before the config file existed.
+    pub fn with_env(mut self, var: impl Fn(&str) -> Option<String>) -> Result<Self, SettingsError> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+
+        if let Some(address) = var(env::APP_ADDRESS_ENV_VAR) {
+            self.application.address = address;
+        }
+        if let Some(origins) = var(env::CORS_ALLOWED_ORIGINS_ENV_VAR) {
+            self.cors.allowed_origins = origins.split(',').map(|origin| origin.trim().to_owned()).collect();
+        }
+        if let Some(secret) = var(env::JWT_SECRET_ENV_VAR) {
+            self.auth.jwt_secret = Secret::new(secret);
+        }
+        if let Some(url) = var(env::DATABASE_URL_ENV_VAR) {
+            self.database.url = Secret::new(url);
+        }
+        if let Some(host_name) = var(env::REDIS_HOST_NAME_ENV_VAR) {
+            self.redis.host_name = host_name;
+        }
+        if let Some(providers) = var(env::EMAIL_PROVIDER_ENV_VAR) {
+            self.email.providers = EmailProvider::parse_list(&providers).map_err(|e| SettingsError::InvalidEnvVar {
+                name: env::EMAIL_PROVIDER_ENV_VAR,
+                reason: e.to_string(),
+            })?;
+        }
+        if let Some(sender) = var(env::EMAIL_SENDER_ENV_VAR) {
+            self.email.sender = sender;
+        }
+        if let Some(token) = var(env::POSTMARK_AUTH_TOKEN_ENV_VAR) {
+            self.email.postmark.auth_token = Some(Secret::new(token));
+        }
+        if let Some(json) = var(env::SMTP_CONFIG_ENV_VAR) {
+            self.email.smtp = Some(from_json(env::SMTP_CONFIG_ENV_VAR, &json)?);
+        }
+        if let Some(password) = var(env::SMTP_PASSWORD_ENV_VAR) {
+            self.email.smtp_password = Some(Secret::new(password));
+        }
+        if let Some(dir) = var(env::DEV_MAILBOX_DIR_ENV_VAR) {
+            self.email.dev_mailbox_dir = PathBuf::from(dir);
+        }
+        if let Some(json) = var(env::EMAIL_BRANDING_ENV_VAR) {
+            self.email.branding = from_json(env::EMAIL_BRANDING_ENV_VAR, &json)?;
+        }
+        if let Some(json) = var(env::WEBHOOK_SUBSCRIPTIONS_ENV_VAR) {
+            self.webhooks.subscriptions = from_json(env::WEBHOOK_SUBSCRIPTIONS_ENV_VAR, &json)?;
+        }
+        if let Some(json) = var(env::PASSWORD_POLICY_ENV_VAR) {
+            self.passwords.policy = from_json(env::PASSWORD_POLICY_ENV_VAR, &json)?;
+        }
+        if let Some(json) = var(env::PASSWORD_HASH_PARAMS_ENV_VAR) {
+            self.passwords.hash_params = from_json(env::PASSWORD_HASH_PARAMS_ENV_VAR, &json)?;
+        }
+        if let Some(path) = var(env::BREACHED_PASSWORDS_PATH_ENV_VAR) {
+            self.passwords.breached_passwords_path = Some(PathBuf::from(path));
+        }
+        if let Some(value) = var(env::INVITE_ONLY_SIGNUP_ENV_VAR) {
+            self.signup.invite_only = value.parse().map_err(|_| SettingsError::InvalidEnvVar {
+                name: env::INVITE_ONLY_SIGNUP_ENV_VAR,
+                reason: "must be true or false".to_owned(),
+            })?;
+        }
+        if let Some(json) = var(env::EMAIL_DOMAIN_POLICY_ENV_VAR) {
+            self.signup.email_domain_policy = from_json(env::EMAIL_DOMAIN_POLICY_ENV_VAR, &json)?;
+        }
+        if let Some(path) = var(env::DISPOSABLE_EMAIL_DOMAINS_PATH_ENV_VAR) {
+            self.signup.disposable_email_domains_path = Some(PathBuf::from(path));
+        }
+        if let Some(endpoint) = var(env::OTLP_ENDPOINT_ENV_VAR) {
+            self.telemetry.otlp_endpoint = Some(endpoint);
+        }
+        if let Some(service_name) = var(env::OTEL_SERVICE_NAME_ENV_VAR) {
+            self.telemetry.service_name = service_name;
+        }
+        if let Some(json) = var(env::LOG_SINKS_ENV_VAR) {
+            self.logging.sinks = from_json(env::LOG_SINKS_ENV_VAR, &json)?;
+        }
+
+        Ok(self)
+    }
+
+    // Checks the settings hang together, the email domain rules are also normalized here
+    pub fn validated(mut self) -> Result<Self, SettingsError> {
+        if self.application.address.parse::<std::net::SocketAddr>().is_err() {
+            return Err(invalid("application.address", "must be an ip:port socket address"));
+        }
+        if self.application.shutdown_timeout.is_zero() {
+            return Err(invalid("application.shutdown_timeout", "must be positive"));
+        }
+        if let Err(reason) = cors_layer(&self.cors) {
+            return Err(invalid("cors", reason));
+        }
+
+        if self.auth.jwt_secret.expose_secret().is_empty() {
+            return Err(invalid("auth.jwt_secret", "must be set"));
+        }
+        if self.auth.token_ttl.is_zero() {
+            return Err(invalid("auth.token_ttl", "must be positive"));
+        }
+        if self.two_fa.code_ttl < Duration::from_secs(60) {
+            return Err(invalid("two_fa.code_ttl", "must be at least a minute"));
+        }
+
+        if self.database.url.expose_secret().is_empty() {
+            return Err(invalid("database.url", "must be set"));
+        }
+
+        self.validate_email()?;
+
+        for subscription in &self.webhooks.subscriptions {
+            if Url::parse(&subscription.url).is_err() {
+                return Err(invalid("webhooks.subscriptions", format!("invalid url: {}", subscription.url)));
+            }
+        }
+        validate_retry_policy("webhooks.retry", &self.webhooks.retry)?;
+
+        let policy = &self.passwords.policy;
+        if policy.min_length < MIN_PASSWORD_LENGTH {
+            return Err(invalid("passwords.policy", format!("min_length must be at least {}", MIN_PASSWORD_LENGTH)));
+        }
+        if policy.min_length > policy.max_length {
+            return Err(invalid("passwords.policy", "min_length must not exceed max_length"));
+        }
+        if policy.min_strength > 4 {
+            return Err(invalid("passwords.policy", "min_strength must be between 0 and 4"));
+        }
+
+        self.signup.email_domain_policy = self.signup.email_domain_policy
+            .normalized()
+            .map_err(|domain| invalid("signup.email_domain_policy", format!("invalid domain: {}", domain)))?;
+
+        if self.health.timeout.is_zero() {
+            return Err(invalid("health.timeout", "must be positive"));
+        }
+
+        if let Some(endpoint) = &self.telemetry.otlp_endpoint {
+            let is_http = Url::parse(endpoint).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+            if !is_http {
+                return Err(invalid("telemetry.otlp_endpoint", "must be an http or https url"));
+            }
+        }
+        if self.telemetry.service_name.is_empty() {
+            return Err(invalid("telemetry.service_name", "must be set"));
+        }
+        if self.telemetry.export_timeout.is_zero() {
+            return Err(invalid("telemetry.export_timeout", "must be positive"));
+        }
+
+        self.validate_logging()?;
+
+        Ok(self)
+    }
+
+    fn validate_logging(&self) -> Result<(), SettingsError> {
+        let logging = &self.logging;
+
+        if let Err(e) = EnvFilter::try_new(&logging.level) {
+            return Err(invalid("logging.level", e.to_string()));
+        }
+        for sink in &logging.sinks {
+            match (sink.kind, &sink.path) {
+                (LogSinkKind::File, None) => return Err(invalid("logging.sinks", "file sinks must set a path")),
+                (LogSinkKind::Stdout, Some(_)) => return Err(invalid("logging.sinks", "stdout sinks have no path")),
+                _ => {}
+            }
+            if sink.rotation.max_size_mb == Some(0) || sink.rotation.period.is_some_and(|period| period.is_zero()) {
+                return Err(invalid("logging.sinks", "rotation max_size_mb and period must be positive"));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_email(&self) -> Result<(), SettingsError> {
+        let email = &self.email;
+
+        if email.providers.is_empty() {
+            return Err(invalid("email.providers", "must list at least one provider"));
+        }
+        for (i, provider) in email.providers.iter().enumerate() {
+            if email.providers[..i].contains(provider) {
+                return Err(invalid("email.providers", format!("{} is listed twice", provider.as_ref())));
+            }
+        }
+        // /dev/inbox serves every email without authentication, so real users' codes must never
+        // fall back to it
+        if email.providers.contains(&EmailProvider::Dev) && email.providers.len() > 1 {
+            return Err(invalid("email.providers", "dev can not be combined with other providers"));
+        }
+        if email.providers.contains(&EmailProvider::Postmark) {
+            let has_auth_token = email.postmark.auth_token.as_ref().is_some_and(|token| !token.expose_secret().is_empty());
+            if !has_auth_token {
+                return Err(invalid("email.postmark.auth_token", "must be set with the postmark provider"));
+            }
+            if Url::parse(&email.postmark.base_url).is_err() {
+                return Err(invalid("email.postmark.base_url", "must be a url"));
+            }
+        }
+        if email.providers.contains(&EmailProvider::Smtp) && email.smtp.is_none() {
+            return Err(invalid("email.smtp", "must be set with the smtp provider"));
+        }
+
+        if Email::parse(Secret::new(email.sender.clone())).is_err() {
+            return Err(invalid("email.sender", "must be an email address"));
+        }
+        if email.timeout.is_zero() {
+            return Err(invalid("email.timeout", "must be positive"));
+        }
+        email.branding.validate().map_err(|reason| invalid("email.branding", reason))?;
+
+        if email.failover.failure_threshold == 0 {
+            return Err(invalid("email.failover.failure_threshold", "must be positive"));
+        }
+        validate_retry_policy("email.outbox.retry", &email.outbox.retry)
+    }
+}
+
+// Used when AUTH_SERVICE_CONFIG is not set, relative to the working directory
+pub const DEFAULT_CONFIG_PATH: &str = "config/auth-service.toml";
+
+fn from_json<T: DeserializeOwned>(name: &'static str, json: &str) -> Result<T, SettingsError> {
+    serde_json::from_str(json).map_err(|e| SettingsError::InvalidEnvVar { name, reason: e.to_string() })
+}
+
+fn invalid(name: &'static str, reason: impl Into<String>) -> SettingsError {
+    SettingsError::Invalid { name, reason: reason.into() }
+}
+
+fn validate_retry_policy(name: &'static str, policy: &RetryPolicy) -> Result<(), SettingsError> {
+    if policy.max_attempts == 0 {
+        return Err(invalid(name, "max_attempts must be positive"));
+    }
+    if policy.initial_backoff > policy.max_backoff {
+        return Err(invalid(name, "initial_backoff must not exceed max_backoff"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn env_vars(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn required_env() -> Vec<(&'static str, &'static str)> {
+        vec![
+            (env::JWT_SECRET_ENV_VAR, "secret"),
+            (env::DATABASE_URL_ENV_VAR, "postgres://postgres@localhost:5432"),
+            (env::POSTMARK_AUTH_TOKEN_ENV_VAR, "token"),
+            (env::EMAIL_SENDER_ENV_VAR, "sender@example.com"),
+        ]
+    }
+
+    fn settings_with(vars: &[(&str, &str)]) -> Result<Settings, SettingsError> {
+        let mut all = required_env();
+        all.extend_from_slice(vars);
+        Settings::default().with_env(env_vars(&all))?.validated()
+    }
+
+    fn invalid_setting(result: Result<Settings, SettingsError>) -> &'static str {
+        match result {
+            Err(SettingsError::Invalid { name, .. }) | Err(SettingsError::InvalidEnvVar { name, .. }) => name,
+            other => panic!("expected an invalid setting, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_shipped_config_file_is_valid() {
+        let shipped = || -> Settings { toml::from_str(include_str!("../../config/auth-service.toml")).unwrap() };
+        let deployment_env = [
+            (env::JWT_SECRET_ENV_VAR, "secret"),
+            (env::DATABASE_URL_ENV_VAR, "postgres://postgres@localhost:5432"),
+            (env::POSTMARK_AUTH_TOKEN_ENV_VAR, "token"),
+        ];
+
+        // Every deployment has to pick its own sender address
+        let without_sender = shipped().with_env(env_vars(&deployment_env)).unwrap().validated();
+        assert_eq!(invalid_setting(without_sender), "email.sender");
+
+        let settings = shipped()
+            .with_env(env_vars(&[&deployment_env[..], &[(env::EMAIL_SENDER_ENV_VAR, "noreply@example.com")]].concat()))
+            .unwrap()
+            .validated()
+            .unwrap();
+
+        assert_eq!(settings.email.providers, vec![EmailProvider::Postmark]);
+    }
+
+    #[test]
+    fn test_parses_toml() {
+        let settings: Settings = toml::from_str(
+            r#"
+            [application]
+            address = "127.0.0.1:4000"
+
+            [auth]
+            token_ttl = "15m"
+
+            [email]
+            providers = ["smtp", "postmark"]
+            sender = "no-reply@example.com"
+
+            [email.smtp]
+            host = "smtp.example.com"
+
+            [email.outbox.retry]
+            max_attempts = 4
+            initial_backoff = "2s"
+            max_backoff = "1m"
+
+            [signup]
+            invite_only = true
+
+            [[logging.sinks]]
+            kind = "file"
+            format = "json"
+            path = "/var/log/auth.log"
+            rotation = { max_size_mb = 50, period = "1d", max_files = 7 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.application.address, "127.0.0.1:4000");
+        assert_eq!(settings.auth.token_ttl, Duration::from_secs(15 * 60));
+        assert_eq!(settings.two_fa.code_ttl, Duration::from_secs(10 * 60));
+        assert_eq!(settings.email.providers, vec![EmailProvider::Smtp, EmailProvider::Postmark]);
+        assert_eq!(settings.email.smtp.unwrap().host, "smtp.example.com");
+        assert_eq!(settings.email.outbox.retry.max_attempts, 4);
+        assert_eq!(settings.email.outbox.retry.initial_backoff, Duration::from_secs(2));
+        assert_eq!(settings.email.outbox.poll_interval, Duration::from_secs(1));
+        assert!(settings.signup.invite_only);
+        let sink = &settings.logging.sinks[0];
+        assert_eq!((sink.kind, sink.format), (LogSinkKind::File, LogFormat::Json));
+        assert_eq!(sink.rotation.period, Some(Duration::from_secs(24 * 60 * 60)));
+        assert_eq!(sink.rotation.max_files, 7);
+    }
+
+    #[test]
+    fn test_send_timeout_covers_every_provider() {
+        let mut email = EmailSettings { timeout: Duration::from_secs(10), ..Default::default() };
+        email.failover.timeout = Duration::from_secs(5);
+
+        email.providers = vec![EmailProvider::Postmark];
+        assert_eq!(email.send_timeout(), Duration::from_secs(10));
+
+        email.providers = vec![EmailProvider::Postmark, EmailProvider::Smtp];
+        assert_eq!(email.send_timeout(), Duration::from_secs(10));
+
+        email.failover.timeout = Duration::from_secs(30);
+        assert_eq!(email.send_timeout(), Duration::from_secs(20));
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys() {
+        assert!(toml::from_str::<Settings>("[auth]\ntoken_tll = \"5m\"").is_err());
+        assert!(toml::from_str::<Settings>("[emails]\nsender = \"a@example.com\"").is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let settings: Settings = toml::from_str("[signup]\ninvite_only = true\n[redis]\nhost_name = \"redis\"").unwrap();
+
+        let settings = settings
+            .with_env(env_vars(&[
+                (env::INVITE_ONLY_SIGNUP_ENV_VAR, "false"),
+                (env::EMAIL_PROVIDER_ENV_VAR, "smtp,postmark"),
+                (env::PASSWORD_POLICY_ENV_VAR, r#"{"min_length": 12}"#),
+                (env::REDIS_HOST_NAME_ENV_VAR, ""),
+                (env::CORS_ALLOWED_ORIGINS_ENV_VAR, "https://app.example.com, https://*.example.org"),
+            ]))
+            .unwrap();
+
+        assert!(!settings.signup.invite_only);
+        assert_eq!(settings.email.providers, vec![EmailProvider::Smtp, EmailProvider::Postmark]);
+        assert_eq!(settings.passwords.policy.min_length, 12);
+        assert_eq!(settings.cors.allowed_origins, vec!["https://app.example.com", "https://*.example.org"]);
+        // Empty variables are ignored
+        assert_eq!(settings.redis.host_name, "redis");
+    }
+
+    #[test]
+    fn test_rejects_malformed_env_vars() {
+        assert_eq!(invalid_setting(settings_with(&[(env::INVITE_ONLY_SIGNUP_ENV_VAR, "yes")])), env::INVITE_ONLY_SIGNUP_ENV_VAR);
+        assert_eq!(invalid_setting(settings_with(&[(env::EMAIL_PROVIDER_ENV_VAR, "carrier-pigeon")])), env::EMAIL_PROVIDER_ENV_VAR);
+        assert_eq!(invalid_setting(settings_with(&[(env::SMTP_CONFIG_ENV_VAR, "{")])), env::SMTP_CONFIG_ENV_VAR);
+    }
+
+    #[test]
+    fn test_validates_settings() {
+        assert!(settings_with(&[]).is_ok());
+
+        let missing_secret = Settings::default()
+            .with_env(env_vars(&[(env::DATABASE_URL_ENV_VAR, "postgres://localhost"), (env::POSTMARK_AUTH_TOKEN_ENV_VAR, "token")]))
+            .unwrap()
+            .validated();
+        assert_eq!(invalid_setting(missing_secret), "auth.jwt_secret");
+
+        let cases = [
+            ((env::APP_ADDRESS_ENV_VAR, "localhost"), "application.address"),
+            ((env::CORS_ALLOWED_ORIGINS_ENV_VAR, "*"), "cors"),
+            ((env::EMAIL_SENDER_ENV_VAR, "not-an-email"), "email.sender"),
+            ((env::EMAIL_PROVIDER_ENV_VAR, "smtp"), "email.smtp"),
+            ((env::EMAIL_PROVIDER_ENV_VAR, "postmark,dev"), "email.providers"),
+            ((env::EMAIL_PROVIDER_ENV_VAR, "dev,postmark"), "email.providers"),
+            ((env::PASSWORD_POLICY_ENV_VAR, r#"{"min_length": 20, "max_length": 10}"#), "passwords.policy"),
+            ((env::PASSWORD_POLICY_ENV_VAR, r#"{"min_length": 6}"#), "passwords.policy"),
+            ((env::EMAIL_BRANDING_ENV_VAR, r#"{"accent_color": "blue"}"#), "email.branding"),
+            ((env::WEBHOOK_SUBSCRIPTIONS_ENV_VAR, r#"[{"url": "nope", "secret": "s"}]"#), "webhooks.subscriptions"),
+            ((env::EMAIL_DOMAIN_POLICY_ENV_VAR, r#"{"denied_domains": ["."]}"#), "signup.email_domain_policy"),
+            ((env::OTLP_ENDPOINT_ENV_VAR, "localhost:4318"), "telemetry.otlp_endpoint"),
+            ((env::LOG_SINKS_ENV_VAR, r#"[{"kind": "file"}]"#), "logging.sinks"),
+            ((env::LOG_SINKS_ENV_VAR, r#"[{"kind": "stdout", "rotation": {"max_size_mb": 0}}]"#), "logging.sinks"),
+        ];
+        for (var, name) in cases {
+            assert_eq!(invalid_setting(settings_with(&[var])), name);
+        }
+    }
+
+    #[test]
+    fn test_postmark_token_only_required_with_postmark() {
+        let settings = Settings::default()
+            .with_env(env_vars(&[
+                (env::JWT_SECRET_ENV_VAR, "secret"),
+                (env::DATABASE_URL_ENV_VAR, "postgres://localhost"),
+                (env::EMAIL_SENDER_ENV_VAR, "sender@example.com"),
+                (env::EMAIL_PROVIDER_ENV_VAR, "dev"),
+            ]))
+            .unwrap()
+            .validated();
+        assert!(settings.is_ok());
+
+        let settings = Settings::default()
+            .with_env(env_vars(&[
+                (env::JWT_SECRET_ENV_VAR, "secret"),
+                (env::DATABASE_URL_ENV_VAR, "postgres://localhost"),
+                (env::EMAIL_SENDER_ENV_VAR, "sender@example.com"),
+            ]))
+            .unwrap()
+            .validated();
+        assert_eq!(invalid_setting(settings), "email.postmark.auth_token");
+    }
+
+    #[test]
+    fn test_normalizes_email_domain_policy() {
+        let settings = settings_with(&[(env::EMAIL_DOMAIN_POLICY_ENV_VAR, r#"{"allowed_domains": ["Example.COM"]}"#)]).unwrap();
+        assert_eq!(settings.signup.email_domain_policy.allowed_domains, vec!["example.com".to_owned()]);
+    }
+}
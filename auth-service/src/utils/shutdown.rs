@@ -0,0 +1,44 @@
+use tokio::signal;
+use tokio_util::sync::CancellationToken;
+
+// Triggers a graceful shutdown: the server stops accepting connections and background workers
+// stop after the work they are doing. Cheap to clone, tests trigger it directly.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownHandle(CancellationToken);
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        self.0.cancel();
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.0.is_cancelled()
+    }
+
+    // Completes once shutdown is triggered, right away if it already was
+    pub async fn wait(&self) {
+        self.0.cancelled().await
+    }
+}
+
+// Completes on SIGINT, or SIGTERM as sent by docker stop and orchestrators during deploys
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c().await.expect("Failed to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("received SIGINT"),
+        _ = terminate => tracing::info!("received SIGTERM"),
+    }
+}
@@ -0,0 +1,156 @@
+use axum::http::HeaderMap;
+use color_eyre::eyre::Result;
+use opentelemetry::{
+    propagation::{Extractor, Injector, TextMapPropagator},
+    Context,
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::settings::TelemetrySettings;
+
+// Spans get OpenTelemetry ids whether or not they are exported, so the trace context of incoming
+// requests is always carried on to the calls they make
+pub fn tracer_provider(settings: &TelemetrySettings) -> Result<SdkTracerProvider> {
+    let resource = Resource::builder().with_service_name(settings.service_name.clone()).build();
+    let builder = SdkTracerProvider::builder().with_resource(resource);
+
+    let Some(endpoint) = &settings.otlp_endpoint else {
+        return Ok(builder.build());
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .with_timeout(settings.export_timeout)
+        .build()?;
+
+    Ok(builder.with_batch_exporter(exporter).build())
+}
+
+// The W3C trace context of an incoming request, empty when it has no valid traceparent header
+pub fn extract_trace_context(headers: &HeaderMap) -> Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+// traceparent and tracestate headers continuing the current span's trace, for outbound calls
+pub fn trace_context_headers() -> reqwest::header::HeaderMap {
+    let mut headers = reqwest::header::HeaderMap::new();
+    let context = tracing::Span::current().context();
+    TraceContextPropagator::new().inject_context(&context, &mut HeaderInjector(&mut headers));
+    headers
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// reqwest is still on http 0.2, so its header map is a different type to axum's
+struct HeaderInjector<'a>(&'a mut reqwest::header::HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use opentelemetry::trace::TracerProvider;
+    use tracing_subscriber::prelude::*;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    fn settings(otlp_endpoint: Option<String>) -> TelemetrySettings {
+        TelemetrySettings { otlp_endpoint, ..TelemetrySettings::default() }
+    }
+
+    fn subscriber(provider: &SdkTracerProvider) -> impl tracing::Subscriber {
+        tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")))
+    }
+
+    #[test]
+    fn test_continues_incoming_trace() {
+        let provider = tracer_provider(&settings(None)).unwrap();
+
+        let mut incoming = HeaderMap::new();
+        incoming.insert("traceparent", format!("00-{}-00f067aa0ba902b7-01", TRACE_ID).parse().unwrap());
+
+        let outgoing = tracing::subscriber::with_default(subscriber(&provider), || {
+            let span = tracing::info_span!("request");
+            span.set_parent(extract_trace_context(&incoming));
+            let _entered = span.enter();
+            trace_context_headers()
+        });
+
+        let traceparent = outgoing.get("traceparent").unwrap().to_str().unwrap();
+        let parts: Vec<&str> = traceparent.split('-').collect();
+        assert_eq!(parts[1], TRACE_ID);
+        // The outbound call's parent is our span, not the caller's
+        assert_ne!(parts[2], "00f067aa0ba902b7");
+    }
+
+    #[test]
+    fn test_starts_a_trace_without_traceparent() {
+        let provider = tracer_provider(&settings(None)).unwrap();
+
+        let outgoing = tracing::subscriber::with_default(subscriber(&provider), || {
+            let span = tracing::info_span!("request");
+            span.set_parent(extract_trace_context(&HeaderMap::new()));
+            let _entered = span.enter();
+            trace_context_headers()
+        });
+
+        assert!(outgoing.contains_key("traceparent"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_exports_spans_to_collector() {
+        let collector = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/traces"))
+            .and(header("content-type", "application/x-protobuf"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1..)
+            .mount(&collector)
+            .await;
+
+        let provider = tracer_provider(&TelemetrySettings {
+            export_timeout: Duration::from_secs(2),
+            ..settings(Some(collector.uri()))
+        })
+        .unwrap();
+
+        tracing::subscriber::with_default(subscriber(&provider), || {
+            tracing::info_span!("request").in_scope(|| tracing::info!("handled"));
+        });
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap()
+            .unwrap();
+
+        collector.verify().await;
+    }
+}
@@ -1,72 +1,165 @@
-use std::sync::Arc;
 use std::time::Duration;
-use std::path::Path;
-use std::fs::File;
 
-use axum::{body::Body, extract::Request, response::Response};
+use axum::{body::Body, extract::Request, http::HeaderValue, middleware::Next, response::Response};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_sdk::trace::SdkTracerProvider;
 use tracing::{Level, Span};
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
 use tracing_error::ErrorLayer;
-use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::prelude::*;
-use tracing_subscriber::{fmt, EnvFilter};
+use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
 
-// file log 
-pub fn init_tracing(file_path: &str) -> Result<()> {
-    let log_file = match Path::new(file_path).exists() {
-        true => File::open(file_path)?,
-        false => File::create(file_path)?
-    };
-    let file_writer = Arc::new(log_file);
-    let make_writer = BoxMakeWriter::new(file_writer);
+use super::{
+    constants::REQUEST_ID_HEADER,
+    rolling_file::RollingFile,
+    settings::{LogFormat, LogSinkKind, LogSinkSettings, LoggingSettings, TelemetrySettings},
+    telemetry::{extract_trace_context, tracer_provider},
+};
+
+// Keeps tracing set up while alive, dropping it flushes the spans not exported yet and the log
+// lines still queued for the non-blocking writers
+pub struct TracingGuard {
+    tracer_provider: SdkTracerProvider,
+    _log_writers: Vec<WorkerGuard>,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            eprintln!("Failed to flush traces: {}", e);
+        }
+    }
+}
 
-    let file_layer = fmt::Layer::default()
-        .with_writer(make_writer) // Send logs to the file
-        .with_target(false); // Optional: disable logging module paths
+type SinkLayer = Box<dyn Layer<Registry> + Send + Sync>;
 
+// Logs to every configured sink, each written from its own thread so requests never wait on it
+pub fn init_tracing(logging: &LoggingSettings, telemetry: &TelemetrySettings) -> Result<TracingGuard> {
+    let mut sink_layers = Vec::new();
+    let mut log_writers = Vec::new();
+    for sink in &logging.sinks {
+        let (writer, guard) = match sink.kind {
+            LogSinkKind::Stdout => tracing_appender::non_blocking(std::io::stdout()),
+            LogSinkKind::File => {
+                let path = sink.path.as_deref().ok_or_else(|| eyre!("File log sinks need a path"))?;
+                tracing_appender::non_blocking(RollingFile::open(path, sink.rotation)?)
+            }
+        };
+        sink_layers.push(sink_layer(sink, writer));
+        log_writers.push(guard);
+    }
+
+    // RUST_LOG takes precedence over the configured level
     let filter_layer = EnvFilter::try_from_default_env()
-        .or_else(|_| EnvFilter::try_new("info"))?;
+        .or_else(|_| EnvFilter::try_new(&logging.level))?;
+
+    let tracer_provider = tracer_provider(telemetry)?;
+    let otel_layer = tracing_opentelemetry::layer()
+        .with_tracer(tracer_provider.tracer(telemetry.service_name.clone()));
 
     tracing_subscriber::registry()
+        .with(sink_layers) // Add a formatting layer per sink
         .with(filter_layer) // Add the filter layer to control log verbosity
-        .with(file_layer) // Add the formatting layer for compact log output
+        .with(otel_layer) // Add the OpenTelemetry layer to carry trace context and export spans
         .with(ErrorLayer::default()) // Add the error layer to capture error contexts
         .init(); // Initialize the tracing subscriber
 
-    Ok(())
+    Ok(TracingGuard { tracer_provider, _log_writers: log_writers })
 }
 
-// stdout for logging
-// pub fn init_tracing() -> Result<()> {
-//     // Create a formatting layer for tracing output with a compact format
-//     let fmt_layer = fmt::layer().compact();
-    
-//     // Create a filter layer to control the verbosity of logs
-//     // Try to get the filter configuration from the environment variables
-//     // If it fails, default to the "info" log level
-//     let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
-
-//     // Build the tracing subscriber registry with the formatting layer,
-//     // the filter layer, and the error layer for enhanced error reporting
-//     tracing_subscriber::registry()
-//         .with(filter_layer) // Add the filter layer to control log verbosity
-//         .with(fmt_layer) // Add the formatting layer for compact log output
-//         .with(ErrorLayer::default()) // Add the error layer to capture error contexts
-//         .init(); // Initialize the tracing subscriber
-
-//     Ok(())
-// }
+fn sink_layer(sink: &LogSinkSettings, writer: NonBlocking) -> SinkLayer {
+    let layer = fmt::layer()
+        .with_writer(writer)
+        .with_target(false) // Optional: disable logging module paths
+        .with_ansi(sink.kind == LogSinkKind::Stdout); // Colours would end up as escape codes in files
+
+    match sink.format {
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+// Identifies a request in the logs, the audit log and the response. Taken from the caller's
+// X-Request-Id header when it is valid, so requests can be followed from app-service and support
+// tickets matched to logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+
+    // Up to 128 letters, digits, '-', '_' and '.', anything else could break log lines or headers
+    pub fn parse(input: &str) -> Option<Self> {
+        let valid = !input.is_empty()
+            && input.len() <= MAX_REQUEST_ID_LENGTH
+            && input.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        valid.then(|| Self(input.to_owned()))
+    }
+}
+
+impl AsRef<str> for RequestId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: RequestId;
+}
+
+// Id of the request being handled, used for error responses which are built without access to
+// the request
+pub fn request_id() -> Option<RequestId> {
+    REQUEST_ID.try_with(|request_id| request_id.clone()).ok()
+}
+
+// Set the request id before the trace layer runs so that handlers can see it too, and echo it
+// on every response
+pub async fn set_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(RequestId::parse)
+        .unwrap_or_else(RequestId::generate);
+    request.extensions_mut().insert(request_id.clone());
+
+    let mut response = REQUEST_ID.scope(request_id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(request_id.as_ref()) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
 
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::new_v4();
-    tracing::span!(
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .cloned()
+        .unwrap_or_else(RequestId::generate);
+    let span = tracing::span!(
         Level::INFO,
         "[REQUEST]",
         method = tracing::field::display(request.method()),
         uri = tracing::field::display(request.uri()),
         version = tracing::field::debug(request.version()),
         request_id = tracing::field::display(request_id),
-    )
+    );
+    // Continue the caller's trace when it sent a traceparent header
+    span.set_parent(extract_trace_context(request.headers()));
+    span
 }
 
 pub fn on_request(_request: &Request<Body>, _span: &Span) {
@@ -96,4 +189,20 @@ pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
             )
         }
     };
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_request_ids() {
+        assert!(RequestId::parse("3f0c2a9e-8d51-4b6e-9a57-1c2d3e4f5a6b").is_some());
+        assert!(RequestId::parse("support_ticket.42").is_some());
+        assert!(RequestId::parse(&"a".repeat(MAX_REQUEST_ID_LENGTH)).is_some());
+
+        for invalid in ["", "with space", "line\nbreak", "<script>", "ünïcode"] {
+            assert!(RequestId::parse(invalid).is_none(), "{:?}", invalid);
+        }
+        assert!(RequestId::parse(&"a".repeat(MAX_REQUEST_ID_LENGTH + 1)).is_none());
+    }
+}
@@ -0,0 +1,270 @@
+use auth_service::{
+    domain::{
+        data_stores::{AuditEventType, AuditOutcome},
+        email::Email,
+        user::UserRole,
+    },
+    routes::AuditLogResponse,
+    ErrorResponse,
+};
+use chrono::{Duration, Utc};
+use secrecy::Secret;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_200_with_own_events() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrong-password",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // -------------------------------------------
+
+    let response = app.get_audit_log(&[]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = response
+        .json::<AuditLogResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditLogResponse")
+        .events;
+
+    let recorded: Vec<_> = events
+        .iter()
+        .map(|e| (e.event_type, e.outcome))
+        .collect();
+
+    // Newest first
+    assert_eq!(
+        recorded,
+        vec![
+            (AuditEventType::Login, AuditOutcome::Success),
+            (AuditEventType::Login, AuditOutcome::Failure),
+            (AuditEventType::Signup, AuditOutcome::Success),
+        ]
+    );
+
+    assert!(events.iter().all(|e| e.actor.as_deref() == Some(random_email.as_str())));
+    assert!(events.iter().all(|e| e.ip.as_deref() == Some("127.0.0.1")));
+    assert!(events.iter().all(|e| e.request_id.is_some()));
+    assert_eq!(events[1].detail.as_deref(), Some("Incorrect credentials"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_with_own_events_if_email_typed_in_other_case() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email.to_uppercase(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_audit_log(&[]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = response
+        .json::<AuditLogResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditLogResponse")
+        .events;
+
+    let recorded: Vec<_> = events
+        .iter()
+        .map(|e| (e.event_type, e.outcome))
+        .collect();
+
+    assert_eq!(
+        recorded,
+        vec![
+            (AuditEventType::Login, AuditOutcome::Success),
+            (AuditEventType::Signup, AuditOutcome::Success),
+        ]
+    );
+
+    // Recorded in canonical form, which lower-cases the domain
+    let (local_part, _) = random_email.split_once('@').unwrap();
+    let expected = format!("{}@example.com", local_part.to_uppercase());
+    assert_eq!(events[0].actor.as_deref(), Some(expected.as_str()));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_filter_events_by_time_range() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let future = (Utc::now() + Duration::minutes(5)).to_rfc3339();
+    let past = (Utc::now() - Duration::minutes(5)).to_rfc3339();
+
+    let response = app.get_audit_log(&[("from", future.as_str())]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = response
+        .json::<AuditLogResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditLogResponse")
+        .events;
+
+    assert!(events.is_empty());
+
+    let response = app
+        .get_audit_log(&[("from", past.as_str()), ("to", future.as_str()), ("limit", "1")])
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = response
+        .json::<AuditLogResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditLogResponse")
+        .events;
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type, AuditEventType::Login);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_if_admin_queries_other_user() {
+    let mut app = TestApp::new().await;
+
+    let other_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": other_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let admin_email = get_random_email();
+    signup_and_login(&app, &admin_email).await;
+
+    app.user_store
+        .write()
+        .await
+        .update_role(&Email::parse(Secret::new(admin_email)).unwrap(), UserRole::Admin)
+        .await
+        .expect("Failed to promote user to admin");
+
+    let response = app.get_audit_log(&[("email", other_email.as_str())]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = response
+        .json::<AuditLogResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditLogResponse")
+        .events;
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type, AuditEventType::Signup);
+    assert_eq!(events[0].actor.as_deref(), Some(other_email.as_str()));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_user_queries_other_user() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let other_email = get_random_email();
+    let response = app.get_audit_log(&[("email", other_email.as_str())]).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Insufficient permissions".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_audit_log(&[]).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+
+    app.clean_up().await;
+}
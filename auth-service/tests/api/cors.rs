@@ -0,0 +1,87 @@
+use auth_service::utils::constants::test;
+use reqwest::header::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+};
+
+use crate::helpers::TestApp;
+
+#[tokio::test]
+async fn should_allow_preflight_from_allowed_origin() {
+    let mut app = TestApp::new().await;
+
+    let response = app.options_preflight("/login", test::cors::ALLOWED_ORIGIN, "POST").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let headers = response.headers();
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], test::cors::ALLOWED_ORIGIN);
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "GET,POST");
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_HEADERS], "content-type");
+    assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], test::cors::MAX_AGE_SECONDS.to_string().as_str());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_allow_preflight_from_allowed_subdomain() {
+    let mut app = TestApp::new().await;
+
+    for origin in ["https://app.example.com", "https://eu.app.example.com"] {
+        let response = app.options_preflight("/login", origin, "POST").await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], origin);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_allow_preflight_from_disallowed_origin() {
+    let mut app = TestApp::new().await;
+
+    let origins = [
+        "http://localhost:8001",
+        "https://localhost:8000",
+        "https://example.com",
+        "http://app.example.com",
+        "https://app.example.com.evil.com",
+        "https://evil.com",
+    ];
+
+    for origin in origins {
+        let response = app.options_preflight("/login", origin, "POST").await;
+        assert!(
+            response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none(),
+            "{} should not be allowed",
+            origin
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_allow_origin_on_actual_requests() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/", &app.address))
+        .header("Origin", "https://app.example.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.com");
+
+    let response = app
+        .http_client
+        .get(format!("{}/", &app.address))
+        .header("Origin", "https://evil.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+    app.clean_up().await;
+}
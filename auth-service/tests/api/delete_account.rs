@@ -1,47 +1,97 @@
-// use auth_service::utils::constants::JWT_COOKIE_NAME;
-
-// use crate::helpers::TestApp;
-
-// #[tokio::test]
-// async fn should_return_200_if_valid_input() {
-//     let app = TestApp::new().await;
-
-//     // signup
-//     let signup_body = serde_json::json!({
-//         "email": "test@email.com",
-//         "password": "password123",
-//         "requires2FA": false
-//     });
-//     let response = app.post_signup(&signup_body).await;
-//     assert_eq!(response.status().as_u16(), 201);
-
-//     // login
-//     let login_body = serde_json::json!({
-//         "email": "test@email.com",
-//         "password": "password123",
-//     });
-//     let response = app.post_login(&login_body).await;
-//     assert_eq!(response.status().as_u16(), 200);
-
-//     // delete account
-//     let auth_cookie = response
-//         .cookies()
-//         .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
-//         .expect("No auth cookie found");
-
-//     let body = serde_json::json!({
-//         "token": auth_cookie.value(),
-//     });
-
-//     let response = app.post_delete_account(&body).await;
-//     assert_eq!(response.status().as_u16(), 200);
-
-//     // signup again to check user was deleted
-//     let signup_body = serde_json::json!({
-//         "email": "test@email.com",
-//         "password": "password123",
-//         "requires2FA": false
-//     });
-//     let response = app.post_signup(&signup_body).await;
-//     assert_eq!(response.status().as_u16(), 201);
-// }
\ No newline at end of file
+use auth_service::{routes::DeleteResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_200_if_valid_input() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    // signup
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // login
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // delete account
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let body = serde_json::json!({
+        "token": auth_cookie.value(),
+    });
+
+    let response = app.post_delete_account(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        response
+            .json::<DeleteResponse>()
+            .await
+            .expect("Could not deserialize response body to DeleteResponse"),
+        DeleteResponse {
+            message: "User deleted successfully!".to_owned(),
+        }
+    );
+
+    // the token is no longer accepted once the account is gone
+    let response = app.post_delete_account(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // signup again to check user was deleted
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let body = serde_json::json!({
+        "token": "invalid",
+    });
+
+    let response = app.post_delete_account(&body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let body = serde_json::json!({
+        "email": get_random_email(),
+    });
+
+    let response = app.post_delete_account(&body).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
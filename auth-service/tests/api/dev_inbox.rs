@@ -0,0 +1,107 @@
+use auth_service::{
+    domain::email::Email,
+    routes::{DevEmailResponse, DevInboxResponse},
+    ErrorResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_show_login_codes_in_dev_inbox() {
+    let mut app = TestApp::new_with_dev_mailbox().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    assert_eq!(app.deliver_emails().await, 1);
+
+    let response = app.get_dev_inbox(&[("email", &random_email)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let emails = response
+        .json::<DevInboxResponse>()
+        .await
+        .expect("Could not deserialize response body to DevInboxResponse")
+        .emails;
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].recipient, random_email);
+    assert!(emails[0].subject.ends_with("login code"));
+
+    let response = app.get_dev_inbox_email(&emails[0].id.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let email = response
+        .json::<DevEmailResponse>()
+        .await
+        .expect("Could not deserialize response body to DevEmailResponse");
+
+    let code_tuple = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(Secret::new(random_email.clone())).unwrap())
+        .await
+        .unwrap();
+
+    let code = code_tuple.1.as_ref().expose_secret();
+    assert!(email.text_body.contains(code.as_str()));
+    assert!(email.html_body.contains(code.as_str()));
+
+    let response = app.get_dev_inbox(&[("email", &get_random_email())]).await;
+    let emails = response.json::<DevInboxResponse>().await.unwrap().emails;
+    assert!(emails.is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_dev_inbox_email() {
+    let mut app = TestApp::new_with_dev_mailbox().await;
+
+    let response = app.get_dev_inbox_email(&Uuid::new_v4().to_string()).await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email not found".to_owned()
+    );
+
+    let response = app.get_dev_inbox(&[("email", "not-an-email")]).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_route_dev_inbox_without_dev_mailbox() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_dev_inbox(&[]).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.get_dev_inbox_email(&Uuid::new_v4().to_string()).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,178 @@
+use auth_service::{
+    domain::{data_stores::DeliveryStatus, email::Email, user::UserRole},
+    routes::{EmailOutboxResponse, OutboxEmailStatus},
+    utils::constants::test,
+};
+use secrecy::Secret;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123"
+    });
+
+    app.post_login(&login_body).await
+}
+
+async fn signup_admin_and_login(app: &TestApp) {
+    let admin_email = get_random_email();
+    signup(app, &admin_email, false).await;
+
+    app.user_store
+        .write()
+        .await
+        .update_role(&Email::parse(Secret::new(admin_email.clone())).unwrap(), UserRole::Admin)
+        .await
+        .expect("Failed to promote user to admin");
+
+    let response = login(app, &admin_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn outbox_email(app: &TestApp, recipient: &str) -> OutboxEmailStatus {
+    let response = app.get_email_outbox(&[("email", recipient)]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let mut emails = response
+        .json::<EmailOutboxResponse>()
+        .await
+        .expect("Could not deserialize response body to EmailOutboxResponse")
+        .emails;
+    assert_eq!(emails.len(), 1);
+
+    emails.remove(0)
+}
+
+// Delivers until the email is no longer pending, waiting out the back-off in between
+async fn deliver_until_settled(app: &TestApp, recipient: &str) -> OutboxEmailStatus {
+    for _ in 0..50 {
+        app.deliver_emails().await;
+
+        let email = outbox_email(app, recipient).await;
+        if email.status != DeliveryStatus::Pending {
+            return email;
+        }
+
+        tokio::time::sleep(test::email_outbox::INITIAL_BACKOFF).await;
+    }
+
+    panic!("Email to {} was never settled", recipient);
+}
+
+#[tokio::test]
+async fn should_not_fail_login_when_email_provider_is_down() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(u64::from(test::email_outbox::MAX_ATTEMPTS))
+        .mount(&app.email_server)
+        .await;
+
+    signup_admin_and_login(&app).await;
+
+    let user_email = get_random_email();
+    signup(&app, &user_email, true).await;
+
+    let response = login(&app, &user_email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    assert_eq!(app.deliver_emails().await, 1);
+
+    let email = outbox_email(&app, &user_email).await;
+    assert_eq!(email.status, DeliveryStatus::Pending);
+    assert_eq!(email.attempts, 1);
+    assert!(email.last_error.is_some());
+    assert!(email.subject.ends_with("login code"));
+
+    let email = deliver_until_settled(&app, &user_email).await;
+    assert_eq!(email.status, DeliveryStatus::Dead);
+    assert_eq!(email.attempts as u32, test::email_outbox::MAX_ATTEMPTS);
+    assert_eq!(email.sent_at, None);
+
+    let response = app.get_email_outbox(&[("status", "dead")]).await;
+    let dead = response.json::<EmailOutboxResponse>().await.unwrap().emails;
+    assert_eq!(dead, vec![email]);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_retry_failed_emails_until_delivered() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    signup_admin_and_login(&app).await;
+
+    let user_email = get_random_email();
+    signup(&app, &user_email, true).await;
+
+    let response = login(&app, &user_email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let email = deliver_until_settled(&app, &user_email).await;
+    assert_eq!(email.status, DeliveryStatus::Sent);
+    assert_eq!(email.attempts, 2);
+    assert_eq!(email.last_error, None);
+    assert!(email.sent_at.is_some());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_show_outbox_to_admins() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_email_outbox(&[]).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let user_email = get_random_email();
+    signup(&app, &user_email, false).await;
+    let response = login(&app, &user_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_email_outbox(&[("email", &user_email)]).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    signup_admin_and_login(&app).await;
+
+    let response = app.get_email_outbox(&[("status", "queued")]).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_email_outbox(&[("email", "not-an-email")]).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
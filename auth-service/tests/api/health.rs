@@ -0,0 +1,105 @@
+use auth_service::routes::{DependencyHealth, HealthStatus, LivenessResponse, ReadinessResponse};
+use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
+
+use crate::helpers::TestApp;
+
+fn dependency<'a>(response: &'a ReadinessResponse, name: &str) -> &'a DependencyHealth {
+    response
+        .dependencies
+        .iter()
+        .find(|dependency| dependency.name == name)
+        .unwrap_or_else(|| panic!("No {} dependency in readiness response", name))
+}
+
+#[tokio::test]
+async fn should_return_200_when_live() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health_live().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<LivenessResponse>()
+        .await
+        .expect("Could not deserialize response body to LivenessResponse");
+    assert_eq!(body.status, HealthStatus::Up);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_when_dependencies_are_up() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health_ready().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<ReadinessResponse>()
+        .await
+        .expect("Could not deserialize response body to ReadinessResponse");
+    assert_eq!(body.status, HealthStatus::Up);
+    assert_eq!(body.dependencies.len(), 3);
+
+    for name in ["postgres", "redis_banned_tokens", "redis_two_fa_codes"] {
+        let dependency = dependency(&body, name);
+        assert_eq!(dependency.status, HealthStatus::Up);
+        assert!(dependency.critical);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_503_when_postgres_is_down() {
+    let mut app = TestApp::new().await;
+
+    app.pg_pool.close().await;
+
+    let response = app.get_health_ready().await;
+    assert_eq!(response.status().as_u16(), 503);
+
+    let body = response
+        .json::<ReadinessResponse>()
+        .await
+        .expect("Could not deserialize response body to ReadinessResponse");
+    assert_eq!(body.status, HealthStatus::Down);
+    assert_eq!(dependency(&body, "postgres").status, HealthStatus::Down);
+    assert_eq!(dependency(&body, "redis_banned_tokens").status, HealthStatus::Up);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_report_email_provider_without_failing_readiness() {
+    let mut app = TestApp::new_with_email_health_check().await;
+
+    Mock::given(path("/server"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = app.get_health_ready().await.json::<ReadinessResponse>().await.unwrap();
+    assert_eq!(body.status, HealthStatus::Up);
+    let email = dependency(&body, "email");
+    assert_eq!(email.status, HealthStatus::Up);
+    assert!(!email.critical);
+
+    // Postmark is down now
+    Mock::given(path("/server"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.get_health_ready().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response.json::<ReadinessResponse>().await.unwrap();
+    assert_eq!(body.status, HealthStatus::Degraded);
+    assert_eq!(dependency(&body, "email").status, HealthStatus::Down);
+
+    app.clean_up().await;
+}
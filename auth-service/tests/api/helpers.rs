@@ -1,64 +1,132 @@
-use std::{str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
 use reqwest::{cookie::Jar, Client};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{postgres::{PgConnectOptions, PgPoolOptions}, Connection, Executor, PgConnection, PgPool};
-use tokio::sync::RwLock;
+use tokio::{sync::RwLock, task::JoinHandle};
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::{MockServer, Request};
 
 use auth_service::{
-    app_state::app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType}, 
-    domain::email::Email, 
-    get_postgres_pool, get_redis_client, 
-    services::data_stores::{HashmapTwoFACodeStore, MockEmailClient, PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore, RedisTwoFACodeStore}, utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME}, Application 
+    app_state::app_state::{AppState, BannedTokenStoreType, DevMailboxType, EmailClientType, EmailOutboxStoreType, MetricsType, SettingsType, TwoFACodeStoreType, UserStoreType}, 
+    domain::{email::Email, email_client::EmailProvider, email_domain_policy::EmailDomainPolicy, password::Password}, 
+    get_postgres_pool, get_redis_connection, 
+    services::{
+        data_stores::{DevEmailClient, DevMailbox, HashmapTwoFACodeStore, HashsetBreachedPasswordStore, MockEmailClient, PostgresEmailOutboxStore, PostgresInvitationStore, PasswordHashParams, PostgresAuditLogStore, PostgresUserStore, PostmarkEmailClient, RedisBannedTokenStore, RedisTwoFACodeStore},
+        email_outbox_worker::EmailOutboxWorker,
+        webhook_dispatcher::{WebhookDispatcher, WebhookSubscription},
+    },
+    utils::{constants::test, metrics::Metrics, retry::RetryPolicy, settings::Settings, shutdown::ShutdownHandle}, Application 
 };
 
+// Seeded into the breached password corpus of every test app
+pub const BREACHED_PASSWORD: &str = "breachedpassword";
+
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
+    pub user_store: UserStoreType,
     pub token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub settings: SettingsType,
+    pub pg_pool: PgPool,
     pub email_server: MockServer,
+    pub email_outbox_worker: EmailOutboxWorker,
+    pub webhook_server: MockServer,
+    // Triggers a graceful shutdown of the server, which then completes `server`
+    pub shutdown: ShutdownHandle,
+    pub server: JoinHandle<Result<(), std::io::Error>>,
     pub db_name: String,
     pub clean_up_called: bool,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::build(false, |_| {}).await
+    }
+
+    pub async fn new_invite_only() -> Self {
+        Self::build(false, |settings| settings.signup.invite_only = true).await
+    }
+
+    // Emails go to the dev mailbox instead of the mock Postmark server
+    pub async fn new_with_dev_mailbox() -> Self {
+        Self::build(true, |_| {}).await
+    }
+
+    // /health/ready also probes the mock Postmark server
+    pub async fn new_with_email_health_check() -> Self {
+        Self::build(false, |settings| settings.health.check_email_provider = true).await
+    }
+
+    pub async fn new_with_settings(configure: impl FnOnce(&mut Settings)) -> Self {
+        Self::build(false, configure).await
+    }
+
+    async fn build(with_dev_mailbox: bool, configure: impl FnOnce(&mut Settings)) -> Self {
         // let user_store = HashmapUserStore::new();
         // let user_store = Arc::new(RwLock::new(user_store));
+        let settings = Arc::new(configure_settings(configure));
         let db_name = Uuid::new_v4().to_string();
-        let pg_pool = configure_postgresql(&db_name).await;
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        let pg_pool = configure_postgresql(&settings, &db_name).await;
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone(), PasswordHashParams::default())));
+        let audit_log_store = Arc::new(RwLock::new(PostgresAuditLogStore::new(pg_pool.clone())));
+        let invitation_store = Arc::new(RwLock::new(PostgresInvitationStore::new(pg_pool.clone())));
+        let email_outbox = Arc::new(RwLock::new(PostgresEmailOutboxStore::new(pg_pool.clone())));
         // let token_store = HashsetBannedTokenStore::new();
         // let token_store = Arc::new(RwLock::new(token_store.clone()));
-        let redis_con = configure_redis();
+        let redis_con = configure_redis(&settings);
         let redis_con = Arc::new(RwLock::new(redis_con));
-        let token_store = RedisBannedTokenStore::new(redis_con);
+        let token_store = RedisBannedTokenStore::new(redis_con, settings.auth.token_ttl);
         let token_store = Arc::new(RwLock::new(token_store));
         // let two_fa_code_store = HashmapTwoFACodeStore::default();
         // let two_fa_code_store = Arc::new(RwLock::new(two_fa_code_store));
-        let redis_con = configure_redis();
-        let two_fa_code_store = RedisTwoFACodeStore::new(Arc::new(RwLock::new(redis_con)));
+        let redis_con = configure_redis(&settings);
+        let two_fa_code_store = RedisTwoFACodeStore::new(Arc::new(RwLock::new(redis_con)), settings.two_fa.code_ttl);
         let two_fa_code_store = Arc::new(RwLock::new(two_fa_code_store));
         // let email_client = MockEmailClient;
         // let email_client = Arc::new(RwLock::new(email_client));
         let email_server = MockServer::start().await;
         let base_url = email_server.uri(); 
-        let email_client = Arc::new(RwLock::new(configure_postmark_email_client(base_url)));
-
-        let app_state = AppState::new(user_store, token_store.clone(), two_fa_code_store.clone(), email_client);
+        let dev_mailbox = with_dev_mailbox.then(|| Arc::new(RwLock::new(DevMailbox::default())));
+        let email_client: EmailClientType = match &dev_mailbox {
+            Some(dev_mailbox) => Arc::new(RwLock::new(configure_dev_email_client(dev_mailbox.clone()))),
+            None => Arc::new(RwLock::new(configure_postmark_email_client(base_url))),
+        };
+        let metrics = Arc::new(Metrics::new().expect("Failed to register metrics"));
+        // Not spawned, tests deliver emails explicitly with `deliver_emails`
+        let email_outbox_worker = configure_email_outbox_worker(email_outbox.clone(), email_client.clone(), metrics.clone());
+        let webhook_server = MockServer::start().await;
+        let webhook_dispatcher = Arc::new(configure_webhook_dispatcher(webhook_server.uri()));
+
+        let breached_password_store = Arc::new(RwLock::new(configure_breached_password_store()));
+        let email_domain_policy = Arc::new(configure_email_domain_policy());
+
+        let app_state = AppState::new(
+            user_store.clone(),
+            token_store.clone(),
+            two_fa_code_store.clone(),
+            email_client,
+            audit_log_store,
+            webhook_dispatcher,
+            breached_password_store,
+            email_domain_policy,
+            invitation_store,
+            email_outbox.clone(),
+            dev_mailbox,
+            settings.clone(),
+            metrics,
+        );
         
-        let app = Application::build(app_state, test::APP_ADDRESS)
+        let app = Application::build(app_state, &settings.application.address)
             .await
             .expect("Failed to build app");
 
         let address = format!("http://{}", app.address.clone());
 
-        #[allow(clippy::let_underscore_future)]
-        let _ = tokio::spawn(app.run());
+        let shutdown = app.shutdown_handle();
+        let server = tokio::spawn(app.run());
 
         let cookie_jar = Arc::new(Jar::default());
         let http_client = reqwest::Client::builder()
@@ -66,7 +134,7 @@ impl TestApp {
             .build()
             .unwrap();
 
-        Self { address, cookie_jar, http_client, token_store, two_fa_code_store, email_server, db_name, clean_up_called: false }
+        Self { address, cookie_jar, http_client, user_store, token_store, two_fa_code_store, settings, pg_pool, email_server, email_outbox_worker, webhook_server, shutdown, server, db_name, clean_up_called: false }
     }
 
     pub async fn get_root(&self) -> reqwest::Response {
@@ -145,23 +213,230 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_create_invitation<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/create-invitation", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_revoke_invitation<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/revoke-invitation", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_create_organisation<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/create-organisation", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_set_membership<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/set-membership", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_set_locale<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/set-locale", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_audit_log(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/audit-log", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // A CORS preflight for a cross-origin request to `path`
+    pub async fn options_preflight(&self, path: &str, origin: &str, method: &str) -> reqwest::Response {
+        self.http_client
+            .request(reqwest::Method::OPTIONS, format!("{}{}", &self.address, path))
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", method)
+            .header("Access-Control-Request-Headers", "content-type")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_health_live(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/health/live", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_health_ready(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/health/ready", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/metrics", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // The rendered metrics, for asserting on individual series
+    pub async fn metrics(&self) -> String {
+        self.get_metrics()
+            .await
+            .text()
+            .await
+            .expect("Failed to read metrics")
+    }
+
+    pub async fn get_dev_inbox(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/dev/inbox", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dev_inbox_email(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/dev/inbox/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_email_outbox(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/email-outbox", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Runs one pass of the outbox worker, returns how many emails it attempted
+    pub async fn deliver_emails(&self) -> usize {
+        self.email_outbox_worker
+            .run_once()
+            .await
+            .expect("Failed to process the email outbox")
+    }
+
+    // Webhooks are delivered in the background, so poll until they arrive
+    pub async fn wait_for_webhooks(&self, count: usize) -> Vec<Request> {
+        for _ in 0..50 {
+            let requests = self.webhook_server
+                .received_requests()
+                .await
+                .expect("Request recording is disabled");
+
+            if requests.len() >= count {
+                return requests;
+            }
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        panic!("Expected {} webhook deliveries", count);
+    }
+
     pub async fn clean_up(&mut self) {
         if self.clean_up_called {
             return;
         }
 
-        delete_database(&self.db_name).await;
+        delete_database(&self.settings, &self.db_name).await;
 
         self.clean_up_called = true;
     }
 }
 
+// A database without any migrations applied, for tests that run them one at a time
+pub struct TestDatabase {
+    pub pg_pool: PgPool,
+    settings: Settings,
+    db_name: String,
+}
+
+impl TestDatabase {
+    pub async fn new() -> Self {
+        let settings = configure_settings(|_| {});
+        let db_name = Uuid::new_v4().to_string();
+        let pg_pool = create_database(settings.database.url.expose_secret(), &db_name).await;
+
+        Self { pg_pool, settings, db_name }
+    }
+
+    pub async fn clean_up(self) {
+        self.pg_pool.close().await;
+        delete_database(&self.settings, &self.db_name).await;
+    }
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
-async fn configure_postgresql(db_name: &str) -> PgPool {
-    let postgresql_conn_url = DATABASE_URL.to_owned();
+// The database and Redis come from the environment like in production, the rest is pinned for tests
+fn configure_settings(configure: impl FnOnce(&mut Settings)) -> Settings {
+    dotenvy::dotenv().ok();
+
+    let mut settings = Settings::default()
+        .with_env(|name| std::env::var(name).ok())
+        .expect("Failed to read settings from the environment");
+
+    settings.application.address = test::APP_ADDRESS.to_owned();
+    settings.cors.allowed_origins = vec![test::cors::ALLOWED_ORIGIN.to_owned(), test::cors::ALLOWED_SUBDOMAINS.to_owned()];
+    settings.cors.max_age = Duration::from_secs(test::cors::MAX_AGE_SECONDS);
+    settings.email.providers = vec![EmailProvider::Postmark];
+    settings.email.sender = test::email_client::SENDER.to_owned();
+    settings.email.postmark.auth_token = Some(Secret::new("auth_token".to_owned()));
+    configure(&mut settings);
+
+    settings.validated().expect("Invalid test settings")
+}
+
+async fn configure_postgresql(settings: &Settings, db_name: &str) -> PgPool {
+    let postgresql_conn_url = settings.database.url.to_owned();
 
     configure_database(&postgresql_conn_url.expose_secret(), &db_name).await;
 
@@ -173,6 +448,15 @@ async fn configure_postgresql(db_name: &str) -> PgPool {
 }
 
 async fn configure_database(db_conn_string: &str, db_name: &str) {
+    let connection = create_database(db_conn_string, db_name).await;
+
+    sqlx::migrate!()
+        .run(&connection)
+        .await
+        .expect("Failed to migrate the database");
+}
+
+async fn create_database(db_conn_string: &str, db_name: &str) -> PgPool {
     let connection = PgPoolOptions::new()
         .connect(db_conn_string)
         .await
@@ -185,19 +469,14 @@ async fn configure_database(db_conn_string: &str, db_name: &str) {
 
     let db_conn_string = format!("{}/{}", db_conn_string, db_name);
 
-    let connection = PgPoolOptions::new()
+    PgPoolOptions::new()
         .connect(&db_conn_string)
         .await
-        .expect("Failed to create Postgres connection pool.");
-
-    sqlx::migrate!()
-        .run(&connection)
-        .await
-        .expect("Failed to migrate the database");
+        .expect("Failed to create Postgres connection pool.")
 }
 
-async fn delete_database(db_name: &str) {
-    let postgresql_conn_url = DATABASE_URL.expose_secret();
+async fn delete_database(settings: &Settings, db_name: &str) {
+    let postgresql_conn_url = settings.database.url.expose_secret();
 
     let connection_options = PgConnectOptions::from_str(postgresql_conn_url)
         .expect("Failed to parse PostgreSQL connection string");
@@ -212,10 +491,8 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database.");
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
+fn configure_redis(settings: &Settings) -> redis::Connection {
+    get_redis_connection(settings.redis.host_name.to_owned(), settings.health.timeout)
         .expect("Failed to get Redis connection")
 }
 
@@ -230,4 +507,56 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
         .expect("Failed to build HTTP client");
 
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
+}
+
+// Keeps the emails in memory only, tests have no use for the .eml files
+fn configure_dev_email_client(dev_mailbox: DevMailboxType) -> DevEmailClient {
+    let sender = Email::parse(Secret::new(test::email_client::SENDER.to_owned())).unwrap();
+
+    DevEmailClient::new(sender, dev_mailbox, None)
+}
+
+fn configure_email_outbox_worker(email_outbox: EmailOutboxStoreType, email_client: EmailClientType, metrics: MetricsType) -> EmailOutboxWorker {
+    let retry_policy = RetryPolicy {
+        max_attempts: test::email_outbox::MAX_ATTEMPTS,
+        initial_backoff: test::email_outbox::INITIAL_BACKOFF,
+        max_backoff: test::email_outbox::MAX_BACKOFF,
+    };
+
+    EmailOutboxWorker::new(email_outbox, email_client, retry_policy, test::email_client::TIMEOUT, metrics)
+}
+
+fn configure_webhook_dispatcher(base_url: String) -> WebhookDispatcher {
+    let subscription = WebhookSubscription {
+        url: format!("{}/webhooks", base_url),
+        secret: Secret::new(test::webhooks::SECRET.to_owned()),
+        events: vec![],
+    };
+
+    let retry_policy = RetryPolicy {
+        max_attempts: test::webhooks::MAX_ATTEMPTS,
+        initial_backoff: test::webhooks::INITIAL_BACKOFF,
+        max_backoff: test::webhooks::MAX_BACKOFF,
+    };
+
+    let http_client = Client::builder()
+        .timeout(test::webhooks::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    WebhookDispatcher::new(vec![subscription], retry_policy, http_client)
+}
+
+fn configure_breached_password_store() -> HashsetBreachedPasswordStore {
+    let mut store = HashsetBreachedPasswordStore::new();
+    store.add_password(&Password::parse_credential(Secret::new(BREACHED_PASSWORD.to_owned())).unwrap());
+    store
+}
+
+fn configure_email_domain_policy() -> EmailDomainPolicy {
+    EmailDomainPolicy {
+        denied_domains: vec![test::email_domains::DENIED.to_owned()],
+        disposable_domains: [test::email_domains::DISPOSABLE.to_owned()].into(),
+        ..Default::default()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,209 @@
+use auth_service::{
+    domain::{email::Email, password::Password, user::{User, UserRole}},
+    routes::{CreateInvitationResponse, RevokeInvitationResponse},
+    ErrorResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// In invite-only mode nobody can sign up without an invitation, so inviters are created directly
+async fn add_user_and_login(app: &TestApp, email: &str, role: UserRole) {
+    let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+    let password = Password::parse_credential(Secret::new("password123".to_owned())).unwrap();
+
+    let mut user_store = app.user_store.write().await;
+    user_store
+        .add_user(User::new(email.clone(), password, false))
+        .await
+        .expect("Failed to add user");
+    user_store
+        .update_role(&email, role)
+        .await
+        .expect("Failed to update role");
+    drop(user_store);
+
+    let login_body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+fn signup_body(email: &str, invite_code: &str) -> serde_json::Value {
+    serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false,
+        "inviteCode": invite_code,
+    })
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_return_403_if_invite_only_and_no_code() {
+    let mut app = TestApp::new_invite_only().await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Signup requires an invitation code");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_signup_with_emailed_invitation() {
+    let mut app = TestApp::new_invite_only().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    add_user_and_login(&app, &get_random_email(), UserRole::User).await;
+
+    let invitee = get_random_email();
+    let response = app.post_create_invitation(&serde_json::json!({ "email": invitee })).await;
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(app.deliver_emails().await, 1);
+
+    let invitation = response
+        .json::<CreateInvitationResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateInvitationResponse");
+    assert_eq!(invitation.max_uses, 1);
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    assert!(String::from_utf8_lossy(&requests[0].body).contains(&invitation.code));
+
+    // Bound to the invitee
+    let response = app.post_signup(&signup_body(&get_random_email(), &invitation.code)).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Invalid invitation code");
+
+    let response = app.post_signup(&signup_body(&invitee, &invitation.code)).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_stop_accepting_invitation_once_used_up() {
+    let mut app = TestApp::new_invite_only().await;
+
+    add_user_and_login(&app, &get_random_email(), UserRole::Admin).await;
+
+    let response = app.post_create_invitation(&serde_json::json!({ "maxUses": 2 })).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let invitation = response.json::<CreateInvitationResponse>().await.unwrap();
+
+    for _ in 0..2 {
+        let response = app.post_signup(&signup_body(&get_random_email(), &invitation.code)).await;
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    let response = app.post_signup(&signup_body(&get_random_email(), &invitation.code)).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_if_user_creates_multi_use_invitation() {
+    let mut app = TestApp::new().await;
+
+    add_user_and_login(&app, &get_random_email(), UserRole::User).await;
+
+    let response = app.post_create_invitation(&serde_json::json!({ "maxUses": 5 })).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Insufficient permissions");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    add_user_and_login(&app, &get_random_email(), UserRole::Admin).await;
+
+    let test_cases = [
+        serde_json::json!({ "maxUses": 0 }),
+        serde_json::json!({ "expiresInHours": 0 }),
+        serde_json::json!({ "expiresInHours": 10000 }),
+        serde_json::json!({ "email": "not-an-email" }),
+    ];
+
+    for test_case in test_cases {
+        let response = app.post_create_invitation(&test_case).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for input: {:?}", test_case);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_let_inviter_or_admin_revoke_invitation() {
+    let mut app = TestApp::new_invite_only().await;
+
+    add_user_and_login(&app, &get_random_email(), UserRole::User).await;
+
+    let response = app.post_create_invitation(&serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let invitation = response.json::<CreateInvitationResponse>().await.unwrap();
+
+    // Somebody else may not revoke it
+    add_user_and_login(&app, &get_random_email(), UserRole::User).await;
+
+    let response = app.post_revoke_invitation(&serde_json::json!({ "code": invitation.code })).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // An admin may
+    add_user_and_login(&app, &get_random_email(), UserRole::Admin).await;
+
+    let response = app.post_revoke_invitation(&serde_json::json!({ "code": invitation.code })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.json::<RevokeInvitationResponse>().await.unwrap(),
+        RevokeInvitationResponse { message: "Invitation revoked".to_owned() }
+    );
+
+    let response = app.post_signup(&signup_body(&get_random_email(), &invitation.code)).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Invalid invitation code");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_create_invitation(&serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_message(response).await, "Missing auth token");
+
+    app.clean_up().await;
+}
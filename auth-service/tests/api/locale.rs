@@ -0,0 +1,216 @@
+use auth_service::{
+    domain::{data_stores::AuditEventType, email::Email, locale::Locale},
+    routes::{AuditLogResponse, SetLocaleResponse},
+    ErrorResponse,
+};
+use secrecy::Secret;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn post_with_language<Body>(app: &TestApp, route: &str, body: &Body, accept_language: &str) -> reqwest::Response
+where
+    Body: serde::Serialize,
+{
+    app.http_client
+        .post(format!("{}/{}", &app.address, route))
+        .header("Accept-Language", accept_language)
+        .json(body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn stored_locale(app: &TestApp, email: &str) -> Option<Locale> {
+    app.user_store
+        .read()
+        .await
+        .get_user(&Email::parse(Secret::new(email.to_owned())).unwrap())
+        .await
+        .expect("Failed to get user")
+        .locale
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_localize_error_messages() {
+    let mut app = TestApp::new().await;
+
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+    });
+
+    let test_cases = [
+        ("de-DE, en;q=0.5", "Falsche Anmeldedaten"),
+        ("ja, es;q=0.8", "Credenciales incorrectas"),
+        ("fr", "Identifiants incorrects"),
+        // Unsupported languages fall back to English
+        ("ja", "Incorrect credentials"),
+    ];
+
+    for (accept_language, expected) in test_cases {
+        let response = post_with_language(&app, "login", &login_body, accept_language).await;
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(error_message(response).await, expected, "Failed for Accept-Language: {}", accept_language);
+    }
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "short",
+        "requires2FA": false
+    });
+
+    let response = post_with_language(&app, "signup", &signup_body, "de").await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_message(response).await, "Das Passwort muss mindestens 8 Zeichen lang sein");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_store_locale_at_signup() {
+    let mut app = TestApp::new().await;
+
+    // Negotiated from the request
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = post_with_language(&app, "signup", &signup_body, "fr-CH, fr;q=0.9").await;
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(stored_locale(&app, &email).await, Some(Locale::Fr));
+
+    // Chosen explicitly
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false,
+        "locale": "es"
+    });
+    let response = post_with_language(&app, "signup", &signup_body, "fr").await;
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(stored_locale(&app, &email).await, Some(Locale::Es));
+
+    // No preference at all
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(stored_locale(&app, &email).await, None);
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false,
+        "locale": "xx"
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_2fa_email_in_stored_locale() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true,
+        "locale": "de"
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // The stored preference wins over the language of the login request
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = post_with_language(&app, "login", &login_body, "fr").await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.deliver_emails().await, 1);
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let email_body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert!(email_body["Subject"].as_str().unwrap().starts_with("Dein Anmeldecode"));
+    assert!(email_body["HtmlBody"].as_str().unwrap().contains(r#"<html lang="de">"#));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_update_stored_locale() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app.post_set_locale(&serde_json::json!({ "locale": "es" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_message(response).await, "Missing auth token");
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_set_locale(&serde_json::json!({ "locale": "es" })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.json::<SetLocaleResponse>().await.unwrap(),
+        SetLocaleResponse { message: "Locale updated".to_owned() }
+    );
+    assert_eq!(stored_locale(&app, &email).await, Some(Locale::Es));
+
+    let response = app.post_set_locale(&serde_json::json!({ "locale": "xx" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(stored_locale(&app, &email).await, Some(Locale::Es));
+
+    let response = app.post_set_locale(&serde_json::json!({ "locale": null })).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(stored_locale(&app, &email).await, None);
+
+    let response = app.get_audit_log(&[]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let events = response.json::<AuditLogResponse>().await.unwrap().events;
+    let set_locale_events = events.iter().filter(|event| event.event_type == AuditEventType::SetLocale).count();
+    assert_eq!(set_locale_events, 2);
+
+    app.clean_up().await;
+}
@@ -3,8 +3,9 @@ use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
 
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::{
-    domain::email::Email, 
+    domain::{data_stores::UserStore, email::Email, password::{Password, MIN_PASSWORD_LENGTH}, user::{AccountStatus, User}}, 
     routes::TwoFactorAuthResponse, 
+    services::data_stores::{PasswordHashParams, PostgresUserStore},
     utils::constants::JWT_COOKIE_NAME, 
     ErrorResponse
 };
@@ -75,6 +76,7 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.deliver_emails().await, 1);
 
     let json_body = response
         .json::<TwoFactorAuthResponse>()
@@ -93,6 +95,16 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     assert_eq!(code_tuple.0.as_ref().expose_secret().clone(), json_body.login_attempt_id);
 
+    // The code is sent as a branded email with both an HTML and a plain-text part
+    let requests = app.email_server.received_requests().await.unwrap();
+    let email_body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let code = code_tuple.1.as_ref().expose_secret();
+    assert!(email_body["Subject"].as_str().unwrap().ends_with("login code"));
+    assert!(email_body["HtmlBody"].as_str().unwrap().starts_with("<!DOCTYPE html>"));
+    assert!(email_body["HtmlBody"].as_str().unwrap().contains(code.as_str()));
+    assert!(email_body["TextBody"].as_str().unwrap().contains(code.as_str()));
+    assert!(!email_body["TextBody"].as_str().unwrap().contains("<p>"));
+
     app.clean_up().await;
 }
 
@@ -196,6 +208,56 @@ async fn should_return_401_if_incorrect_credentials() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_403_if_account_suspended() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.user_store
+        .write()
+        .await
+        .update_status(&Email::parse(Secret::new(random_email.clone())).unwrap(), AccountStatus::Suspended)
+        .await
+        .expect("Failed to suspend account");
+
+    // -------------------------------------------
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME);
+
+    assert!(auth_cookie.is_none());
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Account suspended".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_422_if_malformed_credentials() {
     let mut app = TestApp::new().await;
@@ -233,4 +295,119 @@ async fn should_return_422_if_malformed_credentials() {
     }
 
     app.clean_up().await;
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn should_upgrade_outdated_password_hash_on_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
+
+    // A user whose hash was computed with weaker parameters than the app is configured with
+    let weak_params = PasswordHashParams { memory_cost: 4096, iterations: 1, parallelism: 1 };
+    let mut legacy_store = PostgresUserStore::new(app.pg_pool.clone(), weak_params);
+    let password = Password::parse_credential(Secret::new("password123".to_owned())).unwrap();
+    legacy_store
+        .add_user(User::new(email.clone(), password, false))
+        .await
+        .expect("Failed to add user");
+
+    let password_hash = app.user_store.read().await.get_user(&email).await.unwrap().password;
+    assert!(password_hash.as_ref().expose_secret().contains("m=4096,t=1,p=1"));
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let password_hash = app.user_store.read().await.get_user(&email).await.unwrap().password;
+    assert!(password_hash.as_ref().expose_secret().starts_with("$argon2id$v=19$m=15000,t=2,p=1$"));
+
+    // The upgraded hash still verifies
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_upgrade_imported_legacy_password_hashes_on_login() {
+    let mut app = TestApp::new().await;
+
+    let legacy_hashes = [
+        bcrypt::hash("password123", 4).unwrap(),
+        // passlib pbkdf2_sha256, 1000 rounds
+        "$pbkdf2-sha256$1000$....AXNhbHRzYWx0MTIzNA$Tb3MMmnM8ChA16.iuLjuXtaRdBLsoJrd3n4kroP2eD4".to_owned(),
+    ];
+
+    for legacy_hash in legacy_hashes {
+        let random_email = get_random_email();
+
+        sqlx::query("insert into users (email, password_hash, requires_2fa) values ($1, $2, false)")
+            .bind(&random_email)
+            .bind(&legacy_hash)
+            .execute(&app.pg_pool)
+            .await
+            .expect("Failed to import user");
+
+        let login_body = serde_json::json!({
+            "email": random_email,
+            "password": "wrong-password",
+        });
+
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 401, "Failed for hash: {}", legacy_hash);
+
+        let login_body = serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        });
+
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 200, "Failed for hash: {}", legacy_hash);
+
+        let email = Email::parse(Secret::new(random_email)).unwrap();
+        let password_hash = app.user_store.read().await.get_user(&email).await.unwrap().password;
+        assert!(password_hash.as_ref().expose_secret().starts_with("$argon2id$"));
+
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_if_password_has_shortest_length_policy_allows() {
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.passwords.policy.min_length = MIN_PASSWORD_LENGTH;
+        settings.passwords.policy.min_strength = 0;
+    })
+    .await;
+
+    let random_email = get_random_email();
+    let password = "a".repeat(MIN_PASSWORD_LENGTH);
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": password,
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": password,
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
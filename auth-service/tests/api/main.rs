@@ -5,4 +5,18 @@ mod root;
 mod signup;
 mod verify_2fa;
 mod verify_token;
-mod delete_account;
\ No newline at end of file
+mod delete_account;
+mod audit_log;
+mod webhooks;
+mod invitations;
+mod organisations;
+mod locale;
+mod email_outbox;
+mod dev_inbox;
+mod cors;
+mod health;
+mod metrics;
+mod request_id;
+mod shutdown;
+mod migrations;
+
@@ -0,0 +1,81 @@
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_metrics_in_prometheus_format() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_metrics().await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get("content-type").and_then(|value| value.to_str().ok()),
+        Some("text/plain; version=0.0.4")
+    );
+
+    let body = response.text().await.expect("Failed to read metrics");
+    assert!(body.contains("# TYPE auth_signups_total counter"));
+    assert!(body.contains("auth_signups_total 0"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_count_requests_per_route() {
+    let mut app = TestApp::new().await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let metrics = app.metrics().await;
+    assert!(metrics.contains(r#"http_requests_total{method="POST",route="/signup",status="201"} 1"#));
+    assert!(metrics.contains(r#"http_requests_total{method="POST",route="/signup",status="409"} 1"#));
+    assert!(metrics.contains(r#"http_request_duration_seconds_count{method="POST",route="/signup"} 2"#));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_count_auth_events() {
+    let mut app = TestApp::new().await;
+
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "wrongpassword",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let metrics = app.metrics().await;
+    assert!(metrics.contains("auth_signups_total 1"));
+    assert!(metrics.contains(r#"auth_logins_total{outcome="failure"} 1"#));
+    assert!(metrics.contains(r#"auth_logins_total{outcome="success"} 1"#));
+    assert!(metrics.contains("auth_tokens_banned_total 1"));
+
+    app.clean_up().await;
+}
@@ -0,0 +1,42 @@
+use sqlx::{migrate::Migration, Executor};
+
+use crate::helpers::TestDatabase;
+
+const CASE_INSENSITIVE_USER_EMAIL: i64 = 20261019100200;
+
+fn up_migrations() -> impl Iterator<Item = &'static Migration> {
+    static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!();
+    MIGRATOR.iter().filter(|migration| migration.migration_type.is_up_migration())
+}
+
+#[tokio::test]
+async fn should_stop_case_insensitive_email_migration_on_case_variant_duplicates() {
+    let database = TestDatabase::new().await;
+
+    for migration in up_migrations().filter(|migration| migration.version < CASE_INSENSITIVE_USER_EMAIL) {
+        database.pg_pool.execute(&*migration.sql).await.expect("Failed to run earlier migration");
+    }
+
+    sqlx::query("INSERT INTO users (email, password_hash) VALUES ('Bob@Example.com', 'hash'), ('bob@example.com', 'hash'), ('alice@example.com', 'hash')")
+        .execute(&database.pg_pool)
+        .await
+        .expect("Failed to seed users");
+
+    let migration = up_migrations()
+        .find(|migration| migration.version == CASE_INSENSITIVE_USER_EMAIL)
+        .expect("Missing migration");
+
+    let error = database.pg_pool.execute(&*migration.sql).await.expect_err("Migration should fail");
+    assert!(error.to_string().contains("Bob@Example.com, bob@example.com"), "{}", error);
+    assert!(!error.to_string().contains("alice@example.com"), "{}", error);
+
+    // The documented fix-up: keep one account per address
+    sqlx::query("DELETE FROM users WHERE email = 'Bob@Example.com'")
+        .execute(&database.pg_pool)
+        .await
+        .expect("Failed to delete duplicate");
+
+    database.pg_pool.execute(&*migration.sql).await.expect("Migration should succeed");
+
+    database.clean_up().await;
+}
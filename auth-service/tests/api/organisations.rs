@@ -0,0 +1,201 @@
+use auth_service::{
+    domain::{email::Email, organisation::{OrgRole, Organisation}, user::UserRole},
+    routes::SetMembershipResponse,
+    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    ErrorResponse,
+};
+use secrecy::Secret;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn login(app: &TestApp, email: &str, organisation: Option<&str>) -> reqwest::Response {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "organisation": organisation,
+    });
+
+    app.post_login(&login_body).await
+}
+
+async fn signup_admin_and_login(app: &TestApp) -> String {
+    let admin_email = get_random_email();
+    signup(app, &admin_email).await;
+
+    app.user_store
+        .write()
+        .await
+        .update_role(&Email::parse(Secret::new(admin_email.clone())).unwrap(), UserRole::Admin)
+        .await
+        .expect("Failed to promote user to admin");
+
+    let response = login(app, &admin_email, None).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    admin_email
+}
+
+async fn create_organisation(app: &TestApp, slug: &str) -> Organisation {
+    let response = app.post_create_organisation(&serde_json::json!({ "slug": slug, "name": slug })).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<Organisation>()
+        .await
+        .expect("Could not deserialize response body to Organisation")
+}
+
+async fn set_membership(app: &TestApp, organisation: &str, email: &str, role: &str) -> reqwest::Response {
+    let body = serde_json::json!({
+        "organisation": organisation,
+        "email": email,
+        "role": role,
+    });
+
+    app.post_set_membership(&body).await
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_only_let_admins_create_organisations() {
+    let mut app = TestApp::new().await;
+
+    let user_email = get_random_email();
+    signup(&app, &user_email).await;
+    login(&app, &user_email, None).await;
+
+    let body = serde_json::json!({ "slug": "acme", "name": "Acme" });
+    let response = app.post_create_organisation(&body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    signup_admin_and_login(&app).await;
+
+    let organisation = create_organisation(&app, "Acme").await;
+    assert_eq!(organisation.slug, "acme");
+
+    let response = app.post_create_organisation(&body).await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(error_message(response).await, "Organisation already exists");
+
+    let response = app.post_create_organisation(&serde_json::json!({ "slug": "not a slug", "name": "Acme" })).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_scope_login_to_organisation() {
+    let mut app = TestApp::new().await;
+
+    signup_admin_and_login(&app).await;
+    let acme = create_organisation(&app, "acme").await;
+    let globex = create_organisation(&app, "globex").await;
+
+    let user_email = get_random_email();
+    signup(&app, &user_email).await;
+
+    let response = set_membership(&app, "acme", &user_email, "member").await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.json::<SetMembershipResponse>().await.unwrap(),
+        SetMembershipResponse { message: "Membership updated".to_owned() }
+    );
+    let response = set_membership(&app, "globex", &user_email, "admin").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Members of several organisations have to pick one
+    let response = login(&app, &user_email, None).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_message(response).await, "Organisation required");
+
+    let response = login(&app, &user_email, Some("initech")).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Not a member of this organisation");
+
+    for (slug, organisation, role) in [("acme", &acme, OrgRole::Member), ("globex", &globex, OrgRole::Admin)] {
+        let response = login(&app, &user_email, Some(slug)).await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        let auth_cookie = response
+            .cookies()
+            .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+            .expect("No auth cookie found");
+
+        let claims = validate_token(
+            &Secret::new(auth_cookie.value().to_owned()),
+            &app.settings.auth,
+            app.token_store.clone(),
+            app.user_store.clone(),
+        )
+        .await
+        .expect("Failed to validate token");
+
+        let org = claims.org.expect("No organisation claim");
+        assert_eq!(org.id, organisation.id);
+        assert_eq!(org.role, role);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_let_organisation_admins_manage_members() {
+    let mut app = TestApp::new().await;
+
+    signup_admin_and_login(&app).await;
+    create_organisation(&app, "acme").await;
+    create_organisation(&app, "globex").await;
+
+    let org_admin_email = get_random_email();
+    signup(&app, &org_admin_email).await;
+    let response = set_membership(&app, "acme", &org_admin_email, "admin").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let member_email = get_random_email();
+    signup(&app, &member_email).await;
+
+    // A single membership is picked automatically
+    let response = login(&app, &org_admin_email, None).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = set_membership(&app, "acme", &member_email, "member").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = set_membership(&app, "globex", &member_email, "member").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = set_membership(&app, "acme", &get_random_email(), "member").await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(error_message(response).await, "User not found");
+
+    let response = set_membership(&app, "initech", &member_email, "member").await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(error_message(response).await, "Organisation not found");
+
+    // Plain members may not manage anyone
+    let response = login(&app, &member_email, None).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = set_membership(&app, "acme", &org_admin_email, "member").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,81 @@
+use auth_service::{utils::constants::REQUEST_ID_HEADER, ErrorResponse};
+use uuid::Uuid;
+
+use crate::helpers::TestApp;
+
+async fn post_login_with_request_id(app: &TestApp, request_id: &str) -> reqwest::Response {
+    let login_body = serde_json::json!({
+        "email": "not-an-email",
+        "password": "password123",
+    });
+
+    app.http_client
+        .post(format!("{}/login", &app.address))
+        .header(REQUEST_ID_HEADER, request_id)
+        .json(&login_body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+fn response_request_id(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .expect("No X-Request-Id header in response")
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_generate_request_id() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_health_live().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let request_id = response_request_id(&response);
+    assert!(Uuid::parse_str(&request_id).is_ok());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_echo_incoming_request_id_in_header_and_error() {
+    let mut app = TestApp::new().await;
+
+    let response = post_login_with_request_id(&app, "support-ticket-1234").await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(response_request_id(&response), "support-ticket-1234");
+
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.request_id.as_deref(), Some("support-ticket-1234"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_replace_invalid_request_id() {
+    let mut app = TestApp::new().await;
+
+    let too_long = "a".repeat(129);
+    for invalid in ["<script>alert(1)</script>", too_long.as_str()] {
+        let response = post_login_with_request_id(&app, invalid).await;
+        assert_eq!(response.status().as_u16(), 400);
+
+        let request_id = response_request_id(&response);
+        assert!(Uuid::parse_str(&request_id).is_ok());
+
+        let body = response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse");
+        assert_eq!(body.request_id, Some(request_id));
+    }
+
+    app.clean_up().await;
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+// A signup whose body is only half sent, so the request stays in flight until `finish_request`
+async fn start_request(app: &TestApp) -> (TcpStream, String) {
+    let mut body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    })
+    .to_string();
+
+    let address = app.address.trim_start_matches("http://");
+    let mut stream = TcpStream::connect(address).await.expect("Failed to connect");
+    let head = format!(
+        "POST /signup HTTP/1.1\r\nhost: {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n",
+        address,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+
+    let rest = body.split_off(body.len() / 2);
+    stream.write_all(body.as_bytes()).await.unwrap();
+    // Let the server pick the request up before anything else happens
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    (stream, rest)
+}
+
+async fn finish_request(mut stream: TcpStream, rest: String) -> String {
+    stream.write_all(rest.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.expect("Failed to read response");
+    response
+}
+
+#[tokio::test]
+async fn should_finish_in_flight_requests_on_shutdown() {
+    let mut app = TestApp::new().await;
+
+    let (stream, rest) = start_request(&app).await;
+    app.shutdown.shutdown();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!app.server.is_finished());
+
+    let response = finish_request(stream, rest).await;
+    assert!(response.starts_with("HTTP/1.1 201"), "{}", response);
+
+    tokio::time::timeout(Duration::from_secs(5), &mut app.server)
+        .await
+        .expect("Server did not stop after draining")
+        .unwrap()
+        .unwrap();
+
+    // No longer accepting connections
+    let result = app.http_client.get(format!("{}/health/live", &app.address)).send().await;
+    assert!(result.is_err());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_stop_waiting_for_requests_after_drain_timeout() {
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.application.shutdown_timeout = Duration::from_millis(200);
+    })
+    .await;
+
+    let (_stream, _rest) = start_request(&app).await;
+    app.shutdown.shutdown();
+
+    tokio::time::timeout(Duration::from_secs(2), &mut app.server)
+        .await
+        .expect("Server did not stop after the drain timeout")
+        .unwrap()
+        .unwrap();
+
+    app.clean_up().await;
+}
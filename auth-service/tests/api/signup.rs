@@ -1,5 +1,5 @@
-use crate::helpers::{TestApp, get_random_email};
-use auth_service::{routes::SignupResponse, ErrorResponse};
+use crate::helpers::{TestApp, get_random_email, BREACHED_PASSWORD};
+use auth_service::{routes::SignupResponse, utils::constants::test, ErrorResponse};
 
 #[tokio::test]
 async fn should_return_201_if_valid_input() {
@@ -70,6 +70,67 @@ async fn should_return_400_if_invalid_input() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_400_with_violated_rule_if_password_rejected_by_policy() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let local_part = random_email.split('@').next().unwrap();
+
+    let test_cases = [
+        ("short", "Password must be at least 8 characters long".to_owned()),
+        ("12345678", "Password is too weak (strength 0 of 4, at least 2 required)".to_owned()),
+        (local_part, "Password must not contain your email address or common words".to_owned()),
+    ];
+
+    for (password, error) in test_cases {
+        let signup_body = serde_json::json!({
+            "email": random_email,
+            "password": password,
+            "requires2FA": false
+        });
+
+        let response = app.post_signup(&signup_body).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for password: {}", password);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            error
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_password_breached() {
+    let mut app = TestApp::new().await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": BREACHED_PASSWORD,
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Password has appeared in a data breach".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_409_if_email_already_exists() {
     let mut app = TestApp::new().await;
@@ -100,6 +161,77 @@ async fn should_return_409_if_email_already_exists() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_403_if_email_domain_rejected() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        (format!("user@{}", test::email_domains::DENIED), "Email domain is blocked"),
+        (format!("user@team.{}", test::email_domains::DENIED), "Email domain is blocked"),
+        (
+            format!("user@{}", test::email_domains::DISPOSABLE.to_uppercase()),
+            "Disposable email addresses are not allowed",
+        ),
+    ];
+
+    for (email, message) in test_cases {
+        let input = serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        });
+
+        let response = app.post_signup(&input).await;
+        assert_eq!(response.status().as_u16(), 403, "Failed for input: {:?}", input);
+
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            message.to_owned()
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_treat_emails_case_insensitively() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let input = serde_json::json!({
+        "email": random_email.to_uppercase(),
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&input).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let input = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&input).await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
     let mut app = TestApp::new().await;
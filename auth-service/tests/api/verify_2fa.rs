@@ -2,7 +2,7 @@ use secrecy::{ExposeSecret, Secret};
 
 use auth_service::{
     domain::{data_stores::{LoginAttemptId, TwoFACode}, 
-    email::Email}, 
+    email::Email, user::AccountStatus}, 
     routes::TwoFactorAuthResponse, 
     utils::constants::JWT_COOKIE_NAME, 
     ErrorResponse
@@ -42,6 +42,7 @@ async fn should_return_200_if_correct_code() {
 
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.deliver_emails().await, 1);
 
     let response_body = response
         .json::<TwoFactorAuthResponse>()
@@ -169,6 +170,7 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.deliver_emails().await, 1);
 
     let response_body = response
         .json::<TwoFactorAuthResponse>()
@@ -274,6 +276,7 @@ async fn should_return_401_if_old_code() {
 
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.deliver_emails().await, 1);
 
     let response_body = response
         .json::<TwoFactorAuthResponse>()
@@ -299,6 +302,7 @@ async fn should_return_401_if_old_code() {
 
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.deliver_emails().await, 1);
 
     // 2FA attempt with old login_attempt_id and code
 
@@ -344,6 +348,7 @@ async fn should_return_401_if_same_code_twice() {
 
     let response = app.post_login(&login_body).await;
     assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.deliver_emails().await, 1);
 
     let response_body = response
         .json::<TwoFactorAuthResponse>()
@@ -387,6 +392,86 @@ async fn should_return_401_if_same_code_twice() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_403_if_account_suspended_before_verification() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.deliver_emails().await, 1);
+
+    let response_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
+
+    let code_tuple = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&email)
+        .await
+        .unwrap();
+
+    app.user_store
+        .write()
+        .await
+        .update_status(&email, AccountStatus::Suspended)
+        .await
+        .expect("Failed to suspend account");
+
+    let request_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": response_body.login_attempt_id,
+        "2FACode": code_tuple.1.as_ref().expose_secret(),
+    });
+
+    let response = app.post_verify_2fa(&request_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME);
+
+    assert!(auth_cookie.is_none());
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Account suspended".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
     let mut app = TestApp::new().await;
@@ -1,4 +1,9 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    domain::{email::Email, user::AccountStatus},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::Secret;
 
 use crate::helpers::{TestApp, get_random_email};
 
@@ -133,6 +138,65 @@ async fn should_return_401_if_banned_token() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_403_if_account_suspended_after_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let verify_token_body = serde_json::json!({
+        "token": auth_cookie.value(),
+    });
+
+    let response = app.post_verify_token(&verify_token_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // ---------------------------------------------------------
+
+    app.user_store
+        .write()
+        .await
+        .update_status(&Email::parse(Secret::new(random_email)).unwrap(), AccountStatus::Suspended)
+        .await
+        .expect("Failed to suspend account");
+
+    let response = app.post_verify_token(&verify_token_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Account suspended".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
     let mut app = TestApp::new().await;
@@ -0,0 +1,196 @@
+use auth_service::{
+    domain::{
+        email::Email,
+        security_event::{SecurityEvent, SecurityEventType},
+        user::AccountStatus,
+    },
+    services::webhook_dispatcher::{
+        sign_payload, WEBHOOK_ID_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER,
+    },
+    utils::constants::{test, JWT_COOKIE_NAME},
+};
+use secrecy::Secret;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn header<'a>(request: &'a wiremock::Request, name: &str) -> &'a str {
+    request
+        .headers
+        .get(name)
+        .unwrap_or_else(|| panic!("Missing {} header", name))
+        .to_str()
+        .unwrap()
+}
+
+fn event_types(requests: &[wiremock::Request]) -> Vec<SecurityEventType> {
+    let mut event_types: Vec<_> = requests
+        .iter()
+        .map(|r| r.body_json::<SecurityEvent>().unwrap().event_type)
+        .collect();
+    event_types.sort_by_key(|t| t.as_ref().to_owned());
+    event_types
+}
+
+#[tokio::test]
+async fn should_deliver_signed_event_on_signup() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/webhooks"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.webhook_server)
+        .await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let requests = app.wait_for_webhooks(1).await;
+    let request = &requests[0];
+
+    let event = request.body_json::<SecurityEvent>().unwrap();
+    assert_eq!(event.event_type, SecurityEventType::SignedUp);
+    assert_eq!(event.email, random_email);
+    assert_eq!(header(request, WEBHOOK_ID_HEADER), event.id.to_string());
+
+    let timestamp: i64 = header(request, WEBHOOK_TIMESTAMP_HEADER).parse().unwrap();
+    let expected_signature = sign_payload(&Secret::new(test::webhooks::SECRET.to_owned()), timestamp, &request.body);
+    assert_eq!(header(request, WEBHOOK_SIGNATURE_HEADER), expected_signature);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_deliver_login_and_account_deletion_events() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/webhooks"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.webhook_server)
+        .await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let response = app.post_delete_account(&serde_json::json!({ "token": auth_cookie.value() })).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let requests = app.wait_for_webhooks(3).await;
+
+    assert_eq!(
+        event_types(&requests),
+        vec![SecurityEventType::AccountDeleted, SecurityEventType::LoggedIn, SecurityEventType::SignedUp]
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_deliver_login_rejected_event_if_account_suspended() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/webhooks"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.webhook_server)
+        .await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.user_store
+        .write()
+        .await
+        .update_status(&Email::parse(Secret::new(random_email.clone())).unwrap(), AccountStatus::Suspended)
+        .await
+        .expect("Failed to suspend user");
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let requests = app.wait_for_webhooks(2).await;
+
+    assert_eq!(
+        event_types(&requests),
+        vec![SecurityEventType::LoginRejectedSuspended, SecurityEventType::SignedUp]
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_retry_failed_deliveries() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/webhooks"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&app.webhook_server)
+        .await;
+
+    Mock::given(path("/webhooks"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.webhook_server)
+        .await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let requests = app.wait_for_webhooks(3).await;
+
+    // Every attempt carries the same event id so receivers can deduplicate
+    let event_id = header(&requests[0], WEBHOOK_ID_HEADER);
+    assert!(requests.iter().all(|r| header(r, WEBHOOK_ID_HEADER) == event_id));
+
+    app.clean_up().await;
+}